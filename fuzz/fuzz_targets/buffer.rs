@@ -0,0 +1,41 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use mc_packet_protocol::buffer::{BufferState, MinecraftPacketBuffer};
+use mc_packet_protocol::encryption::Codec;
+
+/// Decompressed packets are bounded by the buffer, anything bigger means the bound was bypassed.
+const MAX_PACKET_LENGTH: usize = 8388608;
+
+#[derive(Arbitrary, Debug)]
+struct BufferInput {
+    decompressing: bool,
+    shared_secret: Option<[u8; 16]>,
+    chunks: Vec<Vec<u8>>,
+}
+
+fuzz_target!(|input: BufferInput| {
+    let mut buffer = MinecraftPacketBuffer::new();
+    if input.decompressing {
+        buffer.enable_decompression();
+    }
+    if let Some(shared_secret) = input.shared_secret {
+        let (read, _) = Codec::new(&shared_secret).expect("A 16 byte secret is always valid.");
+        buffer.enable_decryption(read);
+    }
+
+    for chunk in input.chunks {
+        buffer.inner_buf().extend_from_slice(&chunk);
+        loop {
+            match buffer.poll() {
+                BufferState::PacketReady => match buffer.packet_reader() {
+                    Ok(packet) => assert!(packet.get_ref().len() <= MAX_PACKET_LENGTH),
+                    Err(_) => return,
+                },
+                BufferState::Waiting => break,
+                BufferState::Error(_) => return,
+            }
+        }
+    }
+});
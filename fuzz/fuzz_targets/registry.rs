@@ -0,0 +1,73 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use mc_packet_protocol::protocol_version::{MCProtocol, MapDecodable};
+use mc_packet_protocol::registry::{configuration, handshake, login, play, status, LazyHandle, RegistryBase};
+
+#[derive(Arbitrary, Debug)]
+struct RegistryInput {
+    registry: u8,
+    protocol: u8,
+    packet: Vec<u8>,
+}
+
+/// Decodes every packet it is handed, decode errors are expected but panics are not.
+struct DecodingHandler;
+
+macro_rules! decoding_handler {
+    ($($registry:path),*) => {
+        $(
+            #[async_trait::async_trait]
+            impl $registry for DecodingHandler {
+                async fn handle_unknown(&mut self, _packet_cursor: std::io::Cursor<Vec<u8>>) -> anyhow::Result<()> {
+                    Ok(())
+                }
+
+                async fn handle_default<T: MapDecodable, H: LazyHandle<T> + Send>(
+                    &mut self,
+                    handle: H,
+                ) -> anyhow::Result<()> {
+                    let _ = handle.decode_type();
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+decoding_handler!(
+    handshake::server_bound::RegistryHandler,
+    status::server_bound::RegistryHandler,
+    status::client_bound::RegistryHandler,
+    login::server_bound::RegistryHandler,
+    login::client_bound::RegistryHandler,
+    configuration::server_bound::RegistryHandler,
+    configuration::client_bound::RegistryHandler,
+    play::server_bound::RegistryHandler,
+    play::client_bound::RegistryHandler
+);
+
+async fn handle(input: RegistryInput) -> anyhow::Result<()> {
+    let protocol = MCProtocol::VARIANTS[input.protocol as usize % MCProtocol::VARIANTS.len()];
+    let cursor = std::io::Cursor::new(input.packet);
+    let handler = &mut DecodingHandler;
+    match input.registry % 9 {
+        0 => handshake::server_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        1 => status::server_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        2 => status::client_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        3 => login::server_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        4 => login::client_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        5 => configuration::server_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        6 => configuration::client_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        7 => play::server_bound::Registry::handle_packet(handler, cursor, protocol).await,
+        _ => play::client_bound::Registry::handle_packet(handler, cursor, protocol).await,
+    }
+}
+
+fuzz_target!(|input: RegistryInput| {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("Failed to build runtime.");
+    let _ = runtime.block_on(handle(input));
+});
@@ -0,0 +1,565 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Derives `MapDecodable`, `MapEncodable`, `AsyncMapEncodable`, `WritablePacket` and
+/// `IdentifiedPacket` for a packet struct.
+///
+/// ```ignore
+/// #[derive(Debug, Packet)]
+/// #[packet(id = 0x12, versions = "1.17.1..=1.18")]
+/// pub struct DeclareCommands {
+///     pub nodes: Prefixed<VarInt, Vec<Node>>,
+///     pub root_index: VarInt,
+/// }
+/// ```
+///
+/// A packet may list several `#[packet(...)]` attributes when its id changes between versions,
+/// omitting `versions` matches every protocol.
+///
+/// Fields may be gated with `#[field(since = "1.19")]`, `#[field(until = "1.19")]` (the first
+/// version without the field) or `#[field(versions = "1.17.1..=1.18")]`. Gated fields are skipped
+/// when reading, writing and sizing for other protocols, and decode as `Default::default()` or the
+/// expression given with `#[field(default = "...")]`.
+///
+/// Fields are read and written in declaration order, unless a
+/// `#[layout(versions = "1.20.2..", order = "entity_id, is_hardcore, ...")]` attribute lists every
+/// field in the order used by those versions.
+///
+/// Fields marked `#[field(mapped)]` are read and written through `MapDecodable` and
+/// `MapEncodable` with the packet's protocol, for types whose encoding changes between versions.
+#[proc_macro_derive(Packet, attributes(packet, field, layout))]
+pub fn derive_packet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_packet(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// A version range, either from a `versions = "..."` or `since`/`until` predicate.
+struct VersionRange {
+    lower: Option<(String, bool)>,
+    upper: Option<(String, bool)>,
+}
+
+impl VersionRange {
+    fn parse(spec: &str, span: Span) -> syn::Result<Self> {
+        let spec = spec.trim();
+        if spec == "*" {
+            return Ok(Self { lower: None, upper: None });
+        }
+        let range = if let Some((lower, upper)) = spec.split_once("..=") {
+            Self {
+                lower: non_empty(lower).map(|lower| (lower, true)),
+                upper: Some((non_empty(upper).ok_or_else(|| syn::Error::new(span, "Expected an upper version after `..=`."))?, true)),
+            }
+        } else if let Some((lower, upper)) = spec.split_once("..") {
+            Self {
+                lower: non_empty(lower).map(|lower| (lower, true)),
+                upper: non_empty(upper).map(|upper| (upper, false)),
+            }
+        } else {
+            Self {
+                lower: Some((spec.to_string(), true)),
+                upper: Some((spec.to_string(), true)),
+            }
+        };
+        for (version, _) in range.lower.iter().chain(range.upper.iter()) {
+            validate_version(version, span)?;
+        }
+        Ok(range)
+    }
+
+    /// `since` is inclusive, `until` is the first version without the field.
+    fn between(since: Option<&syn::LitStr>, until: Option<&syn::LitStr>) -> syn::Result<Self> {
+        for version in since.iter().chain(until.iter()) {
+            validate_version(&version.value(), version.span())?;
+        }
+        Ok(Self {
+            lower: since.map(|version| (version.value().trim().to_string(), true)),
+            upper: until.map(|version| (version.value().trim().to_string(), false)),
+        })
+    }
+
+    fn predicate(&self, krate: &TokenStream2) -> TokenStream2 {
+        let mut checks = Vec::new();
+        if let Some((version, _)) = &self.lower {
+            let version = protocol_ident(version);
+            checks.push(quote!(protocol >= #krate::protocol_version::MCProtocol::#version));
+        }
+        if let Some((version, inclusive)) = &self.upper {
+            let version = protocol_ident(version);
+            if *inclusive {
+                checks.push(quote!(protocol <= #krate::protocol_version::MCProtocol::#version));
+            } else {
+                checks.push(quote!(protocol < #krate::protocol_version::MCProtocol::#version));
+            }
+        }
+        if checks.is_empty() {
+            quote!(true)
+        } else {
+            quote!(!matches!(protocol, #krate::protocol_version::MCProtocol::Illegal(_)) #(&& #checks)*)
+        }
+    }
+}
+
+fn non_empty(version: &str) -> Option<String> {
+    let version = version.trim();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_string())
+    }
+}
+
+fn validate_version(version: &str, span: Span) -> syn::Result<()> {
+    if version.trim().split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())) {
+        Ok(())
+    } else {
+        Err(syn::Error::new(span, format!("`{}` is not a version, expected something like \"1.17.1\".", version)))
+    }
+}
+
+/// `1.17.1` => `V1_17_1`, matching the identifiers declared in `protocol_version`.
+fn protocol_ident(version: &str) -> syn::Ident {
+    format_ident!("V{}", version.trim().replace('.', "_"))
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, character) in name.chars().enumerate() {
+        if character.is_uppercase() {
+            if index != 0 {
+                snake.push('_');
+            }
+            snake.extend(character.to_lowercase());
+        } else {
+            snake.push(character);
+        }
+    }
+    snake
+}
+
+struct PacketMapping {
+    id: Lit,
+    versions: VersionRange,
+}
+
+fn parse_packet_attributes(input: &DeriveInput) -> syn::Result<Vec<PacketMapping>> {
+    let mut mappings = Vec::new();
+    for attribute in input.attrs.iter().filter(|attribute| attribute.path.is_ident("packet")) {
+        let list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "Expected `#[packet(id = ..., versions = \"...\")]`.")),
+        };
+        let mut id = None;
+        let mut versions = None;
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("id") => match &name_value.lit {
+                    Lit::Int(_) => id = Some(name_value.lit.clone()),
+                    lit => return Err(syn::Error::new(lit.span(), "Packet ids must be integer literals.")),
+                },
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("versions") => match &name_value.lit {
+                    Lit::Str(spec) => versions = Some(VersionRange::parse(&spec.value(), spec.span())?),
+                    lit => return Err(syn::Error::new(lit.span(), "Versions must be a string such as \"1.17.1..=1.18\".")),
+                },
+                nested => return Err(syn::Error::new(nested.span(), "Unknown packet attribute, expected `id` or `versions`.")),
+            }
+        }
+        mappings.push(PacketMapping {
+            id: id.ok_or_else(|| syn::Error::new(list.span(), "Missing packet `id`."))?,
+            versions: versions.unwrap_or(VersionRange { lower: None, upper: None }),
+        });
+    }
+    if mappings.is_empty() {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "Packets require at least one `#[packet(id = ...)]` attribute.",
+        ));
+    }
+    Ok(mappings)
+}
+
+/// A field order used by a range of versions instead of the declaration order.
+struct PacketLayout {
+    versions: VersionRange,
+    order: Vec<usize>,
+}
+
+fn parse_layout_attributes(input: &DeriveInput, fields: &[PacketField]) -> syn::Result<Vec<PacketLayout>> {
+    let mut layouts = Vec::new();
+    for attribute in input.attrs.iter().filter(|attribute| attribute.path.is_ident("layout")) {
+        let list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "Expected `#[layout(versions = \"...\", order = \"...\")]`.")),
+        };
+        let mut versions = None;
+        let mut order = None;
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("versions") => {
+                    let spec = version_string(name_value)?;
+                    versions = Some(VersionRange::parse(&spec.value(), spec.span())?);
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("order") => match &name_value.lit {
+                    Lit::Str(names) => order = Some(names.clone()),
+                    lit => return Err(syn::Error::new(lit.span(), "`order` must be a string of comma separated fields.")),
+                },
+                nested => return Err(syn::Error::new(nested.span(), "Unknown layout attribute, expected `versions` or `order`.")),
+            }
+        }
+        let versions = versions.ok_or_else(|| syn::Error::new(list.span(), "Missing layout `versions`."))?;
+        let names = order.ok_or_else(|| syn::Error::new(list.span(), "Missing layout `order`."))?;
+
+        let mut order = Vec::new();
+        for name in names.value().split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let index = fields
+                .iter()
+                .position(|field| field.ident == name)
+                .ok_or_else(|| syn::Error::new(names.span(), format!("Unknown field `{}` in layout.", name)))?;
+            if order.contains(&index) {
+                return Err(syn::Error::new(names.span(), format!("Field `{}` appears twice in layout.", name)));
+            }
+            order.push(index);
+        }
+        if order.len() != fields.len() {
+            return Err(syn::Error::new(names.span(), "Layouts must list every field of the packet."));
+        }
+        layouts.push(PacketLayout { versions, order });
+    }
+    Ok(layouts)
+}
+
+struct PacketField {
+    ident: syn::Ident,
+    ty: syn::Type,
+    versions: Option<VersionRange>,
+    default: Option<syn::Expr>,
+    mapped: bool,
+}
+
+fn version_string(name_value: &syn::MetaNameValue) -> syn::Result<syn::LitStr> {
+    match &name_value.lit {
+        Lit::Str(version) => Ok(version.clone()),
+        lit => Err(syn::Error::new(lit.span(), "Expected a version string such as \"1.19\".")),
+    }
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<PacketField> {
+    let mut since = None;
+    let mut until = None;
+    let mut versions = None;
+    let mut default = None;
+    let mut mapped = false;
+    for attribute in field.attrs.iter().filter(|attribute| attribute.path.is_ident("field")) {
+        let list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "Expected `#[field(since = \"...\")]`.")),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("since") => {
+                    since = Some(version_string(name_value)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("until") => {
+                    until = Some(version_string(name_value)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("versions") => {
+                    let spec = version_string(name_value)?;
+                    versions = Some(VersionRange::parse(&spec.value(), spec.span())?);
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("default") => match &name_value.lit {
+                    Lit::Str(expr) => default = Some(expr.parse::<syn::Expr>()?),
+                    lit => return Err(syn::Error::new(lit.span(), "`default` must be an expression string.")),
+                },
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("mapped") => mapped = true,
+                nested => {
+                    return Err(syn::Error::new(
+                        nested.span(),
+                        "Unknown field attribute, expected `since`, `until`, `versions`, `default` or `mapped`.",
+                    ))
+                }
+            }
+        }
+    }
+
+    if versions.is_some() && (since.is_some() || until.is_some()) {
+        return Err(syn::Error::new(field.span(), "Use either `versions` or `since`/`until` on a field, not both."));
+    }
+    let versions = match (versions, since, until) {
+        (Some(versions), _, _) => Some(versions),
+        (None, None, None) => None,
+        (None, since, until) => Some(VersionRange::between(since.as_ref(), until.as_ref())?),
+    };
+    if default.is_some() && versions.is_none() {
+        return Err(syn::Error::new(field.span(), "`default` only applies to fields gated by version."));
+    }
+
+    Ok(PacketField {
+        ident: field.ident.clone().expect("Named fields always have an ident."),
+        ty: field.ty.clone(),
+        versions,
+        default,
+        mapped,
+    })
+}
+
+fn expand_packet(input: DeriveInput) -> syn::Result<TokenStream2> {
+    // Generated code refers to this crate as `crate` from inside the protocol crate itself.
+    let internal = std::env::var("CARGO_CRATE_NAME").is_ok_and(|name| name == "mc_packet_protocol");
+    let krate = if internal { quote!(crate) } else { quote!(::mc_packet_protocol) };
+
+    let name = &input.ident;
+    let mappings = parse_packet_attributes(&input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().map(parse_field).collect::<syn::Result<Vec<_>>>()?,
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(fields) => {
+                return Err(syn::Error::new(fields.span(), "Packets must use named fields."));
+            }
+        },
+        _ => return Err(syn::Error::new(input.ident.span(), "Packets must be structs.")),
+    };
+
+    let layouts = parse_layout_attributes(&input, &fields)?;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let id_checks = mappings.iter().map(|mapping| {
+        let predicate = mapping.versions.predicate(&krate);
+        let id = &mapping.id;
+        quote! {
+            if #predicate {
+                return Some(minecraft_data_types::nums::VarInt::from(#id));
+            }
+        }
+    });
+
+    let field_predicate = |field: &PacketField| field.versions.as_ref().map(|versions| versions.predicate(&krate));
+
+    let decodes = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let decode = if field.mapped {
+            quote!(<#ty as #krate::protocol_version::MapDecodable>::decode_mapped(protocol, _reader))
+        } else {
+            quote!(<#ty as minecraft_data_types::encoder::Decodable>::decode(_reader))
+        };
+        let decode = quote! {
+            anyhow::Context::context(
+                #decode,
+                format!(
+                    "Failed to decode field {} of packet {} for protocol {:?}.",
+                    stringify!(#ident),
+                    stringify!(#name),
+                    protocol
+                ),
+            )?
+        };
+        let default = match &field.default {
+            Some(default) => quote!(#default),
+            None => quote!(::core::default::Default::default()),
+        };
+        match field_predicate(field) {
+            Some(predicate) => quote! {
+                let #ident: #ty = if #predicate { #decode } else { #default };
+            },
+            None => quote!(let #ident: #ty = #decode;),
+        }
+    }).collect::<Vec<_>>();
+    let field_idents: Vec<&syn::Ident> = fields.iter().map(|field| &field.ident).collect();
+
+    let gate = |field: &PacketField, body: TokenStream2| match field_predicate(field) {
+        Some(predicate) => quote!(if #predicate { #body }),
+        None => body,
+    };
+
+    let encodes = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let encode = if field.mapped {
+            quote!(#krate::protocol_version::MapEncodable::encode_mapped(&self.#ident, protocol, _writer))
+        } else {
+            quote!(minecraft_data_types::encoder::Encodable::encode(&self.#ident, _writer))
+        };
+        gate(field, quote! {
+            anyhow::Context::context(
+                #encode,
+                format!(
+                    "Failed to encode field {} of packet {} for protocol {:?}.",
+                    stringify!(#ident),
+                    stringify!(#name),
+                    protocol
+                ),
+            )?;
+        })
+    }).collect::<Vec<_>>();
+
+    let sizes = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let size = if field.mapped {
+            quote!(#krate::protocol_version::MapEncodable::size_mapped(&self.#ident, protocol))
+        } else {
+            quote!(minecraft_data_types::encoder::Encodable::size(&self.#ident))
+        };
+        gate(field, quote! {
+            accum += anyhow::Context::context(
+                #size,
+                format!(
+                    "Failed to size field {} of packet {} for protocol {:?}.",
+                    stringify!(#ident),
+                    stringify!(#name),
+                    protocol
+                ),
+            )?;
+        })
+    }).collect::<Vec<_>>();
+
+    let async_encodes = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let async_encode = if field.mapped {
+            quote!(#krate::protocol_version::AsyncMapEncodable::encode_mapped_async(&self.#ident, protocol, _writer).await)
+        } else {
+            quote!(minecraft_data_types::encoder::AsyncEncodable::async_encode(&self.#ident, _writer).await)
+        };
+        gate(field, quote! {
+            anyhow::Context::context(
+                #async_encode,
+                format!(
+                    "Failed to encode field {} of packet {} for protocol {:?}.",
+                    stringify!(#ident),
+                    stringify!(#name),
+                    protocol
+                ),
+            )?;
+        })
+    }).collect::<Vec<_>>();
+
+    // Statements are generated per field in declaration order and rearranged for each layout.
+    let arrange = |statements: &[TokenStream2]| {
+        let branches = layouts.iter().map(|layout| {
+            let predicate = layout.versions.predicate(&krate);
+            let ordered = layout.order.iter().map(|index| &statements[*index]);
+            quote!(if #predicate { #(#ordered)* })
+        });
+        quote!(#(#branches else)* { #(#statements)* })
+    };
+    let decodes = if layouts.is_empty() {
+        quote!(#(#decodes)*)
+    } else {
+        let branches = layouts.iter().map(|layout| {
+            let predicate = layout.versions.predicate(&krate);
+            let ordered = layout.order.iter().map(|index| &decodes[*index]);
+            quote!(if #predicate { #(#ordered)* (#(#field_idents,)*) })
+        });
+        quote! {
+            let (#(#field_idents,)*) = #(#branches else)* { #(#decodes)* (#(#field_idents,)*) };
+        }
+    };
+    let encodes = arrange(&encodes);
+    let sizes = arrange(&sizes);
+    let async_encodes = arrange(&async_encodes);
+
+    let unsupported = quote! {
+        if <Self as #krate::packet::IdentifiedPacket>::packet_id(protocol).is_none() {
+            anyhow::bail!("Unsupported protocol {:?} for packet {}.", protocol, stringify!(#name));
+        }
+    };
+
+    // Round trip tests are only generated for the registries of this crate, which provide
+    // `ArbitraryField` implementations for their field types and a `Registry` alongside.
+    let round_trip = if internal {
+        let field_types = fields.iter().map(|field| &field.ty);
+        let test_module = format_ident!("{}_round_trip", snake_case(&name.to_string()));
+        quote! {
+            #[cfg(test)]
+            impl #impl_generics crate::registry::round_trip::ArbitraryField for #name #type_generics #where_clause {
+                fn arbitrary_field(_runner: &mut proptest::test_runner::TestRunner) -> Self {
+                    Self {
+                        #(#field_idents: <#field_types as crate::registry::round_trip::ArbitraryField>::arbitrary_field(_runner),)*
+                    }
+                }
+            }
+
+            #[cfg(test)]
+            mod #test_module {
+                #[test]
+                fn supported() {
+                    crate::registry::round_trip::assert_supported::<super::#name>();
+                }
+
+                proptest::proptest! {
+                    #![proptest_config(crate::registry::round_trip::config())]
+
+                    #[test]
+                    fn round_trip(
+                        protocol in crate::registry::round_trip::protocols(),
+                        packet in crate::registry::round_trip::arbitrary::<super::#name>(),
+                    ) {
+                        crate::registry::round_trip::assert_round_trip(protocol, &packet, super::Registry::describe_packet);
+                    }
+                }
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    Ok(quote! {
+        impl #impl_generics #krate::packet::IdentifiedPacket for #name #type_generics #where_clause {
+            fn packet_id(protocol: #krate::protocol_version::MCProtocol) -> Option<minecraft_data_types::nums::VarInt> {
+                #(#id_checks)*
+                None
+            }
+        }
+
+        impl #impl_generics #krate::protocol_version::MapDecodable for #name #type_generics #where_clause {
+            fn decode_mapped<R: std::io::Read>(protocol: #krate::protocol_version::MCProtocol, _reader: &mut R) -> anyhow::Result<Self> {
+                #unsupported
+                #decodes
+                Ok(Self {
+                    #(#field_idents,)*
+                })
+            }
+        }
+
+        impl #impl_generics #krate::protocol_version::MapEncodable for #name #type_generics #where_clause {
+            fn encode_mapped<W: std::io::Write>(&self, protocol: #krate::protocol_version::MCProtocol, _writer: &mut W) -> anyhow::Result<()> {
+                #unsupported
+                #encodes
+                Ok(())
+            }
+
+            #[allow(unused_mut)]
+            fn size_mapped(&self, protocol: #krate::protocol_version::MCProtocol) -> anyhow::Result<minecraft_data_types::nums::VarInt> {
+                #unsupported
+                let mut accum = minecraft_data_types::nums::VarInt::from(0);
+                #sizes
+                Ok(accum)
+            }
+        }
+
+        impl #impl_generics #krate::packet::WritablePacket for #name #type_generics #where_clause {
+            fn to_resolved_packet(&self, protocol: #krate::protocol_version::MCProtocol) -> anyhow::Result<#krate::packet::ResolvedPacket> {
+                let packet_id = <Self as #krate::packet::IdentifiedPacket>::packet_id(protocol).ok_or_else(|| {
+                    anyhow::anyhow!("Unsupported protocol {:?} for packet {}.", protocol, stringify!(#name))
+                })?;
+                #krate::packet::ResolvedPacket::from_mapped_encodable(packet_id, protocol, self)
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl #impl_generics #krate::protocol_version::AsyncMapEncodable for #name #type_generics #where_clause {
+            async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(&self, protocol: #krate::protocol_version::MCProtocol, _writer: &mut W) -> anyhow::Result<()> {
+                #unsupported
+                #async_encodes
+                Ok(())
+            }
+        }
+
+        #round_trip
+    })
+}
@@ -0,0 +1,117 @@
+use mc_packet_protocol::capture::{CaptureReader, CapturedPacket, ConnectionState, Direction};
+use mc_packet_protocol::packet::{PacketWriter, ResolvedPacket};
+use mc_packet_protocol::registry::{configuration, handshake, login, play, status};
+use minecraft_data_types::encoder::Decodable;
+use minecraft_data_types::nums::VarInt;
+use std::time::Duration;
+
+const USAGE: &str = "Usage:
+    mc_capture print <capture>
+    mc_capture replay <capture> <address> <serverbound|clientbound>";
+
+fn describe(packet: &CapturedPacket) -> anyhow::Result<Option<String>> {
+    let cursor = packet.cursor();
+    match (packet.state, packet.direction) {
+        (ConnectionState::Handshake, Direction::ServerBound) => {
+            handshake::server_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Handshake, Direction::ClientBound) => Ok(None),
+        (ConnectionState::Status, Direction::ServerBound) => {
+            status::server_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Status, Direction::ClientBound) => {
+            status::client_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Login, Direction::ServerBound) => {
+            login::server_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Login, Direction::ClientBound) => {
+            login::client_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Configuration, Direction::ServerBound) => {
+            configuration::server_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Configuration, Direction::ClientBound) => {
+            configuration::client_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Play, Direction::ServerBound) => {
+            play::server_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+        (ConnectionState::Play, Direction::ClientBound) => {
+            play::client_bound::Registry::describe_packet(cursor, packet.protocol)
+        }
+    }
+}
+
+fn print(path: &str) -> anyhow::Result<()> {
+    let mut start = None;
+    for packet in CaptureReader::open(path)? {
+        let packet = packet?;
+        let start = *start.get_or_insert(packet.timestamp);
+        let offset = packet.timestamp.saturating_sub(start);
+        let packet_id = packet.packet_id()?;
+        println!(
+            "[{:>10.3}s] {} {:?} {:?} ID=0x{:02X} ({} bytes)",
+            offset.as_secs_f64(),
+            packet.direction,
+            packet.state,
+            packet.protocol,
+            Into::<i32>::into(packet_id),
+            packet.data.len()
+        );
+        match describe(&packet) {
+            Ok(Some(description)) => println!("    {}", description),
+            Ok(None) => println!("    <unknown packet>"),
+            Err(error) => println!("    <failed to decode: {}>", error),
+        }
+    }
+    Ok(())
+}
+
+/// Replays one direction of a capture to the given address with the original timing.
+///
+/// Compression is re-enabled when the capture shows a login `SetCompression`, encryption cannot
+/// be replayed as the shared secret is not part of the capture.
+async fn replay(path: &str, address: &str, direction: Direction) -> anyhow::Result<()> {
+    let stream = tokio::net::TcpStream::connect(address).await?;
+    let (_read, write) = stream.into_split();
+    let mut writer = PacketWriter::new(write);
+
+    let mut start = None;
+    let replay_start = tokio::time::Instant::now();
+    for packet in CaptureReader::open(path)? {
+        let packet = packet?;
+        let start = *start.get_or_insert(packet.timestamp);
+
+        if packet.direction == direction {
+            tokio::time::sleep_until(replay_start + packet.timestamp.saturating_sub(start)).await;
+            let mut resolved = ResolvedPacket::from_cursor(packet.cursor())?;
+            writer.send_resolved_packet(&mut resolved).await?;
+        }
+
+        if let (ConnectionState::Login, Direction::ClientBound) = (packet.state, packet.direction) {
+            let mut cursor = packet.cursor();
+            if *VarInt::decode(&mut cursor)? == 0x03 {
+                writer.enable_compression(*VarInt::decode(&mut cursor)?);
+            }
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["print", path] => print(path),
+        ["replay", path, address, direction] => {
+            let direction = match *direction {
+                "serverbound" => Direction::ServerBound,
+                "clientbound" => Direction::ClientBound,
+                _ => anyhow::bail!("{}", USAGE),
+            };
+            tokio::runtime::Runtime::new()?.block_on(replay(path, address, direction))
+        }
+        _ => anyhow::bail!("{}", USAGE),
+    }
+}
@@ -0,0 +1,143 @@
+use crate::chat::Component;
+use crate::registry::play::client_bound::BossBar;
+use crate::registry::play::{BossBarAction, BossBarColor, BossBarDivision, BossBarFlags};
+
+/// Tracks a boss bar shown to clients, building the `BossBar` packet for each change.
+///
+/// The packets are version independent, send the same one to every viewer whatever their
+/// protocol. Setters only return a packet when the value changed.
+#[derive(Debug, Clone)]
+pub struct BossBarHandle {
+    uuid: uuid::Uuid,
+    title: Component,
+    health: f32,
+    color: BossBarColor,
+    division: BossBarDivision,
+    flags: BossBarFlags,
+}
+
+impl BossBarHandle {
+    /// A full purple bar without notches under a random uuid.
+    pub fn new(title: Component) -> Self {
+        Self::with_uuid(uuid::Uuid::from_u128(rand::random()), title)
+    }
+
+    pub fn with_uuid(uuid: uuid::Uuid, title: Component) -> Self {
+        Self {
+            uuid,
+            title,
+            health: 1.0,
+            color: BossBarColor::Purple,
+            division: BossBarDivision::None,
+            flags: BossBarFlags::empty(),
+        }
+    }
+
+    pub fn uuid(&self) -> uuid::Uuid {
+        self.uuid
+    }
+
+    pub fn title(&self) -> &Component {
+        &self.title
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn color(&self) -> BossBarColor {
+        self.color
+    }
+
+    pub fn division(&self) -> BossBarDivision {
+        self.division
+    }
+
+    pub fn flags(&self) -> BossBarFlags {
+        self.flags
+    }
+
+    fn packet(&self, action: BossBarAction) -> BossBar {
+        BossBar { uuid: self.uuid, action }
+    }
+
+    /// Shows the bar as it is now, to new viewers or again after [`BossBarHandle::remove`].
+    pub fn add(&self) -> BossBar {
+        self.packet(BossBarAction::Add {
+            title: self.title.clone(),
+            health: self.health,
+            color: self.color,
+            division: self.division,
+            flags: self.flags,
+        })
+    }
+
+    pub fn remove(&self) -> BossBar {
+        self.packet(BossBarAction::Remove)
+    }
+
+    /// Clamped between 0 and 1.
+    pub fn set_health(&mut self, health: f32) -> Option<BossBar> {
+        let health = health.clamp(0.0, 1.0);
+        if self.health == health {
+            return None;
+        }
+        self.health = health;
+        Some(self.packet(BossBarAction::UpdateHealth(health)))
+    }
+
+    pub fn set_title(&mut self, title: Component) -> Option<BossBar> {
+        if self.title == title {
+            return None;
+        }
+        self.title = title.clone();
+        Some(self.packet(BossBarAction::UpdateTitle(title)))
+    }
+
+    pub fn set_style(&mut self, color: BossBarColor, division: BossBarDivision) -> Option<BossBar> {
+        if self.color == color && self.division == division {
+            return None;
+        }
+        self.color = color;
+        self.division = division;
+        Some(self.packet(BossBarAction::UpdateStyle { color, division }))
+    }
+
+    pub fn set_flags(&mut self, flags: BossBarFlags) -> Option<BossBar> {
+        if self.flags == flags {
+            return None;
+        }
+        self.flags = flags;
+        Some(self.packet(BossBarAction::UpdateFlags(flags)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::EncodedPacket;
+    use crate::protocol_version::MCProtocol;
+
+    #[test]
+    fn handle_emits_only_changed_actions() {
+        let uuid = uuid::Uuid::from_u128(7);
+        let mut handle = BossBarHandle::with_uuid(uuid, Component::text("Ender Dragon"));
+        assert!(matches!(handle.add().action, BossBarAction::Add { health, .. } if health == 1.0));
+
+        let update = handle.set_health(1.5);
+        assert!(update.is_none());
+        let update = handle.set_health(0.25).unwrap();
+        assert_eq!(update.uuid, uuid);
+        assert!(matches!(update.action, BossBarAction::UpdateHealth(health) if health == 0.25));
+        assert!(handle.set_style(BossBarColor::Purple, BossBarDivision::None).is_none());
+        assert!(handle.set_flags(BossBarFlags::DARKEN_SKY | BossBarFlags::CREATE_FOG).is_some());
+        assert!(matches!(handle.add().action, BossBarAction::Add { flags, .. } if flags.bits() == 0x05));
+
+        // The same packet is written with the id of each protocol.
+        let remove = handle.remove();
+        assert_eq!(*EncodedPacket::from_writable(&remove, MCProtocol::V1_18).unwrap().packet_id(), 0x0D);
+        let packet = EncodedPacket::from_writable(&remove, MCProtocol::V1_20_5).unwrap();
+        assert_eq!(*packet.packet_id(), 0x0A);
+        assert_eq!(packet.raw_bytes().unwrap()[17..], [1]);
+    }
+}
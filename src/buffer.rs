@@ -1,7 +1,8 @@
+use crate::fields::MAX_PREALLOCATION;
 use bytes::{Buf, BufMut, BytesMut};
 use flate2::bufread::ZlibDecoder;
 use minecraft_data_types::nums::VarInt;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::io::{Cursor, Read};
 
 pub enum BufferState {
@@ -18,6 +19,8 @@ pub struct MinecraftPacketBuffer {
 }
 
 const BUFFER_CAPACITY: usize = 2097151 + 3;
+/// The largest decompressed packet a client will accept, anything larger is rejected before allocating.
+const MAX_DECOMPRESSED_LENGTH: usize = 8388608;
 
 impl MinecraftPacketBuffer {
     pub fn new() -> Self {
@@ -49,7 +52,11 @@ impl MinecraftPacketBuffer {
         let mut cursor: Cursor<&[u8]> = Cursor::new(self.decoded.chunk());
 
         if let Ok((size, length)) = VarInt::decode_and_size(&mut cursor) {
-            (length + size) <= self.decoded.len()
+            match (usize::try_from(size), usize::try_from(length)) {
+                (Ok(size), Ok(length)) => size + length <= self.decoded.len(),
+                // a negative length can never become valid, let the reader report it
+                _ => true,
+            }
         } else {
             false
         }
@@ -80,7 +87,7 @@ impl MinecraftPacketBuffer {
             self.decoded.len()
         );
 
-        let read_half = self.bytes.chunks_mut(size_read).next().unwrap();
+        let read_half = &mut self.bytes[..size_read];
 
         if let Some(codec) = &mut self.decryption {
             codec.decrypt(read_half);
@@ -89,7 +96,7 @@ impl MinecraftPacketBuffer {
         self.decoded.put_slice(read_half);
 
         self.bytes.advance(size_read);
-        self.bytes.reserve(BUFFER_CAPACITY - self.bytes.len());
+        self.bytes.reserve(BUFFER_CAPACITY.saturating_sub(self.bytes.len()));
 
         if self.is_packet_available() {
             BufferState::PacketReady
@@ -101,31 +108,44 @@ impl MinecraftPacketBuffer {
     pub fn packet_reader(&mut self) -> anyhow::Result<Cursor<Vec<u8>>> {
         let mut cursor = Cursor::new(self.decoded.chunk());
         let (length_size, length) = VarInt::decode_and_size(&mut cursor)?;
-        self.decoded.advance(length_size.try_into()?);
-        let mut cursor: Cursor<Vec<u8>> = Cursor::new(
-            self.decoded
-                .chunks(length.try_into()?)
-                .next()
-                .unwrap()
-                .to_vec(),
-        );
+        let length_size: usize = length_size.try_into()?;
+        let length: usize = length
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Found negative packet length {}.", length))?;
+        if length_size + length > self.decoded.len() {
+            anyhow::bail!("Packet of length {} is not fully buffered.", length);
+        }
+        self.decoded.advance(length_size);
+        let mut cursor: Cursor<Vec<u8>> = Cursor::new(self.decoded[..length].to_vec());
+        self.decoded.advance(length);
 
         let cursor = if self.decompressing {
             let (decompressed_length_size, decompressed_length) =
                 VarInt::decode_and_size(&mut cursor)?;
+            let decompressed_length: usize = decompressed_length
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Found negative decompressed length {}.", decompressed_length))?;
+            if decompressed_length > MAX_DECOMPRESSED_LENGTH {
+                anyhow::bail!("Decompressed length {} exceeds the maximum of {}.", decompressed_length, MAX_DECOMPRESSED_LENGTH);
+            }
             let remaining_bytes = &cursor.into_inner()[decompressed_length_size.try_into()?..];
             if decompressed_length == 0 {
                 Cursor::new(Vec::from(remaining_bytes))
             } else {
-                let mut target = Vec::with_capacity(decompressed_length.try_into()?);
-                ZlibDecoder::new(remaining_bytes).read_to_end(&mut target)?;
+                // The length is untrusted until the data inflates to it.
+                let mut target = Vec::with_capacity(decompressed_length.min(MAX_PREALLOCATION));
+                ZlibDecoder::new(remaining_bytes)
+                    .take(decompressed_length as u64 + 1)
+                    .read_to_end(&mut target)?;
+                if target.len() != decompressed_length {
+                    anyhow::bail!("Decompressed {} bytes but expected {}.", target.len(), decompressed_length);
+                }
                 Cursor::new(target)
             }
         } else {
             cursor
         };
-        self.decoded.advance(length.try_into()?);
-        self.decoded.reserve(BUFFER_CAPACITY - self.decoded.len());
+        self.decoded.reserve(BUFFER_CAPACITY.saturating_sub(self.decoded.len()));
         Ok(cursor)
     }
 }
@@ -135,3 +155,84 @@ impl Default for MinecraftPacketBuffer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::{PacketStage, ResolvedPacket};
+    use minecraft_data_types::encoder::Encodable;
+
+    fn read_frame(packet: &ResolvedPacket) -> Vec<u8> {
+        let mut frame = Vec::new();
+        packet.write(&mut frame).unwrap();
+        assert_eq!(frame.len(), packet.size().unwrap());
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.enable_decompression();
+        buffer.inner_buf().put_slice(&frame);
+        assert!(matches!(buffer.poll(), BufferState::PacketReady));
+        buffer.packet_reader().unwrap().into_inner()
+    }
+
+    #[test]
+    fn packets_are_reframed_for_a_different_threshold() {
+        let mut packet = ResolvedPacket::new(VarInt::from(0x22), vec![7u8; 512]).unwrap();
+        let raw_bytes = packet.raw_bytes().unwrap();
+
+        packet.compress(256).unwrap();
+        assert!(matches!(packet.stage(), PacketStage::FramedCompressed { .. }));
+        assert_eq!(read_frame(&packet), raw_bytes);
+
+        packet.compress(1024).unwrap();
+        assert!(matches!(packet.stage(), PacketStage::FramedUncompressed));
+        assert_eq!(read_frame(&packet), raw_bytes);
+
+        packet.frame_for(Some(256)).unwrap();
+        assert!(matches!(packet.stage(), PacketStage::FramedCompressed { .. }));
+        assert_eq!(read_frame(&packet), raw_bytes);
+    }
+
+    #[test]
+    fn compressed_packets_are_not_compressed_again() {
+        let mut packet = ResolvedPacket::new(VarInt::from(0x22), vec![7u8; 512]).unwrap();
+        let raw_bytes = packet.raw_bytes().unwrap();
+
+        packet.compress(256).unwrap();
+        let mut first = Vec::new();
+        packet.write(&mut first).unwrap();
+
+        packet.compress(256).unwrap();
+        let mut second = Vec::new();
+        packet.write(&mut second).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(read_frame(&packet), raw_bytes);
+    }
+
+    #[test]
+    fn polling_an_overfull_buffer_does_not_panic() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.inner_buf().put_slice(&vec![0x7F; BUFFER_CAPACITY + 16]);
+        assert!(matches!(buffer.poll(), BufferState::PacketReady));
+        assert!(buffer.packet_reader().is_ok());
+    }
+
+    #[test]
+    fn claimed_decompressed_lengths_are_not_trusted() {
+        let mut compressed = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut compressed, &[0x22, 1, 2]).unwrap();
+        let compressed = compressed.finish().unwrap();
+
+        let mut body = Vec::new();
+        VarInt::try_from(MAX_DECOMPRESSED_LENGTH).unwrap().encode(&mut body).unwrap();
+        body.extend_from_slice(&compressed);
+        let mut frame = Vec::new();
+        VarInt::try_from(body.len()).unwrap().encode(&mut frame).unwrap();
+        frame.extend_from_slice(&body);
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.enable_decompression();
+        buffer.inner_buf().put_slice(&frame);
+        assert!(matches!(buffer.poll(), BufferState::PacketReady));
+        let error = buffer.packet_reader().unwrap_err();
+        assert!(error.to_string().contains("Decompressed 3 bytes"));
+    }
+}
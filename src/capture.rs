@@ -0,0 +1,293 @@
+use crate::protocol_version::MCProtocol;
+use anyhow::Context;
+use minecraft_data_types::{encoder::*, nums::VarInt};
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{Display, Formatter};
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CAPTURE_MAGIC: &[u8; 4] = b"MCPC";
+const CAPTURE_VERSION: u8 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    ServerBound,
+    ClientBound,
+}
+
+impl Direction {
+    fn as_byte(&self) -> u8 {
+        match self {
+            Direction::ServerBound => 0,
+            Direction::ClientBound => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(Direction::ServerBound),
+            1 => Ok(Direction::ClientBound),
+            _ => anyhow::bail!("Failed to decode capture direction {}.", byte),
+        }
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::ServerBound => f.write_str("C->S"),
+            Direction::ClientBound => f.write_str("S->C"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Handshake,
+    Status,
+    Login,
+    Play,
+    Configuration,
+}
+
+impl ConnectionState {
+    fn as_byte(&self) -> u8 {
+        match self {
+            ConnectionState::Handshake => 0,
+            ConnectionState::Status => 1,
+            ConnectionState::Login => 2,
+            ConnectionState::Play => 3,
+            ConnectionState::Configuration => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(ConnectionState::Handshake),
+            1 => Ok(ConnectionState::Status),
+            2 => Ok(ConnectionState::Login),
+            3 => Ok(ConnectionState::Play),
+            4 => Ok(ConnectionState::Configuration),
+            _ => anyhow::bail!("Failed to decode capture connection state {}.", byte),
+        }
+    }
+}
+
+/// A single frame read back from a capture file.
+///
+/// `data` holds the decrypted and decompressed packet, starting with the packet id.
+#[derive(Debug)]
+pub struct CapturedPacket {
+    pub timestamp: Duration,
+    pub direction: Direction,
+    pub state: ConnectionState,
+    pub protocol: MCProtocol,
+    pub data: Vec<u8>,
+}
+
+impl CapturedPacket {
+    pub fn packet_id(&self) -> anyhow::Result<VarInt> {
+        VarInt::decode(&mut Cursor::new(&self.data))
+    }
+
+    pub fn cursor(&self) -> Cursor<Vec<u8>> {
+        Cursor::new(self.data.clone())
+    }
+}
+
+struct RecorderState {
+    writer: Box<dyn Write + Send>,
+    state: ConnectionState,
+    protocol: MCProtocol,
+}
+
+/// Records frames to a capture file, shared between the reader and writer of a connection.
+///
+/// The connection state and protocol are not known to the reader or writer, so the owner of the
+/// connection is expected to update them as the connection progresses.
+#[derive(Clone)]
+pub struct CaptureRecorder {
+    inner: Arc<Mutex<RecorderState>>,
+}
+
+impl CaptureRecorder {
+    pub fn new<W: Write + Send + 'static>(mut writer: W) -> anyhow::Result<Self> {
+        writer.write_all(CAPTURE_MAGIC)?;
+        writer.write_all(&[CAPTURE_VERSION])?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(RecorderState {
+                writer: Box::new(writer),
+                state: ConnectionState::Handshake,
+                protocol: MCProtocol::Undefined,
+            })),
+        })
+    }
+
+    pub fn create<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let file = std::fs::File::create(path).context("Failed to create capture file.")?;
+        Self::new(BufWriter::new(file))
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, RecorderState>> {
+        self.inner
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Capture recorder was poisoned."))
+    }
+
+    pub fn set_state(&self, state: ConnectionState) -> anyhow::Result<()> {
+        self.lock()?.state = state;
+        Ok(())
+    }
+
+    pub fn set_protocol(&self, protocol: MCProtocol) -> anyhow::Result<()> {
+        self.lock()?.protocol = protocol;
+        Ok(())
+    }
+
+    /// Records a decrypted, decompressed packet (packet id followed by the packet body).
+    pub fn record(&self, direction: Direction, data: &[u8]) -> anyhow::Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let timestamp = u64::try_from(timestamp.as_micros())?;
+
+        let mut lock = self.lock()?;
+        let mut frame = Vec::with_capacity(data.len() + 16);
+        frame.extend_from_slice(&timestamp.to_be_bytes());
+        frame.push(direction.as_byte());
+        frame.push(lock.state.as_byte());
+        VarInt::from(lock.protocol.as_i32()).encode(&mut frame)?;
+        VarInt::try_from(data.len())?.encode(&mut frame)?;
+        frame.extend_from_slice(data);
+        lock.writer
+            .write_all(&frame)
+            .context("Failed to write capture frame.")
+    }
+
+    pub fn flush(&self) -> anyhow::Result<()> {
+        self.lock()?.writer.flush().context("Failed to flush capture.")
+    }
+}
+
+pub struct CaptureReader<R: Read> {
+    reader: R,
+}
+
+impl CaptureReader<BufReader<std::fs::File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path).context("Failed to open capture file.")?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut header = [0u8; 5];
+        reader
+            .read_exact(&mut header)
+            .context("Failed to read capture header.")?;
+        if &header[..4] != CAPTURE_MAGIC {
+            anyhow::bail!("Not a packet capture file.");
+        }
+        if header[4] != CAPTURE_VERSION {
+            anyhow::bail!("Unsupported capture version {}.", header[4]);
+        }
+        Ok(Self { reader })
+    }
+
+    pub fn next_packet(&mut self) -> anyhow::Result<Option<CapturedPacket>> {
+        let mut timestamp = [0u8; 8];
+        if self.reader.read(&mut timestamp[..1])? == 0 {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut timestamp[1..])?;
+
+        let mut flags = [0u8; 2];
+        self.reader.read_exact(&mut flags)?;
+        let protocol = MCProtocol::from(VarInt::decode(&mut self.reader)?);
+        let length: usize = VarInt::decode(&mut self.reader)?.try_into()?;
+
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut data)?;
+        if data.len() != length {
+            anyhow::bail!("Capture frame was truncated, expected {} bytes but found {}.", length, data.len());
+        }
+
+        Ok(Some(CapturedPacket {
+            timestamp: Duration::from_micros(u64::from_be_bytes(timestamp)),
+            direction: Direction::from_byte(flags[0])?,
+            state: ConnectionState::from_byte(flags[1])?,
+            protocol,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = anyhow::Result<CapturedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recorded_frames_are_read_back() {
+        let buffer = SharedBuffer::default();
+        let recorder = CaptureRecorder::new(buffer.clone()).unwrap();
+        recorder.record(Direction::ServerBound, &[0x00, 1, 2, 3]).unwrap();
+        recorder.set_state(ConnectionState::Login).unwrap();
+        recorder.set_protocol(MCProtocol::V1_20_5).unwrap();
+        recorder.record(Direction::ClientBound, &[0x02]).unwrap();
+        recorder.flush().unwrap();
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        let packets = CaptureReader::new(Cursor::new(bytes))
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(packets.len(), 2);
+
+        assert_eq!(packets[0].direction, Direction::ServerBound);
+        assert_eq!(packets[0].state, ConnectionState::Handshake);
+        assert_eq!(packets[0].protocol, MCProtocol::Undefined);
+        assert_eq!(packets[0].data, [0x00, 1, 2, 3]);
+
+        assert_eq!(packets[1].direction, Direction::ClientBound);
+        assert_eq!(packets[1].state, ConnectionState::Login);
+        assert_eq!(packets[1].protocol, MCProtocol::V1_20_5);
+        assert_eq!(*packets[1].packet_id().unwrap(), 0x02);
+        assert!(packets[0].timestamp <= packets[1].timestamp);
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let buffer = SharedBuffer::default();
+        let recorder = CaptureRecorder::new(buffer.clone()).unwrap();
+        recorder.record(Direction::ServerBound, &[0x00, 1, 2, 3]).unwrap();
+
+        let mut bytes = buffer.0.lock().unwrap().clone();
+        bytes.pop();
+        let mut reader = CaptureReader::new(Cursor::new(bytes)).unwrap();
+        assert!(reader.next_packet().is_err());
+    }
+}
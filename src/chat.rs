@@ -0,0 +1,976 @@
+use crate::nbt::{Compound, Nbt, Tag};
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
+use minecraft_data_types::common::Chat;
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+/// The character starting a legacy formatting code.
+pub const SECTION_SIGN: char = '§';
+
+/// A text component, sent as JSON before 1.20.3 and as NBT afterwards.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Component {
+    pub content: Content,
+    pub style: Style,
+    /// Children inherit this component's style unless they override it.
+    pub extra: Vec<Component>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Text(String),
+    Translate { key: String, with: Vec<Component> },
+    Score { name: String, objective: String },
+    Selector { selector: String, separator: Option<Box<Component>> },
+    Keybind(String),
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::Text(String::new())
+    }
+}
+
+/// Formatting applied to a component, where `None` inherits from the parent.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underlined: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub obfuscated: Option<bool>,
+    pub font: Option<String>,
+    pub insertion: Option<String>,
+    pub click_event: Option<ClickEvent>,
+    pub hover_event: Option<HoverEvent>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    /// A 24 bit RGB color, supported from 1.16.
+    Hex(u32),
+}
+
+/// The named colors in legacy code order, `§0` to `§f`.
+const NAMED_COLORS: [(Color, &str); 16] = [
+    (Color::Black, "black"),
+    (Color::DarkBlue, "dark_blue"),
+    (Color::DarkGreen, "dark_green"),
+    (Color::DarkAqua, "dark_aqua"),
+    (Color::DarkRed, "dark_red"),
+    (Color::DarkPurple, "dark_purple"),
+    (Color::Gold, "gold"),
+    (Color::Gray, "gray"),
+    (Color::DarkGray, "dark_gray"),
+    (Color::Blue, "blue"),
+    (Color::Green, "green"),
+    (Color::Aqua, "aqua"),
+    (Color::Red, "red"),
+    (Color::LightPurple, "light_purple"),
+    (Color::Yellow, "yellow"),
+    (Color::White, "white"),
+];
+
+impl Color {
+    pub fn name(&self) -> String {
+        match self {
+            Color::Hex(rgb) => format!("#{:06X}", rgb & 0xFFFFFF),
+            named => NAMED_COLORS
+                .iter()
+                .find(|(color, _)| color == named)
+                .map(|(_, name)| name.to_string())
+                .expect("Every named color has a name."),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Color> {
+        match name.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16).ok().map(Color::Hex),
+            Some(_) => None,
+            None => NAMED_COLORS
+                .iter()
+                .find(|(_, named)| *named == name)
+                .map(|(color, _)| *color),
+        }
+    }
+
+    /// The legacy code of a named color, hex colors have none.
+    pub fn legacy_code(&self) -> Option<char> {
+        NAMED_COLORS
+            .iter()
+            .position(|(color, _)| color == self)
+            .and_then(|index| std::char::from_digit(index as u32, 16))
+    }
+
+    pub fn from_legacy_code(code: char) -> Option<Color> {
+        code.to_digit(16).map(|index| NAMED_COLORS[index as usize].0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    pub value: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClickAction {
+    OpenUrl,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    CopyToClipboard,
+}
+
+impl ClickAction {
+    const NAMES: [(ClickAction, &'static str); 5] = [
+        (ClickAction::OpenUrl, "open_url"),
+        (ClickAction::RunCommand, "run_command"),
+        (ClickAction::SuggestCommand, "suggest_command"),
+        (ClickAction::ChangePage, "change_page"),
+        (ClickAction::CopyToClipboard, "copy_to_clipboard"),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(action, _)| action == self)
+            .map(|(_, name)| *name)
+            .expect("Every click action has a name.")
+    }
+
+    pub fn from_name(name: &str) -> Option<ClickAction> {
+        Self::NAMES
+            .iter()
+            .find(|(_, action)| *action == name)
+            .map(|(action, _)| *action)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoverEvent {
+    ShowText(Box<Component>),
+    ShowItem {
+        id: String,
+        count: i32,
+        /// The item's NBT in SNBT form.
+        tag: Option<String>,
+    },
+    ShowEntity {
+        entity_type: String,
+        id: uuid::Uuid,
+        name: Option<Box<Component>>,
+    },
+}
+
+impl Component {
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::from_content(Content::Text(text.into()))
+    }
+
+    pub fn translate<S: Into<String>>(key: S, with: Vec<Component>) -> Self {
+        Self::from_content(Content::Translate {
+            key: key.into(),
+            with,
+        })
+    }
+
+    pub fn score<S: Into<String>>(name: S, objective: S) -> Self {
+        Self::from_content(Content::Score {
+            name: name.into(),
+            objective: objective.into(),
+        })
+    }
+
+    pub fn selector<S: Into<String>>(selector: S) -> Self {
+        Self::from_content(Content::Selector {
+            selector: selector.into(),
+            separator: None,
+        })
+    }
+
+    pub fn keybind<S: Into<String>>(key: S) -> Self {
+        Self::from_content(Content::Keybind(key.into()))
+    }
+
+    fn from_content(content: Content) -> Self {
+        Self {
+            content,
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.style.bold = Some(bold);
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.style.italic = Some(italic);
+        self
+    }
+
+    pub fn underlined(mut self, underlined: bool) -> Self {
+        self.style.underlined = Some(underlined);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> Self {
+        self.style.strikethrough = Some(strikethrough);
+        self
+    }
+
+    pub fn obfuscated(mut self, obfuscated: bool) -> Self {
+        self.style.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn click(mut self, action: ClickAction, value: &str) -> Self {
+        self.style.click_event = Some(ClickEvent {
+            action,
+            value: value.to_string(),
+        });
+        self
+    }
+
+    pub fn hover(mut self, hover: HoverEvent) -> Self {
+        self.style.hover_event = Some(hover);
+        self
+    }
+
+    pub fn append(mut self, child: Component) -> Self {
+        self.extra.push(child);
+        self
+    }
+
+    /// The text of this component and its children without formatting.
+    pub fn to_plain(&self) -> String {
+        let mut plain = String::new();
+        self.walk(&Style::default(), &mut |content, _| plain.push_str(&content_text(content)));
+        plain
+    }
+
+    /// Visits every component depth first with its style merged over its parents'.
+    fn walk<F: FnMut(&Content, &Style)>(&self, parent: &Style, visit: &mut F) {
+        let style = self.style.inherit(parent);
+        visit(&self.content, &style);
+        for child in &self.extra {
+            child.walk(&style, visit);
+        }
+    }
+}
+
+impl Style {
+    fn inherit(&self, parent: &Style) -> Style {
+        Style {
+            color: self.color.or(parent.color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self.click_event.clone().or_else(|| parent.click_event.clone()),
+            hover_event: self.hover_event.clone().or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    fn is_plain(&self) -> bool {
+        self.color.is_none() && self.legacy_decorations().is_empty()
+    }
+
+    fn legacy_decorations(&self) -> Vec<char> {
+        [
+            (self.obfuscated, 'k'),
+            (self.bold, 'l'),
+            (self.strikethrough, 'm'),
+            (self.underlined, 'n'),
+            (self.italic, 'o'),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled == Some(true))
+        .map(|(_, code)| *code)
+        .collect()
+    }
+}
+
+fn content_text(content: &Content) -> String {
+    match content {
+        Content::Text(text) => text.clone(),
+        Content::Translate { key, .. } => key.clone(),
+        Content::Score { .. } => String::new(),
+        Content::Selector { selector, .. } => selector.clone(),
+        Content::Keybind(key) => key.clone(),
+    }
+}
+
+impl From<&str> for Component {
+    fn from(text: &str) -> Self {
+        Component::text(text)
+    }
+}
+
+// Legacy formatting codes.
+
+impl Component {
+    /// Converts text with `§` formatting codes, including `§x§r§r§g§g§b§b` hex colors, into a
+    /// component with one child per formatted run.
+    pub fn from_legacy(legacy: &str) -> Component {
+        let mut root = Component::text("");
+        let mut style = Style::default();
+        let mut text = String::new();
+        let mut chars = legacy.chars().peekable();
+
+        while let Some(next) = chars.next() {
+            if next != SECTION_SIGN {
+                text.push(next);
+                continue;
+            }
+            let code = match chars.next() {
+                Some(code) => code.to_ascii_lowercase(),
+                None => break,
+            };
+            if !text.is_empty() {
+                root.extra.push(Component {
+                    content: Content::Text(std::mem::take(&mut text)),
+                    style: style.clone(),
+                    extra: Vec::new(),
+                });
+            }
+            match code {
+                'x' => {
+                    let digits: String = (0..6)
+                        .filter_map(|_| match (chars.next(), chars.next()) {
+                            (Some(SECTION_SIGN), Some(digit)) => Some(digit),
+                            _ => None,
+                        })
+                        .collect();
+                    if let Ok(rgb) = u32::from_str_radix(&digits, 16) {
+                        style = Style {
+                            color: Some(Color::Hex(rgb)),
+                            ..Default::default()
+                        };
+                    }
+                }
+                'k' => style.obfuscated = Some(true),
+                'l' => style.bold = Some(true),
+                'm' => style.strikethrough = Some(true),
+                'n' => style.underlined = Some(true),
+                'o' => style.italic = Some(true),
+                'r' => style = Style::default(),
+                code => {
+                    // Colors reset every decoration, as they did before components.
+                    if let Some(color) = Color::from_legacy_code(code) {
+                        style = Style {
+                            color: Some(color),
+                            ..Default::default()
+                        };
+                    }
+                }
+            }
+        }
+        if !text.is_empty() {
+            root.extra.push(Component {
+                content: Content::Text(text),
+                style,
+                extra: Vec::new(),
+            });
+        }
+        if root.extra.len() == 1 && root.extra[0].style.is_plain() {
+            return root.extra.remove(0);
+        }
+        root
+    }
+
+    /// Flattens this component into `§` formatted text, dropping events and fonts.
+    pub fn to_legacy(&self) -> String {
+        let mut legacy = String::new();
+        let mut current = Style::default();
+        self.walk(&Style::default(), &mut |content, style| {
+            let text = content_text(content);
+            if text.is_empty() {
+                return;
+            }
+            let (color, decorations) = (style.color, style.legacy_decorations());
+            if color != current.color || decorations != current.legacy_decorations() {
+                match color {
+                    Some(Color::Hex(rgb)) => {
+                        legacy.push(SECTION_SIGN);
+                        legacy.push('x');
+                        for digit in format!("{:06x}", rgb & 0xFFFFFF).chars() {
+                            legacy.push(SECTION_SIGN);
+                            legacy.push(digit);
+                        }
+                    }
+                    Some(named) => {
+                        legacy.push(SECTION_SIGN);
+                        legacy.push(named.legacy_code().expect("Named colors have a code."));
+                    }
+                    None => {
+                        legacy.push(SECTION_SIGN);
+                        legacy.push('r');
+                    }
+                }
+                for code in decorations {
+                    legacy.push(SECTION_SIGN);
+                    legacy.push(code);
+                }
+                current = style.clone();
+            }
+            legacy.push_str(&text);
+        });
+        legacy
+    }
+}
+
+// JSON.
+
+impl Component {
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        match &self.content {
+            Content::Text(text) => {
+                object.insert("text".into(), Value::from(text.as_str()));
+            }
+            Content::Translate { key, with } => {
+                object.insert("translate".into(), Value::from(key.as_str()));
+                if !with.is_empty() {
+                    object.insert("with".into(), Value::Array(with.iter().map(Component::to_json).collect()));
+                }
+            }
+            Content::Score { name, objective } => {
+                let mut score = Map::new();
+                score.insert("name".into(), Value::from(name.as_str()));
+                score.insert("objective".into(), Value::from(objective.as_str()));
+                object.insert("score".into(), Value::Object(score));
+            }
+            Content::Selector { selector, separator } => {
+                object.insert("selector".into(), Value::from(selector.as_str()));
+                if let Some(separator) = separator {
+                    object.insert("separator".into(), separator.to_json());
+                }
+            }
+            Content::Keybind(key) => {
+                object.insert("keybind".into(), Value::from(key.as_str()));
+            }
+        }
+
+        let style = &self.style;
+        if let Some(color) = style.color {
+            object.insert("color".into(), Value::from(color.name()));
+        }
+        for (key, flag) in [
+            ("bold", style.bold),
+            ("italic", style.italic),
+            ("underlined", style.underlined),
+            ("strikethrough", style.strikethrough),
+            ("obfuscated", style.obfuscated),
+        ] {
+            if let Some(flag) = flag {
+                object.insert(key.into(), Value::Bool(flag));
+            }
+        }
+        if let Some(font) = &style.font {
+            object.insert("font".into(), Value::from(font.as_str()));
+        }
+        if let Some(insertion) = &style.insertion {
+            object.insert("insertion".into(), Value::from(insertion.as_str()));
+        }
+        if let Some(click) = &style.click_event {
+            let mut event = Map::new();
+            event.insert("action".into(), Value::from(click.action.name()));
+            event.insert("value".into(), Value::from(click.value.as_str()));
+            object.insert("clickEvent".into(), Value::Object(event));
+        }
+        if let Some(hover) = &style.hover_event {
+            object.insert("hoverEvent".into(), hover.to_json());
+        }
+        if !self.extra.is_empty() {
+            object.insert("extra".into(), Value::Array(self.extra.iter().map(Component::to_json).collect()));
+        }
+        Value::Object(object)
+    }
+
+    /// Reads a component from JSON, including the string and array shorthands.
+    pub fn from_json(json: &Value) -> anyhow::Result<Component> {
+        let object = match json {
+            Value::String(text) => return Ok(Component::text(text.as_str())),
+            Value::Number(number) => return Ok(Component::text(number.to_string())),
+            Value::Bool(flag) => return Ok(Component::text(flag.to_string())),
+            Value::Array(components) => {
+                let mut components = components.iter().map(Component::from_json);
+                let mut first = components
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Empty component array."))??;
+                for component in components {
+                    first.extra.push(component?);
+                }
+                return Ok(first);
+            }
+            Value::Object(object) => object,
+            Value::Null => anyhow::bail!("Null is not a component."),
+        };
+
+        let string = |key: &str| -> anyhow::Result<Option<String>> {
+            match object.get(key) {
+                None => Ok(None),
+                Some(Value::String(value)) => Ok(Some(value.clone())),
+                Some(value) => anyhow::bail!("Expected `{}` to be a string, found {}.", key, value),
+            }
+        };
+        let flag = |key: &str| -> anyhow::Result<Option<bool>> {
+            match object.get(key) {
+                None => Ok(None),
+                Some(Value::Bool(value)) => Ok(Some(*value)),
+                Some(value) => anyhow::bail!("Expected `{}` to be a boolean, found {}.", key, value),
+            }
+        };
+        let components = |key: &str| -> anyhow::Result<Vec<Component>> {
+            match object.get(key) {
+                None => Ok(Vec::new()),
+                Some(Value::Array(values)) => values.iter().map(Component::from_json).collect(),
+                Some(value) => anyhow::bail!("Expected `{}` to be an array, found {}.", key, value),
+            }
+        };
+
+        let content = if let Some(text) = string("text")? {
+            Content::Text(text)
+        } else if let Some(key) = string("translate")? {
+            Content::Translate {
+                key,
+                with: components("with")?,
+            }
+        } else if let Some(score) = object.get("score") {
+            let field = |key: &str| {
+                score
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow::anyhow!("Score component without `{}`.", key))
+            };
+            Content::Score {
+                name: field("name")?,
+                objective: field("objective")?,
+            }
+        } else if let Some(selector) = string("selector")? {
+            Content::Selector {
+                selector,
+                separator: object
+                    .get("separator")
+                    .map(Component::from_json)
+                    .transpose()?
+                    .map(Box::new),
+            }
+        } else if let Some(key) = string("keybind")? {
+            Content::Keybind(key)
+        } else {
+            anyhow::bail!("Component without content: {}", json);
+        };
+
+        let style = Style {
+            color: match string("color")? {
+                Some(name) => Some(Color::from_name(&name).ok_or_else(|| anyhow::anyhow!("Unknown color {}.", name))?),
+                None => None,
+            },
+            bold: flag("bold")?,
+            italic: flag("italic")?,
+            underlined: flag("underlined")?,
+            strikethrough: flag("strikethrough")?,
+            obfuscated: flag("obfuscated")?,
+            font: string("font")?,
+            insertion: string("insertion")?,
+            click_event: match object.get("clickEvent") {
+                Some(event) => {
+                    let action = event.get("action").and_then(Value::as_str).unwrap_or_default();
+                    Some(ClickEvent {
+                        action: ClickAction::from_name(action)
+                            .ok_or_else(|| anyhow::anyhow!("Unknown click action {}.", action))?,
+                        value: event.get("value").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    })
+                }
+                None => None,
+            },
+            hover_event: object.get("hoverEvent").map(HoverEvent::from_json).transpose()?,
+        };
+
+        Ok(Component {
+            content,
+            style,
+            extra: components("extra")?,
+        })
+    }
+
+    pub fn to_json_string(&self) -> String {
+        self.to_json().to_string()
+    }
+
+    pub fn from_json_str(json: &str) -> anyhow::Result<Component> {
+        Component::from_json(&serde_json::from_str(json)?)
+    }
+}
+
+impl HoverEvent {
+    fn to_json(&self) -> Value {
+        let mut event = Map::new();
+        let (action, contents) = match self {
+            HoverEvent::ShowText(text) => ("show_text", text.to_json()),
+            HoverEvent::ShowItem { id, count, tag } => {
+                let mut item = Map::new();
+                item.insert("id".into(), Value::from(id.as_str()));
+                item.insert("count".into(), Value::from(*count));
+                if let Some(tag) = tag {
+                    item.insert("tag".into(), Value::from(tag.as_str()));
+                }
+                ("show_item", Value::Object(item))
+            }
+            HoverEvent::ShowEntity { entity_type, id, name } => {
+                let mut entity = Map::new();
+                entity.insert("type".into(), Value::from(entity_type.as_str()));
+                entity.insert("id".into(), Value::from(id.to_hyphenated().to_string()));
+                if let Some(name) = name {
+                    entity.insert("name".into(), name.to_json());
+                }
+                ("show_entity", Value::Object(entity))
+            }
+        };
+        event.insert("action".into(), Value::from(action));
+        event.insert("contents".into(), contents);
+        Value::Object(event)
+    }
+
+    /// Reads `contents`, or the `value` used before 1.16 for text.
+    fn from_json(json: &Value) -> anyhow::Result<HoverEvent> {
+        let action = json.get("action").and_then(Value::as_str).unwrap_or_default();
+        let contents = json
+            .get("contents")
+            .or_else(|| json.get("value"))
+            .ok_or_else(|| anyhow::anyhow!("Hover event without contents."))?;
+        let string = |key: &str| contents.get(key).and_then(Value::as_str).map(str::to_string);
+        Ok(match action {
+            "show_text" => HoverEvent::ShowText(Box::new(Component::from_json(contents)?)),
+            "show_item" => HoverEvent::ShowItem {
+                id: string("id").ok_or_else(|| anyhow::anyhow!("Item hover without an id."))?,
+                count: contents.get("count").and_then(Value::as_i64).unwrap_or(1) as i32,
+                tag: string("tag"),
+            },
+            "show_entity" => HoverEvent::ShowEntity {
+                entity_type: string("type").ok_or_else(|| anyhow::anyhow!("Entity hover without a type."))?,
+                id: entity_uuid(contents.get("id"))?,
+                name: contents.get("name").map(Component::from_json).transpose()?.map(Box::new),
+            },
+            action => anyhow::bail!("Unknown hover action {}.", action),
+        })
+    }
+}
+
+/// Reads an entity hover's id, a string in JSON or four ints, most significant first, in the NBT
+/// sent from 1.20.3.
+fn entity_uuid(id: Option<&Value>) -> anyhow::Result<uuid::Uuid> {
+    match id {
+        Some(Value::String(id)) => Ok(uuid::Uuid::parse_str(id)?),
+        Some(Value::Array(parts)) if parts.len() == 4 => {
+            let mut bits = 0u128;
+            for part in parts {
+                let part = part
+                    .as_i64()
+                    .and_then(|part| i32::try_from(part).ok())
+                    .ok_or_else(|| anyhow::anyhow!("Entity hover id part {} is not an int.", part))?;
+                bits = bits << 32 | u128::from(part as u32);
+            }
+            Ok(uuid::Uuid::from_u128(bits))
+        }
+        _ => anyhow::bail!("Entity hover without a valid id."),
+    }
+}
+
+impl From<&Component> for Chat {
+    fn from(component: &Component) -> Self {
+        Chat::from(component.to_json_string().as_str())
+    }
+}
+
+impl TryFrom<&Chat> for Component {
+    type Error = anyhow::Error;
+
+    fn try_from(chat: &Chat) -> anyhow::Result<Self> {
+        Component::from_json_str(&chat.to_string())
+    }
+}
+
+// NBT, which mirrors the JSON structure from 1.20.3.
+
+impl Component {
+    pub fn to_nbt(&self) -> Nbt {
+        Nbt::from(json_to_tag(&self.to_json()))
+    }
+
+    pub fn from_nbt(nbt: &Nbt) -> anyhow::Result<Component> {
+        Component::from_json(&tag_to_json(&nbt.root))
+    }
+}
+
+impl Style {
+    /// The style on its own, as scoreboard number formats send it.
+    pub fn to_nbt(&self) -> Nbt {
+        let mut nbt = Component {
+            style: self.clone(),
+            ..Component::default()
+        }
+        .to_nbt();
+        if let Tag::Compound(compound) = &mut nbt.root {
+            compound.0.retain(|(name, _)| name != "text");
+        }
+        nbt
+    }
+
+    pub fn from_nbt(nbt: &Nbt) -> anyhow::Result<Style> {
+        let compound = nbt
+            .root
+            .as_compound()
+            .ok_or_else(|| anyhow::anyhow!("Expected a style compound, found tag {}.", nbt.root.id()))?;
+        let component = compound.clone().with("text", Tag::String(String::new()));
+        Ok(Component::from_nbt(&Nbt::from(component))?.style)
+    }
+}
+
+fn json_to_tag(json: &Value) -> Tag {
+    match json {
+        Value::Null => Tag::Compound(Compound::new()),
+        Value::Bool(flag) => Tag::Byte(*flag as i8),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => match i32::try_from(value) {
+                Ok(value) => Tag::Int(value),
+                Err(_) => Tag::Long(value),
+            },
+            None => Tag::Double(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(string) => Tag::String(string.clone()),
+        Value::Array(values) => Tag::List(values.iter().map(json_to_tag).collect()),
+        Value::Object(object) => Tag::Compound(Compound(
+            object.iter().map(|(key, value)| (key.clone(), json_to_tag(value))).collect(),
+        )),
+    }
+}
+
+fn tag_to_json(tag: &Tag) -> Value {
+    match tag {
+        Tag::End => Value::Null,
+        Tag::Byte(0) => Value::Bool(false),
+        Tag::Byte(1) => Value::Bool(true),
+        Tag::Byte(value) => Value::from(*value),
+        Tag::Short(value) => Value::from(*value),
+        Tag::Int(value) => Value::from(*value),
+        Tag::Long(value) => Value::from(*value),
+        Tag::Float(value) => Value::from(*value),
+        Tag::Double(value) => Value::from(*value),
+        Tag::ByteArray(values) => Value::Array(values.iter().map(|value| Value::from(*value)).collect()),
+        Tag::String(string) => Value::from(string.as_str()),
+        Tag::List(tags) => Value::Array(tags.iter().map(tag_to_json).collect()),
+        Tag::Compound(compound) => match compound.0.as_slice() {
+            // Vanilla wraps entries of mixed lists in a compound with an empty key.
+            [(key, value)] if key.is_empty() => tag_to_json(value),
+            entries => Value::Object(entries.iter().map(|(key, value)| (key.clone(), tag_to_json(value))).collect()),
+        },
+        Tag::IntArray(values) => Value::Array(values.iter().map(|value| Value::from(*value)).collect()),
+        Tag::LongArray(values) => Value::Array(values.iter().map(|value| Value::from(*value)).collect()),
+    }
+}
+
+// Wire formats, JSON in a `Chat` string before 1.20.3 and a nameless NBT root afterwards.
+
+fn uses_nbt(protocol: MCProtocol) -> bool {
+    protocol >= MCProtocol::V1_20_3
+}
+
+impl Decodable for Component {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Component::try_from(&Chat::decode(reader)?)
+    }
+}
+
+impl Encodable for Component {
+    fn encode<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        Chat::from(self).encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Chat::from(self).size()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for Component {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(&self, writer: &mut W) -> anyhow::Result<()> {
+        Chat::from(self).async_encode(writer).await
+    }
+}
+
+impl MapDecodable for Component {
+    fn decode_mapped<R: Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if uses_nbt(protocol) {
+            Component::from_nbt(&Nbt::decode_mapped(protocol, reader)?)
+        } else {
+            Component::decode(reader)
+        }
+    }
+}
+
+impl MapEncodable for Component {
+    fn encode_mapped<W: Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        if uses_nbt(protocol) {
+            self.to_nbt().encode_mapped(protocol, writer)
+        } else {
+            self.encode(writer)
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        if uses_nbt(protocol) {
+            self.to_nbt().size_mapped(protocol)
+        } else {
+            self.size()
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for Component {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        if uses_nbt(protocol) {
+            self.to_nbt().encode_mapped_async(protocol, writer).await
+        } else {
+            self.async_encode(writer).await
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for Component {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+
+        let text = |runner: &mut proptest::test_runner::TestRunner| sample(runner, "[a-zA-Z0-9 ]{0,16}");
+        let mut component = Component::text(text(runner));
+        if bool::arbitrary_field(runner) {
+            component.style.color = Some(Color::from_legacy_code(sample(runner, proptest::char::range('0', '9'))).unwrap());
+            component.style.bold = Some(bool::arbitrary_field(runner));
+        }
+        if bool::arbitrary_field(runner) {
+            component = component.append(Component::translate(text(runner), vec![Component::text(text(runner))]).italic(true));
+        }
+        component
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Component {
+        Component::text("Hello ")
+            .color(Color::Gold)
+            .append(Component::translate("chat.type.text", vec![Component::selector("@p")]).bold(true))
+            .append(
+                Component::text("click")
+                    .color(Color::Hex(0x12AB34))
+                    .click(ClickAction::RunCommand, "/spawn")
+                    .hover(HoverEvent::ShowText(Box::new(Component::keybind("key.jump")))),
+            )
+            .append(Component::score("Notch", "kills"))
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let component = sample();
+        let json = component.to_json_string();
+        assert_eq!(Component::from_json_str(&json).unwrap(), component);
+        assert_eq!(
+            Component::from_json_str(r#"["a", {"text": "b", "bold": true}]"#).unwrap(),
+            Component::text("a").append(Component::text("b").bold(true))
+        );
+        assert!(Component::from_json_str(r#"{"color": "red"}"#).is_err());
+    }
+
+    #[test]
+    fn nbt_round_trips() {
+        let component = sample();
+        assert_eq!(Component::from_nbt(&component.to_nbt()).unwrap(), component);
+    }
+
+    #[test]
+    fn entity_hovers_accept_int_array_ids() {
+        let id = uuid::Uuid::from_u128(0x0011_2233_8899_AABB_CCDD_EEFF_0123_4567);
+        let hover = |id: Value| {
+            let json = serde_json::json!({
+                "text": "Notch",
+                "hoverEvent": {"action": "show_entity", "contents": {"type": "minecraft:player", "id": id}},
+            });
+            Component::from_json(&json).unwrap().style.hover_event
+        };
+        let expected = Some(HoverEvent::ShowEntity {
+            entity_type: String::from("minecraft:player"),
+            id,
+            name: None,
+        });
+        assert_eq!(hover(Value::from(id.to_string())), expected);
+        assert_eq!(
+            hover(serde_json::json!([0x0011_2233, 0x8899_AABB_u32 as i32, 0xCCDD_EEFF_u32 as i32, 0x0123_4567])),
+            expected
+        );
+
+        let ints = vec![0x0011_2233, 0x8899_AABB_u32 as i32, 0xCCDD_EEFF_u32 as i32, 0x0123_4567];
+        let contents = Compound::new()
+            .with("type", Tag::String(String::from("minecraft:player")))
+            .with("id", Tag::IntArray(ints));
+        let hover_event = Compound::new()
+            .with("action", Tag::String(String::from("show_entity")))
+            .with("contents", Tag::Compound(contents));
+        let root = Compound::new()
+            .with("text", Tag::String(String::from("Notch")))
+            .with("hoverEvent", Tag::Compound(hover_event));
+        let component = Component::from_nbt(&Nbt::from(root)).unwrap();
+        assert_eq!(component.style.hover_event, expected);
+    }
+
+    #[test]
+    fn legacy_codes_convert_both_ways() {
+        let component = Component::from_legacy("§6Gold §lbold§r plain §x§1§2§a§b§3§4hex");
+        assert_eq!(component.to_plain(), "Gold bold plain hex");
+        assert_eq!(component.extra[0].style.color, Some(Color::Gold));
+        assert_eq!(component.extra[1].style.bold, Some(true));
+        assert_eq!(component.extra[2].style, Style::default());
+        assert_eq!(component.extra[3].style.color, Some(Color::Hex(0x12AB34)));
+        assert_eq!(component.to_legacy(), "§6Gold §6§lbold§r plain §x§1§2§a§b§3§4hex");
+        assert_eq!(Component::from_legacy("plain"), Component::text("plain"));
+    }
+}
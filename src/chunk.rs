@@ -0,0 +1,809 @@
+use crate::fields::Prefixed;
+use crate::nbt::{Compound, Nbt, Tag};
+use crate::registry::play::client_bound::{ChunkData, ChunkDataAndUpdateLight, UnloadChunk, UpdateLight};
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+use std::convert::TryFrom;
+use std::io::{Cursor, Read};
+
+/// Blocks are indexed `(y * 16 + z) * 16 + x` within a section.
+pub const SECTION_BLOCKS: usize = 4096;
+/// Biomes cover 4x4x4 blocks, indexed `(y * 4 + z) * 4 + x` within a section.
+pub const SECTION_BIOMES: usize = 64;
+/// Bytes of one section's light, a nibble per block.
+pub const LIGHT_ARRAY_LENGTH: usize = 2048;
+
+/// What a paletted container holds, which decides its palette sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    BlockStates,
+    Biomes,
+}
+
+impl ContainerKind {
+    fn entries(&self) -> usize {
+        match self {
+            Self::BlockStates => SECTION_BLOCKS,
+            Self::Biomes => SECTION_BIOMES,
+        }
+    }
+
+    fn max_indirect_bits(&self) -> u8 {
+        match self {
+            Self::BlockStates => 8,
+            Self::Biomes => 3,
+        }
+    }
+
+    /// The client always reads indirect block states with at least 4 bits.
+    fn indirect_bits(&self, bits: u8) -> u8 {
+        match self {
+            Self::BlockStates => bits.max(4),
+            Self::Biomes => bits,
+        }
+    }
+
+    /// Bits of the global palette, the client ignores what is sent and uses its own registry size.
+    /// These match vanilla's block states and biomes from 1.18 to 1.20.5.
+    fn direct_bits(&self) -> u8 {
+        match self {
+            Self::BlockStates => 15,
+            Self::Biomes => 6,
+        }
+    }
+}
+
+/// The smallest number of bits that can index `count` values.
+fn bits_for(count: usize) -> u8 {
+    (usize::BITS - count.saturating_sub(1).leading_zeros()) as u8
+}
+
+/// The longs holding `entries` values of `bits` each, values never span two longs.
+fn packed_length(entries: usize, bits: u8) -> usize {
+    let per_long = 64 / bits as usize;
+    entries.div_ceil(per_long)
+}
+
+fn pack(values: impl ExactSizeIterator<Item = u32>, bits: u8) -> Vec<i64> {
+    let per_long = 64 / bits as usize;
+    let mut longs = vec![0i64; packed_length(values.len(), bits)];
+    for (index, value) in values.enumerate() {
+        let shift = (index % per_long) * bits as usize;
+        longs[index / per_long] |= (value as i64) << shift;
+    }
+    longs
+}
+
+fn unpack(longs: &[i64], entries: usize, bits: u8) -> Vec<u32> {
+    let per_long = 64 / bits as usize;
+    let mask = (1u64 << bits) - 1;
+    (0..entries)
+        .map(|index| {
+            let shift = (index % per_long) * bits as usize;
+            ((longs[index / per_long] as u64 >> shift) & mask) as u32
+        })
+        .collect()
+}
+
+/// The block states or biomes of a section, sent with the smallest palette that fits them.
+///
+/// Values are kept unpacked, a single value is sent without data, up to 256 block states or 8
+/// biomes through an indirect palette and anything more as global ids. 1.17.1 only sent block
+/// states this way, without the single value palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PalettedContainer {
+    kind: ContainerKind,
+    values: Vec<u32>,
+}
+
+impl PalettedContainer {
+    pub fn filled(kind: ContainerKind, value: u32) -> Self {
+        Self {
+            kind,
+            values: vec![value; kind.entries()],
+        }
+    }
+
+    pub fn kind(&self) -> ContainerKind {
+        self.kind
+    }
+
+    pub fn get(&self, index: usize) -> u32 {
+        self.values[index]
+    }
+
+    /// Returns the value that was replaced.
+    pub fn set(&mut self, index: usize, value: u32) -> u32 {
+        std::mem::replace(&mut self.values[index], value)
+    }
+
+    pub fn values(&self) -> &[u32] {
+        &self.values
+    }
+
+    /// Distinct values in the order they first appear, `None` once there are too many for an
+    /// indirect palette.
+    fn palette(&self) -> Option<Vec<u32>> {
+        let max = 1 << self.kind.max_indirect_bits();
+        let mut palette = Vec::new();
+        for value in &self.values {
+            if !palette.contains(value) {
+                if palette.len() == max {
+                    return None;
+                }
+                palette.push(*value);
+            }
+        }
+        Some(palette)
+    }
+
+    fn write<W: std::io::Write>(&self, writer: &mut W, single_value: bool) -> anyhow::Result<()> {
+        let (bits, data) = match self.palette() {
+            Some(palette) if single_value && palette.len() == 1 => {
+                0u8.encode(writer)?;
+                VarInt::try_from(palette[0] as usize)?.encode(writer)?;
+                return VarInt::from(0).encode(writer);
+            }
+            Some(palette) => {
+                let bits = self.kind.indirect_bits(bits_for(palette.len()));
+                bits.encode(writer)?;
+                VarInt::try_from(palette.len())?.encode(writer)?;
+                for value in &palette {
+                    VarInt::try_from(*value as usize)?.encode(writer)?;
+                }
+                let indices = self.values.iter().map(|value| {
+                    palette.iter().position(|entry| entry == value).expect("The palette holds every value.") as u32
+                });
+                (bits, pack(indices, bits))
+            }
+            None => {
+                let bits = self.kind.direct_bits();
+                if let Some(value) = self.values.iter().find(|value| bits_for(**value as usize + 1) > bits) {
+                    anyhow::bail!("{:?} id {} does not fit the global palette.", self.kind, value);
+                }
+                bits.encode(writer)?;
+                (bits, pack(self.values.iter().copied(), bits))
+            }
+        };
+        debug_assert_eq!(data.len(), packed_length(self.values.len(), bits));
+        VarInt::try_from(data.len())?.encode(writer)?;
+        for long in data {
+            long.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn read<R: Read>(kind: ContainerKind, reader: &mut R, single_value: bool) -> anyhow::Result<Self> {
+        let read_id = |reader: &mut R| -> anyhow::Result<u32> {
+            u32::try_from(*VarInt::decode(reader)?).map_err(|_| anyhow::anyhow!("Negative {:?} id.", kind))
+        };
+        let read_data = |reader: &mut R, bits: u8| -> anyhow::Result<Vec<i64>> {
+            let length = usize::try_from(*VarInt::decode(reader)?)?;
+            let expected = if bits == 0 { 0 } else { packed_length(kind.entries(), bits) };
+            if length != expected {
+                anyhow::bail!("Expected {} longs of {:?} data, found {}.", expected, kind, length);
+            }
+            (0..length).map(|_| i64::decode(reader)).collect()
+        };
+
+        let bits = u8::decode(reader)?;
+        let values = if single_value && bits == 0 {
+            let value = read_id(reader)?;
+            read_data(reader, 0)?;
+            vec![value; kind.entries()]
+        } else if bits <= kind.max_indirect_bits() {
+            let bits = kind.indirect_bits(bits);
+            let length = usize::try_from(*VarInt::decode(reader)?)?;
+            if length > 1 << bits {
+                anyhow::bail!("{:?} palette of {} entries does not fit {} bits.", kind, length, bits);
+            }
+            let palette = (0..length).map(|_| read_id(reader)).collect::<anyhow::Result<Vec<_>>>()?;
+            unpack(&read_data(reader, bits)?, kind.entries(), bits)
+                .into_iter()
+                .map(|index| {
+                    palette
+                        .get(index as usize)
+                        .copied()
+                        .ok_or_else(|| anyhow::anyhow!("{:?} palette index {} out of range.", kind, index))
+                })
+                .collect::<anyhow::Result<_>>()?
+        } else if bits <= 32 {
+            unpack(&read_data(reader, bits)?, kind.entries(), bits)
+        } else {
+            anyhow::bail!("Invalid {:?} bits per entry {}.", kind, bits);
+        };
+        Ok(Self { kind, values })
+    }
+}
+
+/// A 16x16x16 section of a chunk, with its block states and biomes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSection {
+    block_count: i16,
+    block_states: PalettedContainer,
+    biomes: PalettedContainer,
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        Self::filled(0, 0)
+    }
+}
+
+fn block_index(x: usize, y: usize, z: usize) -> usize {
+    (y * 16 + z) * 16 + x
+}
+
+impl ChunkSection {
+    /// Block state 0 is air, and the only state counted as empty when changing blocks.
+    pub fn filled(block_state: u32, biome: u32) -> Self {
+        Self {
+            block_count: if block_state == 0 { 0 } else { SECTION_BLOCKS as i16 },
+            block_states: PalettedContainer::filled(ContainerKind::BlockStates, block_state),
+            biomes: PalettedContainer::filled(ContainerKind::Biomes, biome),
+        }
+    }
+
+    /// Blocks other than air, which the client uses to skip empty sections.
+    pub fn block_count(&self) -> i16 {
+        self.block_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.block_count == 0
+    }
+
+    /// Coordinates are within the section, from 0 to 15.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.block_states.get(block_index(x, y, z))
+    }
+
+    /// A decoded count is trusted as sent, it is recounted if a change would take it out of range.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block_state: u32) {
+        let previous = self.block_states.set(block_index(x, y, z), block_state);
+        let change = i16::from(block_state != 0) - i16::from(previous != 0);
+        self.block_count = self
+            .block_count
+            .checked_add(change)
+            .filter(|count| (0..=SECTION_BLOCKS as i16).contains(count))
+            .unwrap_or_else(|| self.block_states.values().iter().filter(|state| **state != 0).count() as i16);
+    }
+
+    /// Coordinates are in biome cells within the section, from 0 to 3.
+    pub fn biome(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.biomes.get((y * 4 + z) * 4 + x)
+    }
+
+    pub fn set_biome(&mut self, x: usize, y: usize, z: usize, biome: u32) {
+        self.biomes.set((y * 4 + z) * 4 + x, biome);
+    }
+
+    pub fn block_states(&self) -> &PalettedContainer {
+        &self.block_states
+    }
+
+    pub fn biomes(&self) -> &PalettedContainer {
+        &self.biomes
+    }
+}
+
+fn read_block_count<R: Read>(reader: &mut R) -> anyhow::Result<i16> {
+    let block_count = i16::decode(reader)?;
+    if !(0..=SECTION_BLOCKS as i16).contains(&block_count) {
+        anyhow::bail!("Invalid section block count {}.", block_count);
+    }
+    Ok(block_count)
+}
+
+impl Decodable for ChunkSection {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            block_count: read_block_count(reader)?,
+            block_states: PalettedContainer::read(ContainerKind::BlockStates, reader, true)?,
+            biomes: PalettedContainer::read(ContainerKind::Biomes, reader, true)?,
+        })
+    }
+}
+
+impl Encodable for ChunkSection {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.block_count.encode(writer)?;
+        self.block_states.write(writer, true)?;
+        self.biomes.write(writer, true)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        let mut bytes = Vec::new();
+        self.encode(&mut bytes)?;
+        VarInt::try_from(bytes.len())
+    }
+}
+
+/// Every section of a chunk from the bottom of the world, prefixed by their size in bytes.
+///
+/// The number of sections depends on the dimension's height, so they are read until the data
+/// runs out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkSections(pub Vec<ChunkSection>);
+
+impl ChunkSections {
+    fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for section in &self.0 {
+            section.encode(&mut bytes)?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl Decodable for ChunkSections {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let bytes = Prefixed::<VarInt, Vec<u8>>::decode(reader)?.into_inner();
+        let mut cursor = Cursor::new(bytes);
+        let mut sections = Vec::new();
+        while (cursor.position() as usize) < cursor.get_ref().len() {
+            sections.push(ChunkSection::decode(&mut cursor)?);
+        }
+        Ok(Self(sections))
+    }
+}
+
+impl Encodable for ChunkSections {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        Prefixed::<VarInt, Vec<u8>>::new(self.to_bytes()?).encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Prefixed::<VarInt, Vec<u8>>::new(self.to_bytes()?).size()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for ChunkSections {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        Prefixed::<VarInt, Vec<u8>>::new(self.to_bytes()?).async_encode(writer).await
+    }
+}
+
+/// A section as 1.17.1 sent it, biomes were sent for the whole column instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyChunkSection {
+    block_count: i16,
+    block_states: PalettedContainer,
+}
+
+impl LegacyChunkSection {
+    pub fn block_count(&self) -> i16 {
+        self.block_count
+    }
+
+    pub fn block_states(&self) -> &PalettedContainer {
+        &self.block_states
+    }
+}
+
+impl From<&ChunkSection> for LegacyChunkSection {
+    fn from(section: &ChunkSection) -> Self {
+        Self {
+            block_count: section.block_count,
+            block_states: section.block_states.clone(),
+        }
+    }
+}
+
+/// The sections set in a 1.17.1 chunk's primary bit mask from the bottom of the world, prefixed
+/// by their size in bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegacyChunkSections(pub Vec<LegacyChunkSection>);
+
+impl LegacyChunkSections {
+    fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for section in &self.0 {
+            section.block_count.encode(&mut bytes)?;
+            section.block_states.write(&mut bytes, false)?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl Decodable for LegacyChunkSections {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let bytes = Prefixed::<VarInt, Vec<u8>>::decode(reader)?.into_inner();
+        let mut cursor = Cursor::new(bytes);
+        let mut sections = Vec::new();
+        while (cursor.position() as usize) < cursor.get_ref().len() {
+            sections.push(LegacyChunkSection {
+                block_count: read_block_count(&mut cursor)?,
+                block_states: PalettedContainer::read(ContainerKind::BlockStates, &mut cursor, false)?,
+            });
+        }
+        Ok(Self(sections))
+    }
+}
+
+impl Encodable for LegacyChunkSections {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        Prefixed::<VarInt, Vec<u8>>::new(self.to_bytes()?).encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Prefixed::<VarInt, Vec<u8>>::new(self.to_bytes()?).size()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for LegacyChunkSections {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        Prefixed::<VarInt, Vec<u8>>::new(self.to_bytes()?).async_encode(writer).await
+    }
+}
+
+/// The light of one section, a nibble per block indexed like block states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightArray(pub Box<[u8; LIGHT_ARRAY_LENGTH]>);
+
+impl LightArray {
+    pub fn filled(level: u8) -> Self {
+        let level = level.min(15);
+        Self(Box::new([level << 4 | level; LIGHT_ARRAY_LENGTH]))
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u8 {
+        let index = block_index(x, y, z);
+        self.0[index / 2] >> (4 * (index % 2)) & 0x0F
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        let index = block_index(x, y, z);
+        let shift = 4 * (index % 2);
+        self.0[index / 2] = self.0[index / 2] & !(0x0F << shift) | (level.min(15) << shift);
+    }
+}
+
+impl Decodable for LightArray {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let length = *VarInt::decode(reader)?;
+        if length != LIGHT_ARRAY_LENGTH as i32 {
+            anyhow::bail!("Expected {} bytes of light, found {}.", LIGHT_ARRAY_LENGTH, length);
+        }
+        let mut light = Box::new([0; LIGHT_ARRAY_LENGTH]);
+        reader.read_exact(light.as_mut())?;
+        Ok(Self(light))
+    }
+}
+
+impl Encodable for LightArray {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        VarInt::from(LIGHT_ARRAY_LENGTH as i32).encode(writer)?;
+        writer.write_all(self.0.as_ref())?;
+        Ok(())
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(VarInt::from(LIGHT_ARRAY_LENGTH as i32).size()? + LIGHT_ARRAY_LENGTH as i32)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for LightArray {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        VarInt::from(LIGHT_ARRAY_LENGTH as i32).async_encode(writer).await?;
+        tokio::io::AsyncWriteExt::write_all(writer, self.0.as_ref()).await?;
+        Ok(())
+    }
+}
+
+/// Packs the height of each column, indexed `z * 16 + x`, as one of the heightmaps sent with
+/// a chunk. Heights are the block above the highest one, from the bottom of the world.
+pub fn heightmap(heights: &[u32; 256], world_height: u32) -> Tag {
+    let bits = bits_for(world_height as usize + 1);
+    Tag::LongArray(pack(heights.iter().copied(), bits))
+}
+
+/// A column of sections built in memory, for generating simple worlds such as lobbies.
+///
+/// Sky light is full everywhere and block light empty, which is enough for worlds without caves.
+#[derive(Debug, Clone)]
+pub struct ChunkColumn {
+    pub x: i32,
+    pub z: i32,
+    pub sections: Vec<ChunkSection>,
+}
+
+impl ChunkColumn {
+    /// An empty column, `section_count` is the dimension's height divided by 16.
+    pub fn new(x: i32, z: i32, section_count: usize) -> Self {
+        Self {
+            x,
+            z,
+            sections: vec![ChunkSection::default(); section_count],
+        }
+    }
+
+    /// `y` counts from the bottom of the world.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block_state: u32) {
+        self.sections[y / 16].set_block(x, y % 16, z, block_state);
+    }
+
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.sections[y / 16].block(x, y % 16, z)
+    }
+
+    fn heights(&self) -> [u32; 256] {
+        let mut heights = [0; 256];
+        for (column, height) in heights.iter_mut().enumerate() {
+            let (x, z) = (column % 16, column / 16);
+            *height = (0..self.sections.len() * 16)
+                .rev()
+                .find(|y| !self.sections[y / 16].is_empty() && self.block(x, *y, z) != 0)
+                .map_or(0, |y| y as u32 + 1);
+        }
+        heights
+    }
+
+    /// Light masks cover a section below and above the world too.
+    fn light_mask(&self) -> Prefixed<VarInt, Vec<i64>> {
+        let sections = self.sections.len() + 2;
+        let mut mask = vec![0i64; sections.div_ceil(64)];
+        for section in 0..sections {
+            mask[section / 64] |= 1 << (section % 64);
+        }
+        Prefixed::from(mask)
+    }
+
+    fn heightmaps(&self) -> Nbt {
+        let world_height = self.sections.len() as u32 * 16;
+        let heightmap = heightmap(&self.heights(), world_height);
+        Nbt::from(
+            Compound::new()
+                .with("MOTION_BLOCKING", heightmap.clone())
+                .with("WORLD_SURFACE", heightmap),
+        )
+    }
+
+    /// The column from 1.18, use [`Self::legacy_packet`] and [`Self::light`] for 1.17.1.
+    pub fn packet(&self) -> ChunkDataAndUpdateLight {
+        let light = self.light();
+        ChunkDataAndUpdateLight {
+            chunk_x: self.x,
+            chunk_z: self.z,
+            heightmaps: self.heightmaps(),
+            sections: ChunkSections(self.sections.clone()),
+            block_entities: Prefixed::default(),
+            trust_edges: light.trust_edges,
+            sky_light_mask: light.sky_light_mask,
+            block_light_mask: light.block_light_mask,
+            empty_sky_light_mask: light.empty_sky_light_mask,
+            empty_block_light_mask: light.empty_block_light_mask,
+            sky_light: light.sky_light,
+            block_light: light.block_light,
+        }
+    }
+
+    /// The column as 1.17.1 sent it, only non-empty sections are sent and its light follows in
+    /// [`Self::light`].
+    pub fn legacy_packet(&self) -> ChunkData {
+        let mut primary_bit_mask = vec![0i64; self.sections.len().div_ceil(64)];
+        for (index, section) in self.sections.iter().enumerate() {
+            if !section.is_empty() {
+                primary_bit_mask[index / 64] |= 1 << (index % 64);
+            }
+        }
+        let biomes = self
+            .sections
+            .iter()
+            .flat_map(|section| section.biomes.values())
+            .map(|biome| VarInt::from(*biome as i32))
+            .collect::<Vec<_>>();
+        let sections = self
+            .sections
+            .iter()
+            .filter(|section| !section.is_empty())
+            .map(LegacyChunkSection::from)
+            .collect();
+        ChunkData {
+            chunk_x: self.x,
+            chunk_z: self.z,
+            primary_bit_mask: Prefixed::from(primary_bit_mask),
+            heightmaps: self.heightmaps(),
+            biomes: Prefixed::from(biomes),
+            sections: LegacyChunkSections(sections),
+            block_entities: Prefixed::default(),
+        }
+    }
+
+    /// Sent with [`Self::legacy_packet`] on 1.17.1, and whenever the light changes.
+    pub fn light(&self) -> UpdateLight {
+        UpdateLight {
+            chunk_x: VarInt::from(self.x),
+            chunk_z: VarInt::from(self.z),
+            trust_edges: true,
+            sky_light_mask: self.light_mask(),
+            block_light_mask: Prefixed::default(),
+            empty_sky_light_mask: Prefixed::default(),
+            empty_block_light_mask: self.light_mask(),
+            sky_light: Prefixed::from(vec![LightArray::filled(15); self.sections.len() + 2]),
+            block_light: Prefixed::default(),
+        }
+    }
+
+    pub fn unload(&self) -> UnloadChunk {
+        UnloadChunk {
+            chunk_x: self.x,
+            chunk_z: self.z,
+        }
+    }
+}
+
+#[cfg(test)]
+mod arbitrary {
+    use super::*;
+    use crate::registry::round_trip::{sample, ArbitraryField};
+    use proptest::test_runner::TestRunner;
+
+    /// Picks palettes of every size, filling the section with a repeating pattern.
+    fn container(runner: &mut TestRunner, kind: ContainerKind) -> PalettedContainer {
+        let distinct = [1, 2, 5, 20, 300][sample(runner, 0..5usize)].min(kind.entries());
+        let palette = (0..distinct).map(|_| sample(runner, 0..1u32 << 14)).collect::<Vec<_>>();
+        let stride = sample(runner, 1..64usize);
+        let mut container = PalettedContainer::filled(kind, palette[0]);
+        for index in 0..kind.entries() {
+            let value = match kind {
+                ContainerKind::Biomes => palette[(index * stride) % distinct] % 64,
+                ContainerKind::BlockStates => palette[(index * stride) % distinct],
+            };
+            container.set(index, value);
+        }
+        container
+    }
+
+    impl ArbitraryField for ChunkSection {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            Self {
+                block_count: sample(runner, 0..4097i16),
+                block_states: container(runner, ContainerKind::BlockStates),
+                biomes: container(runner, ContainerKind::Biomes),
+            }
+        }
+    }
+
+    impl ArbitraryField for ChunkSections {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            Self((0..sample(runner, 0..3usize)).map(|_| ChunkSection::arbitrary_field(runner)).collect())
+        }
+    }
+
+    impl ArbitraryField for LegacyChunkSections {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            Self(
+                (0..sample(runner, 0..3usize))
+                    .map(|_| LegacyChunkSection {
+                        block_count: sample(runner, 0..4097i16),
+                        block_states: container(runner, ContainerKind::BlockStates),
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    impl ArbitraryField for LightArray {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            let mut light = LightArray::filled(sample(runner, 0..16u8));
+            light.set(sample(runner, 0..16usize), sample(runner, 0..16usize), 0, sample(runner, 0..16u8));
+            light
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(section: &ChunkSection) -> (Vec<u8>, ChunkSection) {
+        let mut bytes = Vec::new();
+        section.encode(&mut bytes).unwrap();
+        let decoded = ChunkSection::decode(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(&decoded, section);
+        (bytes, decoded)
+    }
+
+    #[test]
+    fn sections_pick_the_smallest_palette() {
+        // An empty section is two single value containers.
+        let (bytes, _) = round_trip(&ChunkSection::default());
+        assert_eq!(bytes, [0, 0, 0, 0, 0, 0, 0, 0]);
+
+        // Two block states still use 4 bits, 16 to a long.
+        let mut section = ChunkSection::default();
+        section.set_block(1, 0, 0, 9);
+        assert_eq!(section.block_count(), 1);
+        let (bytes, _) = round_trip(&section);
+        assert_eq!(&bytes[..6], &[0, 1, 4, 2, 0, 9]);
+        assert_eq!(&bytes[6..8], &[0x80, 0x02]);
+        assert_eq!(&bytes[8..16], &[0, 0, 0, 0, 0, 0, 0, 0x10]);
+
+        // More states than an indirect palette holds are sent as global ids.
+        for index in 0..300 {
+            section.set_block(index % 16, index / 256, index / 16 % 16, index as u32 + 1);
+        }
+        section.set_biome(3, 3, 3, 40);
+        let (bytes, decoded) = round_trip(&section);
+        assert_eq!(&bytes[2..3], &[15]);
+        assert_eq!(decoded.block(4, 1, 2), 2 * 16 + 4 + 256 + 1);
+        assert_eq!(decoded.biome(3, 3, 3), 40);
+        section.set_block(0, 0, 0, 1 << 15);
+        assert!(section.encode(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn block_counts_stay_in_range() {
+        let mut bytes = Vec::new();
+        ChunkSection::default().encode(&mut bytes).unwrap();
+        for count in [-1i16, SECTION_BLOCKS as i16 + 1] {
+            bytes[..2].copy_from_slice(&count.to_be_bytes());
+            assert!(ChunkSection::decode(&mut Cursor::new(&bytes)).is_err());
+        }
+
+        // A full count sent for an empty section is recounted rather than overflowing.
+        bytes[..2].copy_from_slice(&(SECTION_BLOCKS as i16).to_be_bytes());
+        let mut section = ChunkSection::decode(&mut Cursor::new(&bytes)).unwrap();
+        section.set_block(0, 0, 0, 1);
+        assert_eq!(section.block_count(), 1);
+    }
+
+    #[test]
+    fn columns_build_heightmaps_and_light() {
+        let mut column = ChunkColumn::new(2, -3, 24);
+        for x in 0..16 {
+            for z in 0..16 {
+                column.set_block(x, 63, z, 1);
+            }
+        }
+        column.set_block(5, 70, 5, 1);
+        let packet = column.packet();
+        assert_eq!(packet.sections.0.iter().filter(|section| !section.is_empty()).count(), 2);
+        assert_eq!(packet.sky_light.len(), 26);
+        assert_eq!(*packet.sky_light_mask, vec![(1 << 26) - 1]);
+
+        // 384 blocks need 9 bits, 7 heights to a long.
+        let heightmaps = packet.heightmaps.root.as_compound().unwrap();
+        let heights = match heightmaps.get("MOTION_BLOCKING") {
+            Some(Tag::LongArray(heights)) => unpack(heights, 256, 9),
+            tag => panic!("Expected a long array heightmap, found {:?}.", tag),
+        };
+        assert_eq!(heights[0], 64);
+        assert_eq!(heights[5 * 16 + 5], 71);
+    }
+
+    #[test]
+    fn legacy_columns_send_non_empty_sections() {
+        let mut column = ChunkColumn::new(0, 0, 16);
+        column.set_block(0, 40, 0, 1);
+        column.sections[0].set_biome(1, 0, 0, 7);
+        let packet = column.legacy_packet();
+        assert_eq!(*packet.primary_bit_mask, vec![1 << 2]);
+        assert_eq!(packet.sections.0.len(), 1);
+        assert_eq!(packet.biomes.len(), 16 * SECTION_BIOMES);
+        assert_eq!(*packet.biomes[1], 7);
+
+        // A single block state still has a palette, at the smallest 4 bits.
+        let mut bytes = Vec::new();
+        LegacyChunkSections(vec![LegacyChunkSection::from(&ChunkSection::filled(1, 0))])
+            .encode(&mut bytes)
+            .unwrap();
+        assert_eq!(&bytes[..9], &[0x87, 0x10, 0x10, 0x00, 4, 1, 1, 0x80, 0x02]);
+        let decoded = LegacyChunkSections::decode(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(decoded.0[0].block_states().values(), &[1; SECTION_BLOCKS]);
+        assert_eq!(column.light().sky_light.len(), 18);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::chat::Component;
+use crate::fields::{read_var_long, var_long_bytes, Position, RemainingBytes};
+use crate::nbt::Nbt;
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
+use crate::registry::play::DeathLocation;
+use crate::slot::Slot;
+use minecraft_data_types::auto_string;
+use minecraft_data_types::encoder::{Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+auto_string!(MetadataString, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(MetadataString, 32767);
+
+/// Marks the end of the entries in place of an index.
+const END_OF_METADATA: u8 = 0xFF;
+
+/// The kinds of metadata values, whose ids are their position in each protocol's list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetadataKind {
+    Byte,
+    VarInt,
+    VarLong,
+    Float,
+    String,
+    Chat,
+    OptChat,
+    Slot,
+    Boolean,
+    Rotation,
+    Position,
+    OptPosition,
+    Direction,
+    OptUuid,
+    BlockState,
+    OptBlockState,
+    Nbt,
+    Particle,
+    Particles,
+    VillagerData,
+    OptVarInt,
+    Pose,
+    CatVariant,
+    WolfVariant,
+    FrogVariant,
+    OptGlobalPos,
+    PaintingVariant,
+    SnifferState,
+    ArmadilloState,
+    Vector3,
+    Quaternion,
+}
+
+use MetadataKind as K;
+
+const KINDS_1_17: &[MetadataKind] = &[
+    K::Byte, K::VarInt, K::Float, K::String, K::Chat, K::OptChat, K::Slot, K::Boolean, K::Rotation,
+    K::Position, K::OptPosition, K::Direction, K::OptUuid, K::OptBlockState, K::Nbt, K::Particle,
+    K::VillagerData, K::OptVarInt, K::Pose,
+];
+
+const KINDS_1_19: &[MetadataKind] = &[
+    K::Byte, K::VarInt, K::Float, K::String, K::Chat, K::OptChat, K::Slot, K::Boolean, K::Rotation,
+    K::Position, K::OptPosition, K::Direction, K::OptUuid, K::OptBlockState, K::Nbt, K::Particle,
+    K::VillagerData, K::OptVarInt, K::Pose, K::CatVariant, K::FrogVariant, K::OptGlobalPos,
+    K::PaintingVariant,
+];
+
+/// 1.19.3 inserted `VarLong` and 1.19.4 `BlockState`, shifting everything after them.
+const KINDS_1_20_2: &[MetadataKind] = &[
+    K::Byte, K::VarInt, K::VarLong, K::Float, K::String, K::Chat, K::OptChat, K::Slot, K::Boolean,
+    K::Rotation, K::Position, K::OptPosition, K::Direction, K::OptUuid, K::BlockState,
+    K::OptBlockState, K::Nbt, K::Particle, K::VillagerData, K::OptVarInt, K::Pose, K::CatVariant,
+    K::FrogVariant, K::OptGlobalPos, K::PaintingVariant, K::SnifferState, K::Vector3,
+    K::Quaternion,
+];
+
+const KINDS_1_20_5: &[MetadataKind] = &[
+    K::Byte, K::VarInt, K::VarLong, K::Float, K::String, K::Chat, K::OptChat, K::Slot, K::Boolean,
+    K::Rotation, K::Position, K::OptPosition, K::Direction, K::OptUuid, K::BlockState,
+    K::OptBlockState, K::Nbt, K::Particle, K::Particles, K::VillagerData, K::OptVarInt, K::Pose,
+    K::CatVariant, K::WolfVariant, K::FrogVariant, K::OptGlobalPos, K::PaintingVariant,
+    K::SnifferState, K::ArmadilloState, K::Vector3, K::Quaternion,
+];
+
+fn kinds(protocol: MCProtocol) -> &'static [MetadataKind] {
+    if protocol >= MCProtocol::V1_20_5 {
+        KINDS_1_20_5
+    } else if protocol >= MCProtocol::V1_20_2 {
+        KINDS_1_20_2
+    } else if protocol >= MCProtocol::V1_19 {
+        KINDS_1_19
+    } else {
+        KINDS_1_17
+    }
+}
+
+/// A typed metadata value.
+///
+/// Particles are not parsed, their type and every byte left in the packet are kept as `Opaque`,
+/// which has to be the last entry and is only valid for the protocol it was read with.
+#[derive(Debug, Clone)]
+pub enum MetadataValue {
+    Byte(i8),
+    VarInt(VarInt),
+    /// From 1.19.3.
+    VarLong(i64),
+    Float(f32),
+    String(MetadataString),
+    Chat(Component),
+    OptChat(Option<Component>),
+    Slot(Slot),
+    Boolean(bool),
+    /// Degrees around the x, y and z axes.
+    Rotation([f32; 3]),
+    Position(Position),
+    OptPosition(Option<Position>),
+    Direction(VarInt),
+    OptUuid(Option<uuid::Uuid>),
+    /// From 1.19.4.
+    BlockState(VarInt),
+    /// Air, block state 0, is sent for no block.
+    OptBlockState(VarInt),
+    Nbt(Nbt),
+    VillagerData {
+        villager_type: VarInt,
+        profession: VarInt,
+        level: VarInt,
+    },
+    OptVarInt(Option<VarInt>),
+    Pose(VarInt),
+    /// From 1.19.
+    CatVariant(VarInt),
+    /// From 1.20.5.
+    WolfVariant(VarInt),
+    /// From 1.19.
+    FrogVariant(VarInt),
+    /// From 1.19.
+    OptGlobalPos(Option<DeathLocation>),
+    /// From 1.19.
+    PaintingVariant(VarInt),
+    /// From 1.20.
+    SnifferState(VarInt),
+    /// From 1.20.5.
+    ArmadilloState(VarInt),
+    /// From 1.19.4.
+    Vector3([f32; 3]),
+    /// From 1.19.4.
+    Quaternion([f32; 4]),
+    Opaque {
+        type_id: VarInt,
+        data: RemainingBytes,
+    },
+}
+
+fn read_floats<R: Read, const N: usize>(reader: &mut R) -> anyhow::Result<[f32; N]> {
+    let mut floats = [0.0; N];
+    for float in &mut floats {
+        *float = f32::decode(reader)?;
+    }
+    Ok(floats)
+}
+
+fn write_floats<W: Write>(floats: &[f32], writer: &mut W) -> anyhow::Result<()> {
+    floats.iter().try_for_each(|float| float.encode(writer))
+}
+
+fn read_optional<R: Read, T>(
+    reader: &mut R,
+    read: impl FnOnce(&mut R) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    Ok(if bool::decode(reader)? { Some(read(reader)?) } else { None })
+}
+
+fn write_optional<W: Write, T>(
+    value: &Option<T>,
+    writer: &mut W,
+    write: impl FnOnce(&T, &mut W) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    value.is_some().encode(writer)?;
+    match value {
+        Some(value) => write(value, writer),
+        None => Ok(()),
+    }
+}
+
+impl MetadataValue {
+    fn kind(&self) -> Option<MetadataKind> {
+        Some(match self {
+            Self::Byte(_) => K::Byte,
+            Self::VarInt(_) => K::VarInt,
+            Self::VarLong(_) => K::VarLong,
+            Self::Float(_) => K::Float,
+            Self::String(_) => K::String,
+            Self::Chat(_) => K::Chat,
+            Self::OptChat(_) => K::OptChat,
+            Self::Slot(_) => K::Slot,
+            Self::Boolean(_) => K::Boolean,
+            Self::Rotation(_) => K::Rotation,
+            Self::Position(_) => K::Position,
+            Self::OptPosition(_) => K::OptPosition,
+            Self::Direction(_) => K::Direction,
+            Self::OptUuid(_) => K::OptUuid,
+            Self::BlockState(_) => K::BlockState,
+            Self::OptBlockState(_) => K::OptBlockState,
+            Self::Nbt(_) => K::Nbt,
+            Self::VillagerData { .. } => K::VillagerData,
+            Self::OptVarInt(_) => K::OptVarInt,
+            Self::Pose(_) => K::Pose,
+            Self::CatVariant(_) => K::CatVariant,
+            Self::WolfVariant(_) => K::WolfVariant,
+            Self::FrogVariant(_) => K::FrogVariant,
+            Self::OptGlobalPos(_) => K::OptGlobalPos,
+            Self::PaintingVariant(_) => K::PaintingVariant,
+            Self::SnifferState(_) => K::SnifferState,
+            Self::ArmadilloState(_) => K::ArmadilloState,
+            Self::Vector3(_) => K::Vector3,
+            Self::Quaternion(_) => K::Quaternion,
+            Self::Opaque { .. } => return None,
+        })
+    }
+
+    /// The type id of this value in `protocol`, an error where the type does not exist.
+    pub fn type_id(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        let kind = match (self.kind(), self) {
+            (Some(kind), _) => kind,
+            (None, Self::Opaque { type_id, .. }) => return Ok(*type_id),
+            (None, _) => unreachable!("Only opaque values have no kind."),
+        };
+        kinds(protocol)
+            .iter()
+            .position(|candidate| *candidate == kind)
+            .map(|id| VarInt::from(id as i32))
+            .ok_or_else(|| anyhow::anyhow!("Metadata type {:?} does not exist in {:?}.", kind, protocol))
+    }
+
+    fn read<R: Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        let type_id = VarInt::decode(reader)?;
+        let kind = usize::try_from(*type_id).ok().and_then(|id| kinds(protocol).get(id));
+        Ok(match kind {
+            Some(K::Byte) => Self::Byte(i8::decode(reader)?),
+            Some(K::VarInt) => Self::VarInt(VarInt::decode(reader)?),
+            Some(K::VarLong) => Self::VarLong(read_var_long(reader)?),
+            Some(K::Float) => Self::Float(f32::decode(reader)?),
+            Some(K::String) => Self::String(MetadataString::decode(reader)?),
+            Some(K::Chat) => Self::Chat(Component::decode_mapped(protocol, reader)?),
+            Some(K::OptChat) => {
+                Self::OptChat(read_optional(reader, |reader| Component::decode_mapped(protocol, reader))?)
+            }
+            Some(K::Slot) => Self::Slot(Slot::decode_mapped(protocol, reader)?),
+            Some(K::Boolean) => Self::Boolean(bool::decode(reader)?),
+            Some(K::Rotation) => Self::Rotation(read_floats(reader)?),
+            Some(K::Position) => Self::Position(Position::decode(reader)?),
+            Some(K::OptPosition) => Self::OptPosition(read_optional(reader, Position::decode)?),
+            Some(K::Direction) => Self::Direction(VarInt::decode(reader)?),
+            Some(K::OptUuid) => Self::OptUuid(read_optional(reader, uuid::Uuid::decode)?),
+            Some(K::BlockState) => Self::BlockState(VarInt::decode(reader)?),
+            Some(K::OptBlockState) => Self::OptBlockState(VarInt::decode(reader)?),
+            Some(K::Nbt) => Self::Nbt(Nbt::decode_mapped(protocol, reader)?),
+            Some(K::VillagerData) => Self::VillagerData {
+                villager_type: VarInt::decode(reader)?,
+                profession: VarInt::decode(reader)?,
+                level: VarInt::decode(reader)?,
+            },
+            Some(K::OptVarInt) => Self::OptVarInt(match *VarInt::decode(reader)? {
+                0 => None,
+                value if value > 0 => Some(VarInt::from(value - 1)),
+                value => anyhow::bail!("Invalid optional VarInt {}.", value),
+            }),
+            Some(K::Pose) => Self::Pose(VarInt::decode(reader)?),
+            Some(K::CatVariant) => Self::CatVariant(VarInt::decode(reader)?),
+            Some(K::WolfVariant) => Self::WolfVariant(VarInt::decode(reader)?),
+            Some(K::FrogVariant) => Self::FrogVariant(VarInt::decode(reader)?),
+            Some(K::OptGlobalPos) => Self::OptGlobalPos(read_optional(reader, DeathLocation::decode)?),
+            Some(K::PaintingVariant) => Self::PaintingVariant(VarInt::decode(reader)?),
+            Some(K::SnifferState) => Self::SnifferState(VarInt::decode(reader)?),
+            Some(K::ArmadilloState) => Self::ArmadilloState(VarInt::decode(reader)?),
+            Some(K::Vector3) => Self::Vector3(read_floats(reader)?),
+            Some(K::Quaternion) => Self::Quaternion(read_floats(reader)?),
+            Some(K::Particle | K::Particles) | None => Self::Opaque {
+                type_id,
+                data: RemainingBytes::decode(reader)?,
+            },
+        })
+    }
+
+    fn write<W: Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.type_id(protocol)?.encode(writer)?;
+        match self {
+            Self::Byte(value) => value.encode(writer),
+            Self::VarLong(value) => Ok(writer.write_all(&var_long_bytes(*value))?),
+            Self::Float(value) => value.encode(writer),
+            Self::String(value) => value.encode(writer),
+            Self::Chat(value) => value.encode_mapped(protocol, writer),
+            Self::OptChat(value) => write_optional(value, writer, |value, writer| value.encode_mapped(protocol, writer)),
+            Self::Slot(slot) => slot.encode_mapped(protocol, writer),
+            Self::Boolean(value) => value.encode(writer),
+            Self::Rotation(floats) | Self::Vector3(floats) => write_floats(floats, writer),
+            Self::Quaternion(floats) => write_floats(floats, writer),
+            Self::Position(value) => value.encode(writer),
+            Self::OptPosition(value) => write_optional(value, writer, Position::encode),
+            Self::OptUuid(value) => write_optional(value, writer, uuid::Uuid::encode),
+            Self::Nbt(value) => value.encode_mapped(protocol, writer),
+            Self::VillagerData {
+                villager_type,
+                profession,
+                level,
+            } => {
+                villager_type.encode(writer)?;
+                profession.encode(writer)?;
+                level.encode(writer)
+            }
+            Self::OptVarInt(None) => VarInt::from(0).encode(writer),
+            Self::OptVarInt(Some(value)) => match value.checked_add(1).filter(|_| **value >= 0) {
+                Some(value) => VarInt::from(value).encode(writer),
+                None => anyhow::bail!("Optional VarInt {} cannot be sent.", **value),
+            },
+            Self::OptGlobalPos(value) => write_optional(value, writer, DeathLocation::encode),
+            Self::VarInt(value)
+            | Self::Direction(value)
+            | Self::BlockState(value)
+            | Self::OptBlockState(value)
+            | Self::Pose(value)
+            | Self::CatVariant(value)
+            | Self::WolfVariant(value)
+            | Self::FrogVariant(value)
+            | Self::PaintingVariant(value)
+            | Self::SnifferState(value)
+            | Self::ArmadilloState(value) => value.encode(writer),
+            Self::Opaque { data, .. } => data.encode(writer),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataEntry {
+    pub index: u8,
+    pub value: MetadataValue,
+}
+
+/// The metadata entries of an entity, sent whole on spawn and as changes afterwards.
+///
+/// What each index means depends on the entity type and version, this only knows their types.
+#[derive(Debug, Clone, Default)]
+pub struct EntityMetadata(pub Vec<MetadataEntry>);
+
+impl EntityMetadata {
+    pub fn get(&self, index: u8) -> Option<&MetadataValue> {
+        self.0.iter().find(|entry| entry.index == index).map(|entry| &entry.value)
+    }
+
+    /// Replaces the value at `index` or adds it before any opaque entry.
+    pub fn set(&mut self, index: u8, value: MetadataValue) {
+        if let Some(entry) = self.0.iter_mut().find(|entry| entry.index == index) {
+            entry.value = value;
+            return;
+        }
+        let position = self
+            .0
+            .iter()
+            .position(|entry| matches!(entry.value, MetadataValue::Opaque { .. }))
+            .unwrap_or(self.0.len());
+        self.0.insert(position, MetadataEntry { index, value });
+    }
+
+    fn to_bytes(&self, protocol: MCProtocol) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for (position, entry) in self.0.iter().enumerate() {
+            if entry.index == END_OF_METADATA {
+                anyhow::bail!("Metadata index {} marks the end of the entries.", END_OF_METADATA);
+            }
+            entry.index.encode(&mut bytes)?;
+            entry.value.write(protocol, &mut bytes)?;
+            if let MetadataValue::Opaque { .. } = entry.value {
+                if position + 1 != self.0.len() {
+                    anyhow::bail!("Opaque metadata at index {} has to be the last entry.", entry.index);
+                }
+                // The opaque data already holds the end of the entries.
+                return Ok(bytes);
+            }
+        }
+        END_OF_METADATA.encode(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl MapDecodable for EntityMetadata {
+    fn decode_mapped<R: Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        let mut entries = Vec::new();
+        loop {
+            let index = u8::decode(reader)?;
+            if index == END_OF_METADATA {
+                return Ok(Self(entries));
+            }
+            let value = MetadataValue::read(protocol, reader)?;
+            let opaque = matches!(value, MetadataValue::Opaque { .. });
+            entries.push(MetadataEntry { index, value });
+            if opaque {
+                return Ok(Self(entries));
+            }
+        }
+    }
+}
+
+impl MapEncodable for EntityMetadata {
+    fn encode_mapped<W: Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(&self.to_bytes(protocol)?)?;
+        Ok(())
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        VarInt::try_from(self.to_bytes(protocol)?.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for EntityMetadata {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        tokio::io::AsyncWriteExt::write_all(writer, &self.to_bytes(protocol)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for EntityMetadata {
+    /// Only types every protocol has, opaque values cannot be written for another protocol.
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::fields::PrefixedOption;
+        use crate::registry::round_trip::sample;
+        let entries = (0..sample(runner, 0..4u8))
+            .map(|index| {
+                let value = match sample(runner, 0..10u8) {
+                    0 => MetadataValue::Byte(i8::arbitrary_field(runner)),
+                    1 => MetadataValue::VarInt(VarInt::arbitrary_field(runner)),
+                    2 => MetadataValue::Float(f32::arbitrary_field(runner)),
+                    3 => MetadataValue::String(MetadataString::arbitrary_field(runner)),
+                    4 => MetadataValue::OptChat(PrefixedOption::<Component>::arbitrary_field(runner).0),
+                    5 => MetadataValue::Rotation([f32::arbitrary_field(runner); 3]),
+                    6 => MetadataValue::OptPosition(Some(Position::arbitrary_field(runner))),
+                    7 => MetadataValue::OptUuid(Some(uuid::Uuid::arbitrary_field(runner))),
+                    8 => MetadataValue::Nbt(Nbt::arbitrary_field(runner)),
+                    _ => MetadataValue::OptVarInt(Some(VarInt::from(sample(runner, 0..i32::MAX)))),
+                };
+                MetadataEntry { index: index * 2, value }
+            })
+            .collect();
+        Self(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn encode(metadata: &EntityMetadata, protocol: MCProtocol) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        metadata.encode_mapped(protocol, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn type_ids_shift_between_versions() {
+        let mut metadata = EntityMetadata::default();
+        metadata.set(0, MetadataValue::Byte(0x20));
+        metadata.set(6, MetadataValue::Pose(VarInt::from(5)));
+        metadata.set(2, MetadataValue::OptChat(Some(Component::text("Guide"))));
+
+        // Pose is type 18 before 1.19.3 and 20 after, where VarLong and BlockState come first.
+        assert_eq!(&encode(&metadata, MCProtocol::V1_18)[..6], &[0, 0, 0x20, 6, 18, 5]);
+        assert_eq!(&encode(&metadata, MCProtocol::V1_20_2)[..6], &[0, 0, 0x20, 6, 20, 5]);
+        assert_eq!(&encode(&metadata, MCProtocol::V1_20_5)[..6], &[0, 0, 0x20, 6, 21, 5]);
+
+        for protocol in [MCProtocol::V1_18, MCProtocol::V1_20_3] {
+            let bytes = encode(&metadata, protocol);
+            let decoded = EntityMetadata::decode_mapped(protocol, &mut Cursor::new(&bytes)).unwrap();
+            assert!(matches!(decoded.get(2), Some(MetadataValue::OptChat(Some(name))) if *name == Component::text("Guide")));
+            assert_eq!(encode(&decoded, protocol), bytes);
+        }
+
+        metadata.set(7, MetadataValue::VarLong(-1));
+        assert!(metadata.encode_mapped(MCProtocol::V1_19, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn optional_var_ints_are_checked() {
+        // Type 17 is an optional VarInt on 1.18, sent one higher, and this one is i32::MIN.
+        let bytes = [0, 17, 0x80, 0x80, 0x80, 0x80, 0x08, END_OF_METADATA];
+        assert!(EntityMetadata::decode_mapped(MCProtocol::V1_18, &mut Cursor::new(&bytes)).is_err());
+
+        for value in [i32::MAX, -1] {
+            let mut metadata = EntityMetadata::default();
+            metadata.set(0, MetadataValue::OptVarInt(Some(VarInt::from(value))));
+            assert!(metadata.encode_mapped(MCProtocol::V1_18, &mut Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn particles_are_kept_opaque() {
+        // A byte, a 1.20.2 slot holding one stone, then a particle which takes the rest.
+        let bytes = [0, 0, 0x01, 5, 7, 1, 1, 1, 0, 8, 17, 3, END_OF_METADATA];
+        let mut metadata = EntityMetadata::decode_mapped(MCProtocol::V1_20_2, &mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(metadata.0.len(), 3);
+        assert!(matches!(&metadata.get(5), Some(MetadataValue::Slot(Slot(Some(item)))) if item.count == 1));
+        assert!(matches!(&metadata.get(8), Some(MetadataValue::Opaque { type_id, data }) if **type_id == 17 && data.len() == 2));
+        assert_eq!(encode(&metadata, MCProtocol::V1_20_2), bytes);
+
+        metadata.set(1, MetadataValue::Boolean(true));
+        assert!(matches!(metadata.0[2].value, MetadataValue::Boolean(true)));
+        metadata.0.swap(2, 3);
+        assert!(metadata.encode_mapped(MCProtocol::V1_20_2, &mut Vec::new()).is_err());
+    }
+}
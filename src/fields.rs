@@ -0,0 +1,694 @@
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{Debug, Formatter};
+use std::io::Read;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use tokio::io::AsyncWriteExt;
+
+/// The largest length accepted by default, matching the largest packet the protocol allows.
+pub const DEFAULT_MAX_LENGTH: usize = 2097152;
+
+/// Never reserve more than this up front, a length prefix is untrusted until its elements arrive.
+pub(crate) const MAX_PREALLOCATION: usize = 4096;
+
+/// A type which can prefix a collection with its length.
+pub trait LengthPrefix: Decodable + Encodable + AsyncEncodable + Send + Sync {
+    fn from_length(length: usize) -> anyhow::Result<Self>;
+
+    fn to_length(&self) -> anyhow::Result<usize>;
+}
+
+impl LengthPrefix for VarInt {
+    fn from_length(length: usize) -> anyhow::Result<Self> {
+        VarInt::try_from(length)
+            .map_err(|_| anyhow::anyhow!("Length {} does not fit a VarInt.", length))
+    }
+
+    fn to_length(&self) -> anyhow::Result<usize> {
+        (*self)
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Found negative length {}.", self))
+    }
+}
+
+macro_rules! primitive_length_prefix {
+    ($($primitive:ty),*) => {
+        $(
+            impl LengthPrefix for $primitive {
+                fn from_length(length: usize) -> anyhow::Result<Self> {
+                    <$primitive>::try_from(length)
+                        .map_err(|_| anyhow::anyhow!("Length {} does not fit a {}.", length, stringify!($primitive)))
+                }
+
+                fn to_length(&self) -> anyhow::Result<usize> {
+                    usize::try_from(*self).map_err(|_| anyhow::anyhow!("Found negative length {}.", self))
+                }
+            }
+        )*
+    };
+}
+
+primitive_length_prefix!(u8, u16, i16, i32);
+
+fn check_length(length: usize, max_length: usize) -> anyhow::Result<()> {
+    if length > max_length {
+        anyhow::bail!("Length {} exceeds the maximum of {}.", length, max_length);
+    }
+    Ok(())
+}
+
+/// Reads a VarLong, which `minecraft_data_types` has no codec for.
+pub(crate) fn read_var_long<R: Read>(reader: &mut R) -> anyhow::Result<i64> {
+    let mut value = 0u64;
+    for index in 0..10 {
+        let byte = u8::decode(reader)?;
+        value |= u64::from(byte & 0x7F) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok(value as i64);
+        }
+    }
+    anyhow::bail!("VarLong is longer than 10 bytes.")
+}
+
+pub(crate) fn var_long_bytes(value: i64) -> Vec<u8> {
+    let mut value = value as u64;
+    let mut bytes = Vec::with_capacity(10);
+    loop {
+        if value < 0x80 {
+            bytes.push(value as u8);
+            return bytes;
+        }
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+/// A collection prefixed by its length, the prefix is computed when encoding.
+pub struct Prefixed<L: LengthPrefix, V, const MAX: usize = DEFAULT_MAX_LENGTH> {
+    value: V,
+    _prefix: PhantomData<L>,
+}
+
+impl<L: LengthPrefix, V, const MAX: usize> Prefixed<L, V, MAX> {
+    pub fn new(value: V) -> Self {
+        Self {
+            value,
+            _prefix: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> V {
+        self.value
+    }
+}
+
+impl<L: LengthPrefix, V, const MAX: usize> From<V> for Prefixed<L, V, MAX> {
+    fn from(value: V) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<L: LengthPrefix, V, const MAX: usize> Deref for Prefixed<L, V, MAX> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.value
+    }
+}
+
+impl<L: LengthPrefix, V, const MAX: usize> DerefMut for Prefixed<L, V, MAX> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.value
+    }
+}
+
+impl<L: LengthPrefix, V: Debug, const MAX: usize> Debug for Prefixed<L, V, MAX> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<L: LengthPrefix, V: Clone, const MAX: usize> Clone for Prefixed<L, V, MAX> {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<L: LengthPrefix, V: PartialEq, const MAX: usize> PartialEq for Prefixed<L, V, MAX> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<L: LengthPrefix, V: Default, const MAX: usize> Default for Prefixed<L, V, MAX> {
+    fn default() -> Self {
+        Self::new(V::default())
+    }
+}
+
+impl<L: LengthPrefix, T: Decodable, const MAX: usize> Decodable for Prefixed<L, Vec<T>, MAX> {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let length = L::decode(reader)?.to_length()?;
+        check_length(length, MAX)?;
+        let mut values = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+        for _ in 0..length {
+            values.push(T::decode(reader)?);
+        }
+        Ok(Self::new(values))
+    }
+}
+
+impl<L: LengthPrefix, T: Encodable, const MAX: usize> Encodable for Prefixed<L, Vec<T>, MAX> {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        check_length(self.value.len(), MAX)?;
+        L::from_length(self.value.len())?.encode(writer)?;
+        for value in &self.value {
+            value.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        let mut size = L::from_length(self.value.len())?.size()?;
+        for value in &self.value {
+            size += value.size()?;
+        }
+        Ok(size)
+    }
+}
+
+#[async_trait::async_trait]
+impl<L: LengthPrefix, T: AsyncEncodable + Send + Sync, const MAX: usize> AsyncEncodable
+    for Prefixed<L, Vec<T>, MAX>
+{
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        check_length(self.value.len(), MAX)?;
+        L::from_length(self.value.len())?
+            .async_encode(writer)
+            .await?;
+        for value in &self.value {
+            value.async_encode(writer).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<L: LengthPrefix, T: MapDecodable, const MAX: usize> MapDecodable for Prefixed<L, Vec<T>, MAX> {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        let length = L::decode(reader)?.to_length()?;
+        check_length(length, MAX)?;
+        let mut values = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+        for _ in 0..length {
+            values.push(T::decode_mapped(protocol, reader)?);
+        }
+        Ok(Self::new(values))
+    }
+}
+
+impl<L: LengthPrefix, T: MapEncodable, const MAX: usize> MapEncodable for Prefixed<L, Vec<T>, MAX> {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        check_length(self.value.len(), MAX)?;
+        L::from_length(self.value.len())?.encode(writer)?;
+        for value in &self.value {
+            value.encode_mapped(protocol, writer)?;
+        }
+        Ok(())
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        let mut size = L::from_length(self.value.len())?.size()?;
+        for value in &self.value {
+            size += value.size_mapped(protocol)?;
+        }
+        Ok(size)
+    }
+}
+
+#[async_trait::async_trait]
+impl<L: LengthPrefix, T: AsyncMapEncodable + Send + Sync, const MAX: usize> AsyncMapEncodable
+    for Prefixed<L, Vec<T>, MAX>
+{
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        check_length(self.value.len(), MAX)?;
+        L::from_length(self.value.len())?
+            .async_encode(writer)
+            .await?;
+        for value in &self.value {
+            value.encode_mapped_async(protocol, writer).await?;
+        }
+        Ok(())
+    }
+}
+
+/// An optional value prefixed by a boolean.
+#[derive(Debug, Clone)]
+pub struct PrefixedOption<T>(pub Option<T>);
+
+impl<T> Default for PrefixedOption<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T> From<Option<T>> for PrefixedOption<T> {
+    fn from(value: Option<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Deref for PrefixedOption<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Option<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for PrefixedOption<T> {
+    fn deref_mut(&mut self) -> &mut Option<T> {
+        &mut self.0
+    }
+}
+
+impl<T: Decodable> Decodable for PrefixedOption<T> {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        if bool::decode(reader)? {
+            Ok(Self(Some(T::decode(reader)?)))
+        } else {
+            Ok(Self(None))
+        }
+    }
+}
+
+impl<T: Encodable> Encodable for PrefixedOption<T> {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.0.is_some().encode(writer)?;
+        if let Some(value) = &self.0 {
+            value.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        match &self.0 {
+            Some(value) => Ok(value.size()? + 1),
+            None => Ok(VarInt::from(1)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: AsyncEncodable + Send + Sync> AsyncEncodable for PrefixedOption<T> {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.0.is_some().async_encode(writer).await?;
+        if let Some(value) = &self.0 {
+            value.async_encode(writer).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: MapDecodable> MapDecodable for PrefixedOption<T> {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if bool::decode(reader)? {
+            Ok(Self(Some(T::decode_mapped(protocol, reader)?)))
+        } else {
+            Ok(Self(None))
+        }
+    }
+}
+
+impl<T: MapEncodable> MapEncodable for PrefixedOption<T> {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.0.is_some().encode(writer)?;
+        if let Some(value) = &self.0 {
+            value.encode_mapped(protocol, writer)?;
+        }
+        Ok(())
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        match &self.0 {
+            Some(value) => Ok(value.size_mapped(protocol)? + 1),
+            None => Ok(VarInt::from(1)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: AsyncMapEncodable + Send + Sync> AsyncMapEncodable for PrefixedOption<T> {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.0.is_some().async_encode(writer).await?;
+        if let Some(value) = &self.0 {
+            value.encode_mapped_async(protocol, writer).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Every byte left in the packet, only valid as the last field.
+#[derive(Clone, Default)]
+pub struct RemainingBytes<const MAX: usize = DEFAULT_MAX_LENGTH>(pub Vec<u8>);
+
+impl<const MAX: usize> Debug for RemainingBytes<MAX> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RemainingBytes({} bytes)", self.0.len())
+    }
+}
+
+impl<const MAX: usize> From<Vec<u8>> for RemainingBytes<MAX> {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const MAX: usize> Deref for RemainingBytes<MAX> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl<const MAX: usize> Decodable for RemainingBytes<MAX> {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.take(MAX as u64 + 1).read_to_end(&mut bytes)?;
+        check_length(bytes.len(), MAX)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl<const MAX: usize> Encodable for RemainingBytes<MAX> {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        check_length(self.0.len(), MAX)?;
+        writer.write_all(&self.0)?;
+        Ok(())
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        VarInt::try_from(self.0.len())
+            .map_err(|_| anyhow::anyhow!("Length {} does not fit a VarInt.", self.0.len()))
+    }
+}
+
+#[async_trait::async_trait]
+impl<const MAX: usize> AsyncEncodable for RemainingBytes<MAX> {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        check_length(self.0.len(), MAX)?;
+        writer.write_all(&self.0).await?;
+        Ok(())
+    }
+}
+
+/// A fixed number of bytes without a length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedArray<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Default for FixedArray<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for FixedArray<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<const N: usize> Deref for FixedArray<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Decodable for FixedArray<N> {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let mut bytes = [0; N];
+        reader.read_exact(&mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl<const N: usize> Encodable for FixedArray<N> {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(&self.0)?;
+        Ok(())
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        VarInt::try_from(N).map_err(|_| anyhow::anyhow!("Length {} does not fit a VarInt.", N))
+    }
+}
+
+#[async_trait::async_trait]
+impl<const N: usize> AsyncEncodable for FixedArray<N> {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        writer.write_all(&self.0).await?;
+        Ok(())
+    }
+}
+
+/// A block position packed into an `i64` as 26 bits of x, 26 bits of z and 12 bits of y.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    fn pack(&self) -> i64 {
+        ((self.x as i64 & 0x3FFFFFF) << 38) | ((self.z as i64 & 0x3FFFFFF) << 12) | (self.y as i64 & 0xFFF)
+    }
+
+    fn unpack(packed: i64) -> Self {
+        Self {
+            x: (packed >> 38) as i32,
+            y: (packed << 52 >> 52) as i32,
+            z: (packed << 26 >> 38) as i32,
+        }
+    }
+}
+
+impl Decodable for Position {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self::unpack(i64::decode(reader)?))
+    }
+}
+
+impl Encodable for Position {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.pack().encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(VarInt::from(8))
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for Position {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.pack().async_encode(writer).await
+    }
+}
+
+/// A chunk section position packed into an `i64` as 22 bits of x, 22 bits of z and 20 bits of y.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectionPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl SectionPosition {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The section containing a block.
+    pub fn of(position: Position) -> Self {
+        Self::new(position.x >> 4, position.y >> 4, position.z >> 4)
+    }
+
+    fn pack(&self) -> i64 {
+        ((self.x as i64 & 0x3FFFFF) << 42) | ((self.z as i64 & 0x3FFFFF) << 20) | (self.y as i64 & 0xFFFFF)
+    }
+
+    fn unpack(packed: i64) -> Self {
+        Self {
+            x: (packed >> 42) as i32,
+            y: (packed << 44 >> 44) as i32,
+            z: (packed << 22 >> 42) as i32,
+        }
+    }
+}
+
+impl Decodable for SectionPosition {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self::unpack(i64::decode(reader)?))
+    }
+}
+
+impl Encodable for SectionPosition {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.pack().encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(VarInt::from(8))
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for SectionPosition {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.pack().async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+mod arbitrary {
+    use super::*;
+    use crate::registry::round_trip::{sample, ArbitraryField};
+    use proptest::prelude::*;
+    use proptest::test_runner::TestRunner;
+
+    impl<L: LengthPrefix, T: ArbitraryField, const MAX: usize> ArbitraryField
+        for Prefixed<L, Vec<T>, MAX>
+    {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            let length = sample(runner, 0..16usize.min(MAX + 1));
+            Self::new((0..length).map(|_| T::arbitrary_field(runner)).collect())
+        }
+    }
+
+    impl<T: ArbitraryField> ArbitraryField for PrefixedOption<T> {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            if bool::arbitrary_field(runner) {
+                Self(Some(T::arbitrary_field(runner)))
+            } else {
+                Self(None)
+            }
+        }
+    }
+
+    impl<const MAX: usize> ArbitraryField for RemainingBytes<MAX> {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            Self(sample(
+                runner,
+                proptest::collection::vec(any::<u8>(), 0..256usize.min(MAX + 1)),
+            ))
+        }
+    }
+
+    impl<const N: usize> ArbitraryField for FixedArray<N> {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            let mut bytes = [0; N];
+            bytes
+                .iter_mut()
+                .for_each(|byte| *byte = u8::arbitrary_field(runner));
+            Self(bytes)
+        }
+    }
+
+    impl ArbitraryField for Position {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            Self {
+                x: sample(runner, -(1 << 25)..(1 << 25)),
+                y: sample(runner, -(1 << 11)..(1 << 11)),
+                z: sample(runner, -(1 << 25)..(1 << 25)),
+            }
+        }
+    }
+
+    impl ArbitraryField for SectionPosition {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            Self {
+                x: sample(runner, -(1 << 21)..(1 << 21)),
+                y: sample(runner, -(1 << 19)..(1 << 19)),
+                z: sample(runner, -(1 << 21)..(1 << 21)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn prefixed_rejects_lengths_over_the_maximum() {
+        let mut bytes = Vec::new();
+        VarInt::from(5).encode(&mut bytes).unwrap();
+        bytes.extend_from_slice(&[0; 5]);
+        assert!(Prefixed::<VarInt, Vec<u8>, 4>::decode(&mut Cursor::new(&bytes)).is_err());
+        assert!(Prefixed::<VarInt, Vec<u8>, 4>::new(vec![0; 5])
+            .encode(&mut Vec::new())
+            .is_err());
+        assert_eq!(
+            Prefixed::<VarInt, Vec<u8>, 5>::decode(&mut Cursor::new(&bytes))
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn remaining_bytes_rejects_lengths_over_the_maximum() {
+        assert!(RemainingBytes::<4>::decode(&mut Cursor::new(vec![0; 5])).is_err());
+        assert_eq!(
+            RemainingBytes::<4>::decode(&mut Cursor::new(vec![0; 4]))
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn position_packs_negative_coordinates() {
+        let position = Position::new(-33554432, -2048, 33554431);
+        let mut bytes = Vec::new();
+        position.encode(&mut bytes).unwrap();
+        assert_eq!(bytes, [0x80, 0x00, 0x00, 0x1F, 0xFF, 0xFF, 0xF8, 0x00]);
+        assert_eq!(Position::decode(&mut Cursor::new(&bytes)).unwrap(), position);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::chat::Component;
+use crate::packet::{EncodedPacket, IdentifiedPacket, OutboundPacket};
+use crate::protocol_version::{MCProtocol, MapDecodable};
+use crate::registry::play::{client_bound, server_bound};
+use flume::{Receiver, Sender};
+use minecraft_data_types::{encoder::Decodable, nums::VarInt};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+
+/// The translation key vanilla clients show when a keep-alive times out.
+const TIMEOUT_REASON: &str = "disconnect.timeout";
+/// Shown when the client echoes a keep-alive it was not sent, which vanilla has no key for.
+const UNEXPECTED_RESPONSE_REASON: &str = "Received an unexpected keep-alive.";
+
+#[derive(Debug, Copy, Clone)]
+pub struct KeepAliveConfig {
+    /// How often a keep-alive is sent.
+    pub interval: Duration,
+    /// How long the client has to echo a keep-alive, checked whenever the next one is due.
+    pub timeout: Duration,
+}
+
+impl Default for KeepAliveConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(15),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Shares the latency measured by a running keep-alive service.
+#[derive(Clone, Default)]
+pub struct KeepAliveHandle {
+    latency: Arc<Mutex<Option<Duration>>>,
+}
+
+impl KeepAliveHandle {
+    /// The round trip time of the last echoed keep-alive, `None` until the first one.
+    pub fn latency(&self) -> Option<Duration> {
+        *self.latency.lock().expect("Keep-alive latency was poisoned.")
+    }
+
+    fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().expect("Keep-alive latency was poisoned.") = Some(latency);
+    }
+}
+
+struct PendingKeepAlive {
+    keep_alive_id: i64,
+    sent_at: Instant,
+}
+
+/// Sends play keep-alives through the outbound sender returned by [`crate::packet::spin`] and
+/// answers them from its inbound packets.
+///
+/// Every inbound packet other than a keep-alive is forwarded unchanged. The client is sent a
+/// `Disconnect` and the service ends with an error when it echoes the wrong id or does not echo
+/// in time, dropping the forwarding sender.
+pub fn spawn_keep_alive(
+    protocol: MCProtocol,
+    config: KeepAliveConfig,
+    outbound: Sender<OutboundPacket>,
+    inbound: Receiver<Cursor<Vec<u8>>>,
+    forward: Sender<Cursor<Vec<u8>>>,
+) -> (KeepAliveHandle, JoinHandle<anyhow::Result<()>>) {
+    let handle = KeepAliveHandle::default();
+    let task_handle = handle.clone();
+    let join_handle = tokio::task::spawn(async move {
+        let response_id = server_bound::KeepAlive::packet_id(protocol)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported protocol {:?} for keep-alives.", protocol))?;
+        let mut interval = tokio::time::interval(config.interval);
+        let mut pending: Option<PendingKeepAlive> = None;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Some(pending) = &pending {
+                        if pending.sent_at.elapsed() >= config.timeout {
+                            let reason = Component::translate(TIMEOUT_REASON, Vec::new());
+                            disconnect(protocol, &outbound, reason).await?;
+                            anyhow::bail!("Keep-alive {} timed out.", pending.keep_alive_id);
+                        }
+                        continue;
+                    }
+                    let keep_alive_id = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+                    let packet = EncodedPacket::from_writable(&client_bound::KeepAlive { keep_alive_id }, protocol)?;
+                    outbound.send_async(OutboundPacket::from(Arc::new(packet))).await?;
+                    pending = Some(PendingKeepAlive {
+                        keep_alive_id,
+                        sent_at: Instant::now(),
+                    });
+                }
+                packet = inbound.recv_async() => {
+                    let mut packet = match packet {
+                        Ok(packet) => packet,
+                        Err(_) => return Ok(()),
+                    };
+                    if *VarInt::decode(&mut packet)? != *response_id {
+                        packet.set_position(0);
+                        if forward.send_async(packet).await.is_err() {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+
+                    let response = server_bound::KeepAlive::decode_mapped(protocol, &mut packet)?;
+                    match pending.take() {
+                        Some(expected) if expected.keep_alive_id == response.keep_alive_id => {
+                            task_handle.set_latency(expected.sent_at.elapsed());
+                        }
+                        _ => {
+                            disconnect(protocol, &outbound, Component::text(UNEXPECTED_RESPONSE_REASON)).await?;
+                            anyhow::bail!("Received unexpected keep-alive {}.", response.keep_alive_id);
+                        }
+                    }
+                }
+            }
+        }
+    });
+    (handle, join_handle)
+}
+
+async fn disconnect(protocol: MCProtocol, outbound: &Sender<OutboundPacket>, reason: Component) -> anyhow::Result<()> {
+    let disconnect = client_bound::Disconnect { reason };
+    let packet = EncodedPacket::from_writable(&disconnect, protocol)?;
+    outbound.send_async(OutboundPacket::from(Arc::new(packet))).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Service {
+        outbound: Receiver<OutboundPacket>,
+        inbound: Sender<Cursor<Vec<u8>>>,
+        join_handle: JoinHandle<anyhow::Result<()>>,
+        _forwarded: Receiver<Cursor<Vec<u8>>>,
+    }
+
+    fn start(protocol: MCProtocol) -> Service {
+        let (outbound_sender, outbound) = flume::unbounded();
+        let (inbound, inbound_receiver) = flume::unbounded();
+        let (forward, _forwarded) = flume::unbounded();
+        let config = KeepAliveConfig {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_millis(50),
+        };
+        let (_, join_handle) = spawn_keep_alive(protocol, config, outbound_sender, inbound_receiver, forward);
+        Service {
+            outbound,
+            inbound,
+            join_handle,
+            _forwarded,
+        }
+    }
+
+    async fn next<T: MapDecodable + IdentifiedPacket>(service: &Service, protocol: MCProtocol) -> T {
+        let packet = match service.outbound.recv_async().await.unwrap() {
+            OutboundPacket::Encoded(packet) => packet,
+            OutboundPacket::Resolved(_) => panic!("Keep-alives should be sent encoded."),
+        };
+        assert_eq!(Some(packet.packet_id()), T::packet_id(protocol));
+        let mut cursor = Cursor::new(packet.raw_bytes().unwrap());
+        VarInt::decode(&mut cursor).unwrap();
+        T::decode_mapped(protocol, &mut cursor).unwrap()
+    }
+
+    #[tokio::test]
+    async fn unanswered_keep_alives_time_out() {
+        let protocol = MCProtocol::V1_20_5;
+        let service = start(protocol);
+        next::<client_bound::KeepAlive>(&service, protocol).await;
+
+        let disconnect = next::<client_bound::Disconnect>(&service, protocol).await;
+        assert!(format!("{:?}", disconnect).contains(TIMEOUT_REASON));
+        let error = service.join_handle.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn wrong_keep_alive_ids_disconnect() {
+        let protocol = MCProtocol::V1_20_5;
+        let service = start(protocol);
+        let keep_alive = next::<client_bound::KeepAlive>(&service, protocol).await;
+
+        let response = server_bound::KeepAlive {
+            keep_alive_id: keep_alive.keep_alive_id + 1,
+        };
+        let raw_bytes = EncodedPacket::from_writable(&response, protocol).unwrap().raw_bytes().unwrap();
+        service.inbound.send_async(Cursor::new(raw_bytes)).await.unwrap();
+
+        let disconnect = next::<client_bound::Disconnect>(&service, protocol).await;
+        let reason = format!("{:?}", disconnect);
+        assert!(reason.contains(UNEXPECTED_RESPONSE_REASON));
+        assert!(!reason.contains(TIMEOUT_REASON));
+        let error = service.join_handle.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("unexpected keep-alive"));
+    }
+}
@@ -1,7 +1,37 @@
 #![feature(trait_alias)]
+// Packets repeat `#[packet(id = ...)]` for each version range, which clippy mistakes for duplicates.
+#![allow(clippy::duplicated_attributes)]
 
+pub use mc_packet_protocol_derive::Packet;
+
+#[cfg(feature = "play")]
+pub mod boss_bar;
 pub mod buffer;
+pub mod capture;
+pub mod chat;
+#[cfg(feature = "play")]
+pub mod chunk;
 pub mod encryption;
+#[cfg(feature = "play")]
+pub mod entity_metadata;
+pub mod fields;
+#[cfg(feature = "play")]
+pub mod keep_alive;
+pub mod nbt;
 pub mod packet;
 pub mod protocol_version;
 pub mod registry;
+#[cfg(feature = "play")]
+pub mod sidebar;
+#[cfg(feature = "play")]
+pub mod signed_chat;
+#[cfg(feature = "play")]
+pub mod slot;
+#[cfg(feature = "play")]
+pub mod tab_list;
+#[cfg(feature = "play")]
+pub mod teleport;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "play")]
+pub mod title;
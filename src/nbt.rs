@@ -0,0 +1,457 @@
+use crate::fields::MAX_PREALLOCATION;
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use tokio::io::AsyncWriteExt;
+
+/// Nesting deeper than this is rejected, vanilla uses the same limit.
+const MAX_DEPTH: usize = 512;
+
+/// A single NBT value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    /// Only valid as a root, where it marks an absent value.
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// Empty lists are written with the `End` element type.
+    List(Vec<Tag>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+/// Named tags in the order they were read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Compound(pub Vec<(String, Tag)>);
+
+impl Compound {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        self.0.iter().find(|(key, _)| key == name).map(|(_, tag)| tag)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Tag> {
+        self.0.iter_mut().find(|(key, _)| key == name).map(|(_, tag)| tag)
+    }
+
+    /// Replaces the tag with the same name or appends a new one.
+    pub fn insert<S: Into<String>>(&mut self, name: S, tag: Tag) {
+        let name = name.into();
+        match self.get_mut(&name) {
+            Some(existing) => *existing = tag,
+            None => self.0.push((name, tag)),
+        }
+    }
+
+    pub fn with<S: Into<String>>(mut self, name: S, tag: Tag) -> Self {
+        self.insert(name, tag);
+        self
+    }
+}
+
+impl Tag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Tag::End => 0,
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(compound) => Some(compound),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn read_payload<R: Read>(id: u8, reader: &mut R, depth: usize) -> anyhow::Result<Tag> {
+        if depth > MAX_DEPTH {
+            anyhow::bail!("NBT nested deeper than {}.", MAX_DEPTH);
+        }
+        Ok(match id {
+            0 => Tag::End,
+            1 => Tag::Byte(i8::from_be_bytes(read_array(reader)?)),
+            2 => Tag::Short(i16::from_be_bytes(read_array(reader)?)),
+            3 => Tag::Int(i32::from_be_bytes(read_array(reader)?)),
+            4 => Tag::Long(i64::from_be_bytes(read_array(reader)?)),
+            5 => Tag::Float(f32::from_be_bytes(read_array(reader)?)),
+            6 => Tag::Double(f64::from_be_bytes(read_array(reader)?)),
+            7 => Tag::ByteArray(read_elements(reader, |reader| Ok(i8::from_be_bytes(read_array(reader)?)))?),
+            8 => Tag::String(read_string(reader)?),
+            9 => {
+                let element_id = read_array::<R, 1>(reader)?[0];
+                let length = read_length(reader)?;
+                if element_id == 0 && length > 0 {
+                    anyhow::bail!("NBT list of {} end tags.", length);
+                }
+                let mut elements = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+                for _ in 0..length {
+                    elements.push(Tag::read_payload(element_id, reader, depth + 1)?);
+                }
+                Tag::List(elements)
+            }
+            10 => {
+                let mut compound = Compound::new();
+                loop {
+                    let id = read_array::<R, 1>(reader)?[0];
+                    if id == 0 {
+                        break;
+                    }
+                    let name = read_string(reader)?;
+                    compound.0.push((name, Tag::read_payload(id, reader, depth + 1)?));
+                }
+                Tag::Compound(compound)
+            }
+            11 => Tag::IntArray(read_elements(reader, |reader| Ok(i32::from_be_bytes(read_array(reader)?)))?),
+            12 => Tag::LongArray(read_elements(reader, |reader| Ok(i64::from_be_bytes(read_array(reader)?)))?),
+            id => anyhow::bail!("Unknown NBT tag id {}.", id),
+        })
+    }
+
+    fn write_payload<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        match self {
+            Tag::End => {}
+            Tag::Byte(value) => writer.write_all(&value.to_be_bytes())?,
+            Tag::Short(value) => writer.write_all(&value.to_be_bytes())?,
+            Tag::Int(value) => writer.write_all(&value.to_be_bytes())?,
+            Tag::Long(value) => writer.write_all(&value.to_be_bytes())?,
+            Tag::Float(value) => writer.write_all(&value.to_be_bytes())?,
+            Tag::Double(value) => writer.write_all(&value.to_be_bytes())?,
+            Tag::ByteArray(values) => {
+                write_length(writer, values.len())?;
+                for value in values {
+                    writer.write_all(&value.to_be_bytes())?;
+                }
+            }
+            Tag::String(value) => write_string(writer, value)?,
+            Tag::List(elements) => {
+                let element_id = elements.first().map_or(0, Tag::id);
+                if elements.iter().any(|element| element.id() != element_id || element_id == 0) {
+                    anyhow::bail!("NBT lists must hold a single tag type other than end.");
+                }
+                writer.write_all(&[element_id])?;
+                write_length(writer, elements.len())?;
+                for element in elements {
+                    element.write_payload(writer)?;
+                }
+            }
+            Tag::Compound(compound) => {
+                for (name, tag) in &compound.0 {
+                    if let Tag::End = tag {
+                        anyhow::bail!("NBT compounds cannot hold end tags.");
+                    }
+                    writer.write_all(&[tag.id()])?;
+                    write_string(writer, name)?;
+                    tag.write_payload(writer)?;
+                }
+                writer.write_all(&[0])?;
+            }
+            Tag::IntArray(values) => {
+                write_length(writer, values.len())?;
+                for value in values {
+                    writer.write_all(&value.to_be_bytes())?;
+                }
+            }
+            Tag::LongArray(values) => {
+                write_length(writer, values.len())?;
+                for value in values {
+                    writer.write_all(&value.to_be_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_length<R: Read>(reader: &mut R) -> anyhow::Result<usize> {
+    let length = i32::from_be_bytes(read_array(reader)?);
+    usize::try_from(length).map_err(|_| anyhow::anyhow!("Found negative NBT length {}.", length))
+}
+
+fn write_length<W: Write>(writer: &mut W, length: usize) -> anyhow::Result<()> {
+    let length = i32::try_from(length).map_err(|_| anyhow::anyhow!("NBT length {} does not fit an int.", length))?;
+    writer.write_all(&length.to_be_bytes())?;
+    Ok(())
+}
+
+fn read_elements<R: Read, T>(reader: &mut R, read: fn(&mut R) -> anyhow::Result<T>) -> anyhow::Result<Vec<T>> {
+    let length = read_length(reader)?;
+    let mut values = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+    for _ in 0..length {
+        values.push(read(reader)?);
+    }
+    Ok(values)
+}
+
+/// Reads a string in Java's modified UTF-8, which encodes nul as two bytes and characters
+/// outside the basic multilingual plane as surrogate pairs.
+fn read_string<R: Read>(reader: &mut R) -> anyhow::Result<String> {
+    let length = u16::from_be_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+
+    let mut units = Vec::with_capacity(length);
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let (unit, width) = match byte {
+            0x01..=0x7F => (byte as u16, 1),
+            0xC0..=0xDF if index + 1 < bytes.len() => {
+                (((byte as u16 & 0x1F) << 6) | (bytes[index + 1] as u16 & 0x3F), 2)
+            }
+            0xE0..=0xEF if index + 2 < bytes.len() => (
+                ((byte as u16 & 0x0F) << 12) | ((bytes[index + 1] as u16 & 0x3F) << 6) | (bytes[index + 2] as u16 & 0x3F),
+                3,
+            ),
+            _ => anyhow::bail!("Invalid modified UTF-8 in NBT string."),
+        };
+        units.push(unit);
+        index += width;
+    }
+    String::from_utf16(&units).map_err(|_| anyhow::anyhow!("Invalid surrogate pair in NBT string."))
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> anyhow::Result<()> {
+    let mut bytes = Vec::with_capacity(value.len());
+    for unit in value.encode_utf16() {
+        match unit {
+            0x01..=0x7F => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    let length = u16::try_from(bytes.len())
+        .map_err(|_| anyhow::anyhow!("NBT string of {} bytes is too long.", bytes.len()))?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// A root NBT value as sent in packets.
+///
+/// `Decodable` and `Encodable` use the nameless root of 1.20.2 and later, fields of older packets
+/// should be marked `#[field(mapped)]` so the root name is read and written for their protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nbt {
+    /// The root name, always empty in vanilla and dropped from 1.20.2.
+    pub name: String,
+    pub root: Tag,
+}
+
+impl Default for Nbt {
+    fn default() -> Self {
+        Self::from(Compound::new())
+    }
+}
+
+impl From<Compound> for Nbt {
+    fn from(compound: Compound) -> Self {
+        Self {
+            name: String::new(),
+            root: Tag::Compound(compound),
+        }
+    }
+}
+
+impl From<Tag> for Nbt {
+    fn from(root: Tag) -> Self {
+        Self {
+            name: String::new(),
+            root,
+        }
+    }
+}
+
+impl Nbt {
+    fn has_named_root(protocol: MCProtocol) -> bool {
+        protocol < MCProtocol::V1_20_2
+    }
+
+    fn read<R: Read>(reader: &mut R, named: bool) -> anyhow::Result<Self> {
+        let id = read_array::<R, 1>(reader)?[0];
+        let name = if named && id != 0 {
+            read_string(reader)?
+        } else {
+            String::new()
+        };
+        Ok(Self {
+            name,
+            root: Tag::read_payload(id, reader, 0)?,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, named: bool) -> anyhow::Result<()> {
+        writer.write_all(&[self.root.id()])?;
+        if named && self.root.id() != 0 {
+            write_string(writer, &self.name)?;
+        }
+        self.root.write_payload(writer)
+    }
+
+    fn to_bytes(&self, named: bool) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, named)?;
+        Ok(bytes)
+    }
+
+    fn size_of(&self, named: bool) -> anyhow::Result<VarInt> {
+        let length = self.to_bytes(named)?.len();
+        VarInt::try_from(length).map_err(|_| anyhow::anyhow!("NBT of {} bytes does not fit a VarInt.", length))
+    }
+}
+
+impl Decodable for Nbt {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Self::read(reader, false)
+    }
+}
+
+impl Encodable for Nbt {
+    fn encode<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.write(writer, false)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        self.size_of(false)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for Nbt {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(&self, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(&self.to_bytes(false)?).await?;
+        Ok(())
+    }
+}
+
+impl MapDecodable for Nbt {
+    fn decode_mapped<R: Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Self::read(reader, Self::has_named_root(protocol))
+    }
+}
+
+impl MapEncodable for Nbt {
+    fn encode_mapped<W: Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.write(writer, Self::has_named_root(protocol))
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        self.size_of(Self::has_named_root(protocol))
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for Nbt {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        writer
+            .write_all(&self.to_bytes(Self::has_named_root(protocol))?)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for Nbt {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        let mut compound = Compound::new();
+        for index in 0..sample(runner, 0..4usize) {
+            let tag = match sample(runner, 0..5u8) {
+                0 => Tag::Byte(i8::arbitrary_field(runner)),
+                1 => Tag::Int(i32::arbitrary_field(runner)),
+                2 => Tag::String(sample(runner, "[a-z\u{0}\u{e9}\u{1F600}]{0,8}")),
+                3 => Tag::List((0..sample(runner, 0..3usize)).map(|_| Tag::Long(i64::arbitrary_field(runner))).collect()),
+                _ => Tag::Compound(Compound::new().with("value", Tag::Double(f64::arbitrary_field(runner)))),
+            };
+            compound.insert(format!("tag{}", index), tag);
+        }
+        Nbt::from(compound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn root_name_depends_on_protocol() {
+        let nbt = Nbt::from(Compound::new().with("text", Tag::String(String::from("hi"))));
+        let mut legacy = Vec::new();
+        nbt.encode_mapped(MCProtocol::V1_18, &mut legacy).unwrap();
+        let mut modern = Vec::new();
+        nbt.encode_mapped(MCProtocol::V1_20_2, &mut modern).unwrap();
+        assert_eq!(&legacy[..3], &[10, 0, 0]);
+        assert_eq!(&legacy[3..], &modern[1..]);
+        assert_eq!(Nbt::decode_mapped(MCProtocol::V1_18, &mut Cursor::new(legacy)).unwrap(), nbt);
+    }
+
+    #[test]
+    fn strings_use_modified_utf8() {
+        let nbt = Nbt::from(Tag::String(String::from("\u{0}\u{1F600}")));
+        let bytes = nbt.to_bytes(false).unwrap();
+        assert_eq!(bytes, vec![8, 0, 8, 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(Nbt::decode(&mut Cursor::new(bytes)).unwrap(), nbt);
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let nested = |depth: usize| {
+            let mut bytes = vec![9];
+            for _ in 0..depth {
+                bytes.extend_from_slice(&[9, 0, 0, 0, 1]);
+            }
+            bytes.extend_from_slice(&[0, 0, 0, 0, 0]);
+            Nbt::decode(&mut Cursor::new(bytes))
+        };
+        assert!(nested(MAX_DEPTH).is_ok());
+        assert!(nested(MAX_DEPTH + 1).is_err());
+    }
+}
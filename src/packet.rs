@@ -1,10 +1,12 @@
 use crate::buffer::BufferState;
+use crate::capture::{CaptureRecorder, Direction};
 use crate::protocol_version::{MCProtocol, MapEncodable};
 use anyhow::Context;
 use flate2::bufread::ZlibEncoder;
 use flate2::Compression;
 use flume::Sender;
 use minecraft_data_types::{encoder::*, nums::VarInt};
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{Debug, Display, Formatter};
 use std::io::{Read, Seek};
@@ -19,8 +21,42 @@ pub trait WritablePacket: MapEncodable {
     fn to_resolved_packet(&self, protocol: MCProtocol) -> anyhow::Result<ResolvedPacket>;
 }
 
+pub trait IdentifiedPacket {
+    /// The id of this packet for the given protocol, or `None` if the protocol does not have it.
+    fn packet_id(protocol: MCProtocol) -> Option<VarInt>;
+}
+
+/// The encoding stage of a [`ResolvedPacket`]'s frame.
+///
+/// The raw packet body is never modified, so moving between stages is always possible and a
+/// packet can be framed again for a writer with a different compression threshold.
+#[derive(Clone)]
+pub enum PacketStage {
+    /// `length | id | data`, used when compression is disabled.
+    Raw,
+    /// `length | 0 | id | data`, used when compression is enabled but the packet is below the threshold.
+    FramedUncompressed,
+    /// `length | data length | zlib(id | data)`.
+    FramedCompressed {
+        packet_length: VarInt,
+        compressed: Vec<u8>,
+    },
+}
+
+impl Debug for PacketStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PacketStage::Raw => f.write_str("Raw"),
+            PacketStage::FramedUncompressed => f.write_str("FramedUncompressed"),
+            PacketStage::FramedCompressed { packet_length, .. } => {
+                write!(f, "FramedCompressed({})", packet_length)
+            }
+        }
+    }
+}
+
 pub struct ResolvedPacket {
-    compression_data: Option<(VarInt, VarInt)>,
+    stage: PacketStage,
     packet_id: VarInt,
     uncompressed_length: VarInt,
     packet: Vec<u8>,
@@ -28,7 +64,7 @@ pub struct ResolvedPacket {
 
 impl Display for ResolvedPacket {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "(ID={}[{:x}], {})", self.packet_id, Into::<i32>::into(self.packet_id), self.uncompressed_length)
+        write!(f, "(ID={}[{:x}], {}, {:?})", self.packet_id, Into::<i32>::into(self.packet_id), self.uncompressed_length, self.stage)
     }
 }
 
@@ -41,7 +77,7 @@ impl Debug for ResolvedPacket {
 impl ResolvedPacket {
     pub fn new(packet_id: VarInt, packet: Vec<u8>) -> anyhow::Result<Self> {
         Ok(Self {
-            compression_data: None,
+            stage: PacketStage::Raw,
             packet_id,
             uncompressed_length: packet_id.size()? + VarInt::try_from(packet.len())?,
             packet,
@@ -55,7 +91,7 @@ impl ResolvedPacket {
         std::io::Read::read_to_end(&mut cursor, &mut packet)?;
         log::trace!("Generated packet from cursor: len {}, packet_id: {}, packet_len: {}", packet_size + VarInt::try_from(packet.len())?, packet_id, packet.len());
         Ok(Self {
-            compression_data: None,
+            stage: PacketStage::Raw,
             packet_id,
             uncompressed_length: packet_size + VarInt::try_from(packet.len())?,
             packet,
@@ -73,100 +109,253 @@ impl ResolvedPacket {
         ResolvedPacket::new(packet_id, packet)
     }
 
+    pub fn packet_id(&self) -> VarInt {
+        self.packet_id
+    }
+
+    pub fn stage(&self) -> &PacketStage {
+        &self.stage
+    }
+
+    /// The uncompressed packet id followed by the packet body.
+    pub fn raw_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(usize::try_from(self.uncompressed_length)?);
+        self.packet_id.encode(&mut bytes)?;
+        bytes.extend_from_slice(&self.packet);
+        Ok(bytes)
+    }
+
+    /// Frames the packet for a connection with the given compression threshold, or without
+    /// compression if `None`.
+    pub fn frame_for(&mut self, compression_threshold: Option<i32>) -> anyhow::Result<()> {
+        match compression_threshold {
+            Some(compression_threshold) => self.compress(compression_threshold),
+            None => {
+                self.stage = PacketStage::Raw;
+                Ok(())
+            }
+        }
+    }
+
+    /// Frames the packet for a compressing connection, this is idempotent and only compresses
+    /// the body if it is not already compressed.
     pub fn compress(&mut self, compression_threshold: i32) -> anyhow::Result<()> {
-        let mut new_packet = Vec::with_capacity(usize::try_from(self.uncompressed_length)?);
-        self.packet_id.encode(&mut new_packet)?;
-        new_packet.append(&mut self.packet);
-
-        if self.uncompressed_length > compression_threshold {
-            log::trace!(
-                "Compressing packet of length {} for threshold {}",
-                self.uncompressed_length,
-                compression_threshold
-            );
-
-            let mut encoder = ZlibEncoder::new(new_packet.as_slice(), Compression::default());
-
-            let mut compressed = Vec::new();
-            encoder.read_to_end(&mut compressed)?;
-
-            self.compression_data = Some((
-                VarInt::try_from(compressed.len())? + self.uncompressed_length.size()?,
-                self.uncompressed_length,
-            ));
-            self.packet = compressed;
-        } else {
-            log::trace!(
-                "Not compressing packet of length {} for threshold {}",
-                self.uncompressed_length,
-                compression_threshold
-            );
-            self.compression_data = Some((self.uncompressed_length + 1, VarInt::from(0)));
-            self.packet = new_packet;
+        let should_compress = self.uncompressed_length > compression_threshold;
+        let already_framed = matches!(
+            (&self.stage, should_compress),
+            (PacketStage::FramedCompressed { .. }, true) | (PacketStage::FramedUncompressed, false)
+        );
+        match (already_framed, should_compress) {
+            (true, _) => {
+                log::trace!(
+                    "Packet of length {} already framed for threshold {}",
+                    self.uncompressed_length,
+                    compression_threshold
+                );
+            }
+            (false, true) => {
+                log::trace!(
+                    "Compressing packet of length {} for threshold {}",
+                    self.uncompressed_length,
+                    compression_threshold
+                );
+
+                let uncompressed = self.raw_bytes()?;
+                let mut encoder = ZlibEncoder::new(uncompressed.as_slice(), Compression::default());
+
+                let mut compressed = Vec::new();
+                encoder.read_to_end(&mut compressed)?;
+
+                self.stage = PacketStage::FramedCompressed {
+                    packet_length: VarInt::try_from(compressed.len())? + self.uncompressed_length.size()?,
+                    compressed,
+                };
+            }
+            (false, false) => {
+                log::trace!(
+                    "Not compressing packet of length {} for threshold {}",
+                    self.uncompressed_length,
+                    compression_threshold
+                );
+                self.stage = PacketStage::FramedUncompressed;
+            }
         }
         Ok(())
     }
 
     pub fn write<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
-        if let Some((packet_length, data_length)) = self.compression_data {
-            log::trace!(
-                "Compression Encoding ({}, {}) for {}",
-                packet_length,
-                data_length,
-                self.packet.len()
-            );
-            packet_length.encode(writer)?;
-            data_length.encode(writer)?;
-            writer.write_all(&self.packet)?; // the packet will include the ID if compressed
-            Ok(())
-        } else {
-            self.uncompressed_length.encode(writer)?;
-            self.packet_id.encode(writer)?;
-            writer.write_all(&self.packet)?;
-            Ok(())
+        match &self.stage {
+            PacketStage::Raw => {
+                self.uncompressed_length.encode(writer)?;
+                self.packet_id.encode(writer)?;
+                writer.write_all(&self.packet)?;
+            }
+            PacketStage::FramedUncompressed => {
+                (self.uncompressed_length + 1).encode(writer)?;
+                VarInt::from(0).encode(writer)?;
+                self.packet_id.encode(writer)?;
+                writer.write_all(&self.packet)?;
+            }
+            PacketStage::FramedCompressed { packet_length, compressed } => {
+                log::trace!(
+                    "Compression Encoding ({}, {}) for {}",
+                    packet_length,
+                    self.uncompressed_length,
+                    compressed.len()
+                );
+                packet_length.encode(writer)?;
+                self.uncompressed_length.encode(writer)?;
+                writer.write_all(compressed)?; // the compressed body includes the ID
+            }
         }
+        Ok(())
     }
 
     pub async fn write_async<W: tokio::io::AsyncWrite + Send + Unpin>(
         &self,
         writer: &mut W,
     ) -> anyhow::Result<()> {
-        if let Some((packet_length, data_length)) = self.compression_data {
-            log::trace!(
-                "Compression Encoding ({}, {}) for {} => {}",
-                packet_length,
-                data_length,
-                self.packet.len(),
-                self.packet_id,
-            );
-            packet_length.async_encode(writer).await?;
-            data_length.async_encode(writer).await?;
-            writer.write_all(&self.packet).await?; // the packet will include the ID if compressed
-            Ok(())
-        } else {
-            log::trace!(
-                "No compression, Encoding ({}) for {} => {}",
-                self.uncompressed_length,
-                self.packet.len(),
-                self.packet_id,
-            );
-            self.uncompressed_length.async_encode(writer).await?;
-            self.packet_id.async_encode(writer).await?;
-            writer.write_all(&self.packet).await?;
-            Ok(())
+        match &self.stage {
+            PacketStage::Raw => {
+                log::trace!(
+                    "No compression, Encoding ({}) for {} => {}",
+                    self.uncompressed_length,
+                    self.packet.len(),
+                    self.packet_id,
+                );
+                self.uncompressed_length.async_encode(writer).await?;
+                self.packet_id.async_encode(writer).await?;
+                writer.write_all(&self.packet).await?;
+            }
+            PacketStage::FramedUncompressed => {
+                log::trace!(
+                    "Below threshold, Encoding ({}, 0) for {} => {}",
+                    self.uncompressed_length + 1,
+                    self.packet.len(),
+                    self.packet_id,
+                );
+                (self.uncompressed_length + 1).async_encode(writer).await?;
+                VarInt::from(0).async_encode(writer).await?;
+                self.packet_id.async_encode(writer).await?;
+                writer.write_all(&self.packet).await?;
+            }
+            PacketStage::FramedCompressed { packet_length, compressed } => {
+                log::trace!(
+                    "Compression Encoding ({}, {}) for {} => {}",
+                    packet_length,
+                    self.uncompressed_length,
+                    compressed.len(),
+                    self.packet_id,
+                );
+                packet_length.async_encode(writer).await?;
+                self.uncompressed_length.async_encode(writer).await?;
+                writer.write_all(compressed).await?; // the compressed body includes the ID
+            }
         }
+        Ok(())
     }
 
     pub fn size(&self) -> anyhow::Result<usize> {
-        if let Some((packet_length, _)) = self.compression_data {
-            (packet_length.size()? + packet_length)
-                .try_into()
-                .context("Failed to convert VarInt to usize.")
-        } else {
-            (self.uncompressed_length.size()? + self.uncompressed_length)
-                .try_into()
-                .context("Failed to convert VarInt to usize.")
+        let packet_length = match &self.stage {
+            PacketStage::Raw => self.uncompressed_length,
+            PacketStage::FramedUncompressed => self.uncompressed_length + 1,
+            PacketStage::FramedCompressed { packet_length, .. } => *packet_length,
+        };
+        (packet_length.size()? + packet_length)
+            .try_into()
+            .context("Failed to convert VarInt to usize.")
+    }
+}
+
+/// An immutable packet which can be shared between many writers, used for broadcasting.
+///
+/// Frames are built lazily for each compression threshold and cached, so the packet is only
+/// compressed once per threshold no matter how many writers send it.
+pub struct EncodedPacket {
+    packet_id: VarInt,
+    packet: Vec<u8>,
+    frames: std::sync::Mutex<HashMap<Option<i32>, Arc<Vec<u8>>>>,
+}
+
+impl Display for EncodedPacket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(ID={}[{:x}], {})", self.packet_id, Into::<i32>::into(self.packet_id), self.packet.len())
+    }
+}
+
+impl Debug for EncodedPacket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl EncodedPacket {
+    pub fn new(packet_id: VarInt, packet: Vec<u8>) -> Self {
+        Self {
+            packet_id,
+            packet,
+            frames: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_writable<T: WritablePacket>(writable: &T, protocol: MCProtocol) -> anyhow::Result<Self> {
+        Ok(Self::from_resolved(writable.to_resolved_packet(protocol)?))
+    }
+
+    pub fn from_resolved(packet: ResolvedPacket) -> Self {
+        Self::new(packet.packet_id, packet.packet)
+    }
+
+    pub fn packet_id(&self) -> VarInt {
+        self.packet_id
+    }
+
+    /// The uncompressed packet id followed by the packet body.
+    pub fn raw_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(self.packet.len() + 5);
+        self.packet_id.encode(&mut bytes)?;
+        bytes.extend_from_slice(&self.packet);
+        Ok(bytes)
+    }
+
+    /// Retrieves the unencrypted frame for the given compression threshold, building and caching
+    /// it on first use.
+    pub fn frame(&self, compression_threshold: Option<i32>) -> anyhow::Result<Arc<Vec<u8>>> {
+        let mut frames = self
+            .frames
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Encoded packet frame cache was poisoned."))?;
+        if let Some(frame) = frames.get(&compression_threshold) {
+            return Ok(Arc::clone(frame));
         }
+
+        let mut resolved = ResolvedPacket::new(self.packet_id, self.packet.clone())?;
+        resolved.frame_for(compression_threshold)?;
+        let mut frame = Vec::with_capacity(resolved.size()?);
+        resolved.write(&mut frame)?;
+
+        let frame = Arc::new(frame);
+        frames.insert(compression_threshold, Arc::clone(&frame));
+        Ok(frame)
+    }
+}
+
+/// A packet queued for a writer, either owned by the connection or shared across a broadcast.
+#[derive(Debug)]
+pub enum OutboundPacket {
+    Resolved(ResolvedPacket),
+    Encoded(Arc<EncodedPacket>),
+}
+
+impl From<ResolvedPacket> for OutboundPacket {
+    fn from(packet: ResolvedPacket) -> Self {
+        OutboundPacket::Resolved(packet)
+    }
+}
+
+impl From<Arc<EncodedPacket>> for OutboundPacket {
+    fn from(packet: Arc<EncodedPacket>) -> Self {
+        OutboundPacket::Encoded(packet)
     }
 }
 
@@ -178,6 +367,7 @@ pub struct PacketWriter<T: MovableAsyncWrite> {
     internal_writer: T,
     codec: Option<crate::encryption::Codec>,
     compression_threshold: Option<i32>,
+    recorder: Option<(CaptureRecorder, Direction)>,
 }
 
 impl<T: MovableAsyncWrite> PacketWriter<T> {
@@ -186,6 +376,7 @@ impl<T: MovableAsyncWrite> PacketWriter<T> {
             internal_writer,
             codec: None,
             compression_threshold: None,
+            recorder: None,
         }
     }
 
@@ -197,13 +388,25 @@ impl<T: MovableAsyncWrite> PacketWriter<T> {
         self.compression_threshold = Some(compression_threshold);
     }
 
+    /// Records every packet sent by this writer, before compression and encryption.
+    pub fn attach_recorder(&mut self, recorder: CaptureRecorder, direction: Direction) {
+        self.recorder = Some((recorder, direction));
+    }
+
+    fn record<F: FnOnce() -> anyhow::Result<Vec<u8>>>(&self, raw_bytes: F) {
+        if let Some((recorder, direction)) = &self.recorder {
+            if let Err(error) = raw_bytes().and_then(|bytes| recorder.record(*direction, &bytes)) {
+                log::warn!("Failed to record sent packet: {}", error);
+            }
+        }
+    }
+
     pub async fn send_resolved_packet(
         &mut self,
         packet: &mut ResolvedPacket,
     ) -> anyhow::Result<()> {
-        if let Some(compression) = self.compression_threshold {
-            packet.compress(compression)?;
-        }
+        self.record(|| packet.raw_bytes());
+        packet.frame_for(self.compression_threshold)?;
         if let Some(codec) = &mut self.codec {
             let mut buf = Vec::with_capacity(packet.size()?);
             packet.write_async(&mut buf).await?;
@@ -219,12 +422,38 @@ impl<T: MovableAsyncWrite> PacketWriter<T> {
                 .context("Failed to write non-encoded packet.")
         }
     }
+
+    pub async fn send_encoded_packet(&mut self, packet: &EncodedPacket) -> anyhow::Result<()> {
+        self.record(|| packet.raw_bytes());
+        let frame = packet.frame(self.compression_threshold)?;
+        if let Some(codec) = &mut self.codec {
+            let mut buf = frame.to_vec();
+            codec.encrypt(&mut buf);
+            self.internal_writer
+                .write_all(&buf)
+                .await
+                .context("Failed to write encoded packet.")
+        } else {
+            self.internal_writer
+                .write_all(&frame)
+                .await
+                .context("Failed to write non-encoded packet.")
+        }
+    }
+
+    pub async fn send_outbound_packet(&mut self, packet: &mut OutboundPacket) -> anyhow::Result<()> {
+        match packet {
+            OutboundPacket::Resolved(packet) => self.send_resolved_packet(packet).await,
+            OutboundPacket::Encoded(packet) => self.send_encoded_packet(packet).await,
+        }
+    }
 }
 
 pub struct PacketReader<T: MovableAsyncRead> {
     internal_reader: T,
     buffer: crate::buffer::MinecraftPacketBuffer,
     address: Arc<SocketAddr>,
+    recorder: Option<(CaptureRecorder, Direction)>,
 }
 
 impl<T: MovableAsyncRead> PacketReader<T> {
@@ -233,9 +462,15 @@ impl<T: MovableAsyncRead> PacketReader<T> {
             internal_reader,
             buffer: crate::buffer::MinecraftPacketBuffer::new(),
             address,
+            recorder: None,
         }
     }
 
+    /// Records every packet read by this reader, after decryption and decompression.
+    pub fn attach_recorder(&mut self, recorder: CaptureRecorder, direction: Direction) {
+        self.recorder = Some((recorder, direction));
+    }
+
     pub fn enable_decryption(&mut self, codec: crate::encryption::Codec) {
         self.buffer.enable_decryption(codec);
     }
@@ -259,7 +494,13 @@ impl<T: MovableAsyncRead> PacketReader<T> {
         loop {
             match self.poll() {
                 BufferState::PacketReady => {
-                    return self.buffer.packet_reader();
+                    let cursor = self.buffer.packet_reader()?;
+                    if let Some((recorder, direction)) = &self.recorder {
+                        if let Err(error) = recorder.record(*direction, cursor.get_ref()) {
+                            log::warn!(target: &self.address.to_string(), "Failed to record read packet: {}", error);
+                        }
+                    }
+                    return Ok(cursor);
                 }
                 BufferState::Waiting => {
                     log::trace!(target: &self.address.to_string(), "Buf read awaiting packet: Encoded {}, Decoded: {}", encoded, decoded);
@@ -318,6 +559,13 @@ impl<R: MovableAsyncRead, W: MovableAsyncWrite> PacketReadWriteLocker<R, W> {
         drop(write_lock);
         Ok(())
     }
+
+    pub async fn send_encoded_packet(&self, packet: &EncodedPacket) -> anyhow::Result<()> {
+        let mut write_lock = self.lock_writer().await;
+        write_lock.send_encoded_packet(packet).await?;
+        drop(write_lock);
+        Ok(())
+    }
 }
 
 pub fn spin<R: MovableAsyncRead, W: MovableAsyncWrite>(
@@ -325,7 +573,7 @@ pub fn spin<R: MovableAsyncRead, W: MovableAsyncWrite>(
     locker: Arc<PacketReadWriteLocker<R, W>>,
     sender: Sender<std::io::Cursor<Vec<u8>>>,
 ) -> (
-    Sender<ResolvedPacket>,
+    Sender<OutboundPacket>,
     JoinHandle<anyhow::Result<()>>,
     JoinHandle<anyhow::Result<()>>,
 ) {
@@ -349,12 +597,39 @@ pub fn spin<R: MovableAsyncRead, W: MovableAsyncWrite>(
         let target = format!("write/{}", write_identifier);
         log::trace!(target: &target, "Open write handle.");
         loop {
-            let mut next_packet = flume_read.recv()?;
+            let mut next_packet = flume_read.recv_async().await?;
             log::trace!(target: &target, "Write Handle: Next Packet: {:?}", next_packet);
             let mut write_lock = write.lock().await;
-            write_lock.send_resolved_packet(&mut next_packet).await?;
+            write_lock.send_outbound_packet(&mut next_packet).await?;
             drop(write_lock);
         }
     });
     (flume_write, read_handle, write_handle)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fresh_frame(packet_id: VarInt, packet: Vec<u8>, compression_threshold: Option<i32>) -> Vec<u8> {
+        let mut resolved = ResolvedPacket::new(packet_id, packet).unwrap();
+        resolved.frame_for(compression_threshold).unwrap();
+        let mut frame = Vec::new();
+        resolved.write(&mut frame).unwrap();
+        frame
+    }
+
+    #[test]
+    fn encoded_frames_are_cached_per_threshold() {
+        let body = vec![7u8; 512];
+        let packet = EncodedPacket::new(VarInt::from(0x22), body.clone());
+
+        for compression_threshold in [None, Some(256), Some(1024)] {
+            let frame = packet.frame(compression_threshold).unwrap();
+            assert_eq!(*frame, fresh_frame(VarInt::from(0x22), body.clone(), compression_threshold));
+            // The second call hands out the cached frame rather than encoding again.
+            assert!(Arc::ptr_eq(&frame, &packet.frame(compression_threshold).unwrap()));
+        }
+        assert_eq!(packet.frames.lock().unwrap().len(), 3);
+    }
+}
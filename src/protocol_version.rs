@@ -1,4 +1,4 @@
-use std::{cmp::{PartialOrd, PartialEq}, fmt::{Debug, Formatter, Write}};
+use std::{cmp::{PartialOrd, PartialEq}, fmt::{Debug, Display, Formatter, Write}};
 use minecraft_data_types::nums::VarInt;
 macro_rules! protocol {
     ($($string_name:literal => $protocol_version:literal as $protocol_identifier:ident,)*) => {
@@ -11,6 +11,9 @@ macro_rules! protocol {
         }
 
         impl MCProtocol {
+            /// Every known protocol, excluding `Illegal`.
+            pub const VARIANTS: &'static [MCProtocol] = &[$(MCProtocol::$protocol_identifier,)*];
+
             pub fn as_i32(&self) -> i32 {
                 match self {
                     $(
@@ -21,11 +24,11 @@ macro_rules! protocol {
             }
         }
 
-        impl ToString for MCProtocol {
-            fn to_string(&self) -> std::string::String {
+        impl Display for MCProtocol {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
                 match self {
-                    $(MCProtocol::$protocol_identifier => $string_name.to_string(),)*
-                    _ => String::from("Unknown"),
+                    $(MCProtocol::$protocol_identifier => f.write_str($string_name),)*
+                    _ => f.write_str("Unknown"),
                 }
             }
         }
@@ -78,6 +81,10 @@ protocol! {
     "Undefined" => 0 as Undefined,
     "1.17.1" => 756 as V1_17_1,
     "1.18" => 757 as V1_18,
+    "1.19" => 759 as V1_19,
+    "1.20.2" => 764 as V1_20_2,
+    "1.20.3" => 765 as V1_20_3,
+    "1.20.5" => 766 as V1_20_5,
 }
 
 pub trait MapDecodable: Sized {
@@ -0,0 +1,144 @@
+use crate::chat::Component;
+use crate::fields::{Prefixed, PrefixedOption, RemainingBytes};
+use crate::nbt::Nbt;
+use crate::registry;
+use crate::registry::{CookiePayload, TransferHost};
+use crate::Packet;
+use minecraft_data_types::auto_string;
+use minecraft_data_types::common::Identifier;
+use minecraft_data_types::nums::VarInt;
+
+auto_string!(ResourcePackUrl, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ResourcePackUrl, 255);
+
+auto_string!(ResourcePackHash, 40);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ResourcePackHash, 40);
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, versions = "1.20.5..")]
+pub struct CookieRequest {
+    pub key: Identifier,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x01, versions = "1.20.5..")]
+pub struct PluginMessage {
+    pub channel: Identifier,
+    pub data: RemainingBytes<1048576>,
+}
+
+/// Text components are sent as NBT from 1.20.3.
+#[derive(Debug, Packet)]
+#[packet(id = 0x01, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x02, versions = "1.20.5..")]
+pub struct Disconnect {
+    #[field(mapped)]
+    pub reason: Component,
+}
+
+/// Ends configuration, the connection enters play once the client acknowledges it.
+#[derive(Debug, Packet)]
+#[packet(id = 0x02, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x03, versions = "1.20.5..")]
+pub struct FinishConfiguration {}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x03, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x04, versions = "1.20.5..")]
+pub struct KeepAlive {
+    pub keep_alive_id: i64,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x04, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x05, versions = "1.20.5..")]
+pub struct Ping {
+    pub id: i32,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x06, versions = "1.20.5..")]
+pub struct ResetChat {}
+
+/// Every registry in one codec before 1.20.5, one registry per packet after.
+#[derive(Debug, Packet)]
+#[packet(id = 0x05, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x07, versions = "1.20.5..")]
+pub struct RegistryData {
+    #[field(until = "1.20.5")]
+    pub codec: Nbt,
+    #[field(since = "1.20.5", default = "Identifier::from(\"\")")]
+    pub registry_id: Identifier,
+    #[field(since = "1.20.5")]
+    pub entries: Prefixed<VarInt, Vec<super::RegistryEntry>>,
+}
+
+/// Removes the pack with the given id, or every pack without one.
+#[derive(Debug, Packet)]
+#[packet(id = 0x06, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x08, versions = "1.20.5..")]
+pub struct RemoveResourcePack {
+    pub uuid: PrefixedOption<uuid::Uuid>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x06, versions = "1.20.2")]
+#[packet(id = 0x07, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x09, versions = "1.20.5..")]
+pub struct AddResourcePack {
+    #[field(since = "1.20.3")]
+    pub uuid: uuid::Uuid,
+    pub url: ResourcePackUrl,
+    pub hash: ResourcePackHash,
+    pub forced: bool,
+    #[field(mapped)]
+    pub prompt: PrefixedOption<Component>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0A, versions = "1.20.5..")]
+pub struct StoreCookie {
+    pub key: Identifier,
+    pub payload: CookiePayload,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0B, versions = "1.20.5..")]
+pub struct Transfer {
+    pub host: TransferHost,
+    pub port: VarInt,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x07, versions = "1.20.2")]
+#[packet(id = 0x08, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x0C, versions = "1.20.5..")]
+pub struct FeatureFlags {
+    pub flags: Prefixed<VarInt, Vec<Identifier>>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0E, versions = "1.20.5..")]
+pub struct KnownPacks {
+    pub packs: Prefixed<VarInt, Vec<super::KnownPack>>,
+}
+
+registry! {
+    CookieRequest,
+    PluginMessage,
+    Disconnect,
+    FinishConfiguration,
+    KeepAlive,
+    Ping,
+    ResetChat,
+    RegistryData,
+    RemoveResourcePack,
+    AddResourcePack,
+    StoreCookie,
+    Transfer,
+    FeatureFlags,
+    KnownPacks,
+}
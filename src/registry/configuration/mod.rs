@@ -0,0 +1,147 @@
+use crate::fields::PrefixedOption;
+use crate::nbt::Nbt;
+use crate::strict_enum;
+use crate::strict_flags;
+use minecraft_data_types::auto_string;
+use minecraft_data_types::common::Identifier;
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+
+auto_string!(Locale, 16);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(Locale, 16);
+
+auto_string!(PackString, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(PackString, 64);
+
+strict_enum! {
+    @internal
+    ChatMode; VarInt {
+        0 => Enabled;
+        1 => CommandsOnly;
+        2 => Hidden;
+    }
+    MainHand; VarInt {
+        0 => Left;
+        1 => Right;
+    }
+}
+
+strict_flags! {
+    @internal
+    SkinParts; u8; Preserve {
+        0x01 => CAPE;
+        0x02 => JACKET;
+        0x04 => LEFT_SLEEVE;
+        0x08 => RIGHT_SLEEVE;
+        0x10 => LEFT_PANTS_LEG;
+        0x20 => RIGHT_PANTS_LEG;
+        0x40 => HAT;
+    }
+}
+
+/// One entry of a registry sent with `RegistryData` from 1.20.5, without data when the client
+/// already knows it from a shared known pack.
+#[derive(Debug)]
+pub struct RegistryEntry {
+    pub id: Identifier,
+    pub data: PrefixedOption<Nbt>,
+}
+
+impl Decodable for RegistryEntry {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: Identifier::decode(reader)?,
+            data: PrefixedOption::decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for RegistryEntry {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.id.encode(writer)?;
+        self.data.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.id.size()? + self.data.size()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for RegistryEntry {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.id.async_encode(writer).await?;
+        self.data.async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for RegistryEntry {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            id: Identifier::arbitrary_field(runner),
+            data: PrefixedOption::arbitrary_field(runner),
+        }
+    }
+}
+
+/// A data pack both sides may share, so its registry entries need not be sent.
+#[derive(Debug)]
+pub struct KnownPack {
+    pub namespace: PackString,
+    pub id: PackString,
+    pub version: PackString,
+}
+
+impl Decodable for KnownPack {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            namespace: PackString::decode(reader)?,
+            id: PackString::decode(reader)?,
+            version: PackString::decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for KnownPack {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.namespace.encode(writer)?;
+        self.id.encode(writer)?;
+        self.version.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.namespace.size()? + self.id.size()? + self.version.size()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for KnownPack {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.namespace.async_encode(writer).await?;
+        self.id.async_encode(writer).await?;
+        self.version.async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for KnownPack {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            namespace: PackString::arbitrary_field(runner),
+            id: PackString::arbitrary_field(runner),
+            version: PackString::arbitrary_field(runner),
+        }
+    }
+}
+
+pub mod client_bound;
+pub mod server_bound;
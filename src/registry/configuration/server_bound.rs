@@ -0,0 +1,96 @@
+use crate::fields::{Prefixed, PrefixedOption, RemainingBytes};
+use crate::registry;
+use crate::registry::CookiePayload;
+use crate::strict_enum;
+use crate::Packet;
+use minecraft_data_types::common::Identifier;
+use minecraft_data_types::nums::VarInt;
+
+strict_enum! {
+    @internal
+    ResourcePackResult; VarInt {
+        0 => SuccessfullyLoaded;
+        1 => Declined;
+        2 => FailedDownload;
+        3 => Accepted;
+        4 => Downloaded;
+        5 => InvalidUrl;
+        6 => FailedReload;
+        7 => Discarded;
+        _ => Unknown;
+    }
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, versions = "1.20.2..")]
+pub struct ClientInformation {
+    pub locale: super::Locale,
+    pub view_distance: i8,
+    pub chat_mode: super::ChatMode,
+    pub chat_colors: bool,
+    pub displayed_skin_parts: super::SkinParts,
+    pub main_hand: super::MainHand,
+    pub enable_text_filtering: bool,
+    pub allow_server_listings: bool,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x01, versions = "1.20.5..")]
+pub struct CookieResponse {
+    pub key: Identifier,
+    pub payload: PrefixedOption<CookiePayload>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x01, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x02, versions = "1.20.5..")]
+pub struct PluginMessage {
+    pub channel: Identifier,
+    pub data: RemainingBytes<32767>,
+}
+
+/// Acknowledges `FinishConfiguration`, the connection is in play once this is sent.
+#[derive(Debug, Packet)]
+#[packet(id = 0x02, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x03, versions = "1.20.5..")]
+pub struct AcknowledgeFinishConfiguration {}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x03, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x04, versions = "1.20.5..")]
+pub struct KeepAlive {
+    pub keep_alive_id: i64,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x04, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x05, versions = "1.20.5..")]
+pub struct Pong {
+    pub id: i32,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x05, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x06, versions = "1.20.5..")]
+pub struct ResourcePackResponse {
+    #[field(since = "1.20.3")]
+    pub uuid: uuid::Uuid,
+    pub result: ResourcePackResult,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x07, versions = "1.20.5..")]
+pub struct KnownPacks {
+    pub packs: Prefixed<VarInt, Vec<super::KnownPack>>,
+}
+
+registry! {
+    ClientInformation,
+    CookieResponse,
+    PluginMessage,
+    AcknowledgeFinishConfiguration,
+    KeepAlive,
+    Pong,
+    ResourcePackResponse,
+    KnownPacks,
+}
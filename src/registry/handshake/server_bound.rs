@@ -1,22 +1,32 @@
-use crate::create_registry;
+use crate::registry;
 use crate::strict_enum;
+use crate::Packet;
 use minecraft_data_types::auto_string;
+use minecraft_data_types::nums::VarInt;
 
 auto_string!(ServerAddress, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ServerAddress, 255);
 
 strict_enum! {
+    @internal
     NextState; minecraft_data_types::nums::VarInt {
         1 => Status;
         2 => Login;
+        3 => Transfer;
+        _ => Unknown;
     }
 }
 
-create_registry! {
-    Handshake {
-        protocol_version: minecraft_data_types::nums::VarInt,
-        server_address: ServerAddress,
-        server_port: u16,
-        next_state: NextState,
-        |LocalProtocol => (_) => (0x00);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x00)]
+pub struct Handshake {
+    pub protocol_version: VarInt,
+    pub server_address: ServerAddress,
+    pub server_port: u16,
+    pub next_state: NextState,
+}
+
+registry! {
+    Handshake,
 }
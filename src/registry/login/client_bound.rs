@@ -1,46 +1,74 @@
-use crate::create_registry;
+use crate::chat::Component;
+use crate::fields::{Prefixed, RemainingBytes};
+use crate::registry;
+use crate::Packet;
 use minecraft_data_types::auto_string;
-use minecraft_data_types::common::{Chat, Identifier};
+use minecraft_data_types::common::Identifier;
 use minecraft_data_types::nums::VarInt;
 use rand::rngs::OsRng;
 use rand::RngCore;
 use rsa::{PublicKeyParts, RsaPrivateKey, RsaPublicKey};
-use std::convert::TryFrom;
 
-pub type PublicKeyBytes = (VarInt, Vec<u8>);
+pub type PublicKeyBytes = Prefixed<VarInt, Vec<u8>>;
 
 auto_string!(ServerId, 20);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ServerId, 20);
 
-create_registry! {
-    Disconnect {
-        reason: Chat,
-        |LocalProtocol => (_) => (0x00);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x00)]
+pub struct Disconnect {
+    pub reason: Component,
+}
 
-    EncryptionRequest {
-        server_id: ServerId,
-        public_key: PublicKeyBytes,
-        verify_token: super::VerifyToken,
-        |LocalProtocol => (_) => (0x01);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x01)]
+pub struct EncryptionRequest {
+    pub server_id: ServerId,
+    pub public_key: PublicKeyBytes,
+    pub verify_token: super::VerifyToken,
+    #[field(since = "1.20.5")]
+    pub should_authenticate: bool,
+}
 
-    LoginSuccess {
-        uuid: uuid::Uuid,
-        username: super::LoginName,
-        |LocalProtocol => (_) => (0x02);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x02)]
+pub struct LoginSuccess {
+    pub uuid: uuid::Uuid,
+    pub username: super::LoginName,
+    #[field(since = "1.19")]
+    pub properties: Prefixed<VarInt, Vec<super::Property>>,
+    #[field(since = "1.20.5")]
+    pub strict_error_handling: bool,
+}
 
-    SetCompression {
-        threshold: VarInt,
-        |LocalProtocol => (_) => (0x03);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x03)]
+pub struct SetCompression {
+    pub threshold: VarInt,
+}
 
-    LoginPluginRequest {
-        message_id: VarInt,
-        channel: Identifier,
-        data: Vec<u8>,
-        |LocalProtocol => (_) => (0x04);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x04)]
+pub struct LoginPluginRequest {
+    pub message_id: VarInt,
+    pub channel: Identifier,
+    pub data: RemainingBytes<1048576>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x05, versions = "1.20.5..")]
+pub struct CookieRequest {
+    pub key: Identifier,
+}
+
+registry! {
+    Disconnect,
+    EncryptionRequest,
+    LoginSuccess,
+    SetCompression,
+    LoginPluginRequest,
+    CookieRequest,
 }
 
 impl EncryptionRequest {
@@ -65,8 +93,9 @@ impl EncryptionRequest {
             public_key,
             Self {
                 server_id: ServerId::from(server_id),
-                public_key: (VarInt::try_from(pem.len())?, pem),
-                verify_token: (VarInt::from(4), verify_token),
+                public_key: PublicKeyBytes::from(pem),
+                verify_token: super::VerifyToken::from(verify_token),
+                should_authenticate: true,
             },
         ))
     }
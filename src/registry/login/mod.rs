@@ -1,8 +1,114 @@
+use crate::fields::Prefixed;
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
 use minecraft_data_types::auto_string;
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
 use minecraft_data_types::nums::VarInt;
 
+pub use crate::registry::{Property, PropertyString};
+
 auto_string!(LoginName, 16);
-pub type VerifyToken = (VarInt, Vec<u8>);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(LoginName, 16);
+pub type VerifyToken = Prefixed<VarInt, Vec<u8>>;
+
+/// How `EncryptionResponse` proves the client holds the server's key.
+///
+/// 1.19 clients with a chat signing key sign the verify token instead of encrypting it, every
+/// other version only sends the encrypted token.
+#[derive(Debug)]
+pub enum VerifyTokenResponse {
+    Encrypted(VerifyToken),
+    Signed {
+        salt: i64,
+        message_signature: Prefixed<VarInt, Vec<u8>>,
+    },
+}
+
+impl VerifyTokenResponse {
+    fn has_choice(protocol: MCProtocol) -> bool {
+        protocol == MCProtocol::V1_19
+    }
+
+    fn check_supported(&self, protocol: MCProtocol) -> anyhow::Result<()> {
+        if matches!(self, Self::Signed { .. }) && !Self::has_choice(protocol) {
+            anyhow::bail!("Signed verify tokens are only sent on 1.19, not {:?}.", protocol);
+        }
+        Ok(())
+    }
+}
+
+impl MapDecodable for VerifyTokenResponse {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if !Self::has_choice(protocol) || bool::decode(reader)? {
+            Ok(Self::Encrypted(VerifyToken::decode(reader)?))
+        } else {
+            Ok(Self::Signed {
+                salt: i64::decode(reader)?,
+                message_signature: Prefixed::decode(reader)?,
+            })
+        }
+    }
+}
+
+impl MapEncodable for VerifyTokenResponse {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.check_supported(protocol)?;
+        if Self::has_choice(protocol) {
+            matches!(self, Self::Encrypted(_)).encode(writer)?;
+        }
+        match self {
+            Self::Encrypted(verify_token) => verify_token.encode(writer),
+            Self::Signed { salt, message_signature } => {
+                salt.encode(writer)?;
+                message_signature.encode(writer)
+            }
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        self.check_supported(protocol)?;
+        let choice = VarInt::from(i32::from(Self::has_choice(protocol)));
+        match self {
+            Self::Encrypted(verify_token) => Ok(choice + verify_token.size()?),
+            Self::Signed { message_signature, .. } => Ok(choice + 8 + message_signature.size()?),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for VerifyTokenResponse {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.check_supported(protocol)?;
+        if Self::has_choice(protocol) {
+            matches!(self, Self::Encrypted(_)).async_encode(writer).await?;
+        }
+        match self {
+            Self::Encrypted(verify_token) => verify_token.async_encode(writer).await,
+            Self::Signed { salt, message_signature } => {
+                salt.async_encode(writer).await?;
+                message_signature.async_encode(writer).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for VerifyTokenResponse {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        if bool::arbitrary_field(runner) {
+            Self::Encrypted(VerifyToken::arbitrary_field(runner))
+        } else {
+            Self::Signed {
+                salt: i64::arbitrary_field(runner),
+                message_signature: Prefixed::arbitrary_field(runner),
+            }
+        }
+    }
+}
 
 pub mod client_bound;
 pub mod server_bound;
@@ -1,31 +1,61 @@
-use crate::create_registry;
+use crate::fields::{Prefixed, PrefixedOption, RemainingBytes};
+use crate::registry;
+use crate::registry::CookiePayload;
+use crate::Packet;
+use minecraft_data_types::common::Identifier;
 use minecraft_data_types::nums::VarInt;
 use rand::rngs::OsRng;
 use rand::RngCore;
 use rsa::pkcs1::FromRsaPublicKey;
 use rsa::{PaddingScheme, PublicKey, RsaPublicKey};
-use std::convert::TryFrom;
 
-pub type SharedSecret = (VarInt, Vec<u8>);
+pub type SharedSecret = Prefixed<VarInt, Vec<u8>>;
 
-create_registry! {
-    LoginStart {
-        name: super::LoginName,
-        |LocalProtocol => (_) => (0x00);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x00)]
+pub struct LoginStart {
+    pub name: super::LoginName,
+    /// The chat signing key, sent with `PlayerSession` once in play from 1.19.1.
+    #[field(versions = "1.19")]
+    pub public_key: PrefixedOption<crate::registry::PlayerPublicKey>,
+    #[field(since = "1.20.2")]
+    pub uuid: uuid::Uuid,
+}
 
-    EncryptionResponse {
-        shared_secret: SharedSecret,
-        verify_token: super::VerifyToken,
-        |LocalProtocol => (_) => (0x01);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x01)]
+pub struct EncryptionResponse {
+    pub shared_secret: SharedSecret,
+    #[field(mapped)]
+    pub verify_token: super::VerifyTokenResponse,
+}
 
-    LoginPluginResponse {
-        message_id: VarInt,
-        successful: bool,
-        data: Vec<u8>,
-        |LocalProtocol => (_) => (0x02);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x02)]
+pub struct LoginPluginResponse {
+    pub message_id: VarInt,
+    pub successful: bool,
+    pub data: RemainingBytes<1048576>,
+}
+
+/// Acknowledges `LoginSuccess`, the connection is in configuration once this is sent.
+#[derive(Debug, Packet)]
+#[packet(id = 0x03, versions = "1.20.2..")]
+pub struct LoginAcknowledged {}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x04, versions = "1.20.5..")]
+pub struct CookieResponse {
+    pub key: Identifier,
+    pub payload: PrefixedOption<CookiePayload>,
+}
+
+registry! {
+    LoginStart,
+    EncryptionResponse,
+    LoginPluginResponse,
+    LoginAcknowledged,
+    CookieResponse,
 }
 
 impl EncryptionResponse {
@@ -45,14 +75,10 @@ impl EncryptionResponse {
         Ok((
             shared_secret,
             Self {
-                shared_secret: (
-                    VarInt::try_from(encrypted_shared_secret.len())?,
-                    encrypted_shared_secret,
-                ),
-                verify_token: (
-                    VarInt::try_from(encrypted_verify_token.len())?,
+                shared_secret: SharedSecret::from(encrypted_shared_secret),
+                verify_token: super::VerifyTokenResponse::Encrypted(super::VerifyToken::from(
                     encrypted_verify_token,
-                ),
+                )),
             },
         ))
     }
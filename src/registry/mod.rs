@@ -1,15 +1,149 @@
 use crate::protocol_version::{MCProtocol, MapDecodable};
 use anyhow::Context;
+use crate::fields::{Prefixed, PrefixedOption};
 use crate::packet::ResolvedPacket;
+use minecraft_data_types::auto_string;
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
 
+#[cfg(feature = "configuration")]
+pub mod configuration;
 #[cfg(feature = "handshake")]
 pub mod handshake;
 #[cfg(feature = "login")]
 pub mod login;
 #[cfg(feature = "play")]
 pub mod play;
+#[cfg(test)]
+pub(crate) mod round_trip;
 #[cfg(feature = "status")]
 pub mod status;
+#[cfg(all(feature = "handshake", feature = "login", feature = "configuration", feature = "play"))]
+mod transition;
+
+/// The payload of a cookie stored on the client, shared by every state's cookie packets.
+pub type CookiePayload = Prefixed<VarInt, Vec<u8>, 5120>;
+
+auto_string!(TransferHost, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(TransferHost, 255);
+
+/// A player's chat signing key from their Mojang profile, sent at login in 1.19 and with
+/// `PlayerSession` afterwards.
+#[derive(Debug, Clone)]
+pub struct PlayerPublicKey {
+    /// Milliseconds since the epoch.
+    pub expires_at: i64,
+    /// The X.509 encoded RSA key.
+    pub key: Prefixed<VarInt, Vec<u8>, 512>,
+    /// Mojang's signature over the key and expiry.
+    pub key_signature: Prefixed<VarInt, Vec<u8>, 4096>,
+}
+
+impl Decodable for PlayerPublicKey {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            expires_at: i64::decode(reader)?,
+            key: Prefixed::decode(reader)?,
+            key_signature: Prefixed::decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for PlayerPublicKey {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.expires_at.encode(writer)?;
+        self.key.encode(writer)?;
+        self.key_signature.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.expires_at.size()? + self.key.size()? + self.key_signature.size()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for PlayerPublicKey {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.expires_at.async_encode(writer).await?;
+        self.key.async_encode(writer).await?;
+        self.key_signature.async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+impl round_trip::ArbitraryField for PlayerPublicKey {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            expires_at: i64::arbitrary_field(runner),
+            key: round_trip::ArbitraryField::arbitrary_field(runner),
+            key_signature: round_trip::ArbitraryField::arbitrary_field(runner),
+        }
+    }
+}
+
+auto_string!(PropertyString, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(PropertyString, 64);
+
+/// A signed game profile property, such as `textures`.
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub name: PropertyString,
+    pub value: PropertyString,
+    pub signature: PrefixedOption<PropertyString>,
+}
+
+impl Decodable for Property {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let name = PropertyString::decode(reader)?;
+        let value = PropertyString::decode(reader)?;
+        let signature = PrefixedOption::decode(reader)?;
+        Ok(Self {
+            name,
+            value,
+            signature,
+        })
+    }
+}
+
+impl Encodable for Property {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.name.encode(writer)?;
+        self.value.encode(writer)?;
+        self.signature.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.name.size()? + self.value.size()? + self.signature.size()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for Property {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.name.async_encode(writer).await?;
+        self.value.async_encode(writer).await?;
+        self.signature.async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+impl round_trip::ArbitraryField for Property {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            name: PropertyString::arbitrary_field(runner),
+            value: PropertyString::arbitrary_field(runner),
+            signature: PrefixedOption::arbitrary_field(runner),
+        }
+    }
+}
 
 pub trait LazyHandle<T: MapDecodable> {
     fn decode_type(self) -> anyhow::Result<T>;
@@ -61,13 +195,55 @@ pub trait RegistryBase<H: Send + Sync> {
     ) -> anyhow::Result<()>;
 }
 
+/// Declares an enum over an index type, unknown indices fail to decode unless the enum opts in
+/// to a catch-all variant holding the raw index with `_ => Unknown;`. Only `Debug` is derived,
+/// attributes before an enum's name such as further derives are applied to it.
+///
+/// ```ignore
+/// strict_enum! {
+///     NextState; VarInt {
+///         1 => Status;
+///         2 => Login;
+///         _ => Unknown;
+///     }
+/// }
+/// ```
+///
+/// Registries of this crate declare theirs with a leading `@internal`, which also implements
+/// `ArbitraryField` for the round trip tests.
 #[macro_export]
 macro_rules! strict_enum {
-    ($($enum_name:ident; $index_type:ty { $($byte_representation:literal => $option_name:ident;)* })*) => {
+    (@unknown $enum_name:ident, $index:ident, $unknown_name:ident) => {
+        Ok($enum_name::$unknown_name($index))
+    };
+    (@unknown $enum_name:ident, $index:ident,) => {
+        anyhow::bail!("Failed to decode index {} for {}.", $index, stringify!($enum_name))
+    };
+    (@internal $($(#[$attr:meta])* $enum_name:ident; $index_type:ty { $($byte_representation:literal => $option_name:ident;)* $(_ => $unknown_name:ident;)? })*) => {
+        $crate::strict_enum! {
+            $($(#[$attr])* $enum_name; $index_type { $($byte_representation => $option_name;)* $(_ => $unknown_name;)? })*
+        }
+        $(
+            #[cfg(test)]
+            impl $crate::registry::round_trip::ArbitraryField for $enum_name {
+                fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+                    let mut variants = vec![
+                        $($enum_name::$option_name,)*
+                        $($enum_name::$unknown_name($crate::registry::round_trip::ArbitraryField::arbitrary_field(runner)),)?
+                    ];
+                    let index = $crate::registry::round_trip::sample(runner, 0..variants.len());
+                    variants.swap_remove(index)
+                }
+            }
+        )*
+    };
+    ($($(#[$attr:meta])* $enum_name:ident; $index_type:ty { $($byte_representation:literal => $option_name:ident;)* $(_ => $unknown_name:ident;)? })*) => {
         $(
+            $(#[$attr])*
             #[derive(Debug)]
             pub enum $enum_name {
                 $($option_name,)*
+                $($unknown_name($index_type),)?
             }
 
             impl minecraft_data_types::encoder::Decodable for $enum_name {
@@ -77,7 +253,7 @@ macro_rules! strict_enum {
                         $(
                             $byte_representation => Ok($enum_name::$option_name),
                         )*
-                        _ => anyhow::bail!("Failed to decode index {} for {}.", index, stringify!($enum_name)),
+                        _ => $crate::strict_enum!(@unknown $enum_name, index, $($unknown_name)?),
                     }
                 }
             }
@@ -91,6 +267,9 @@ macro_rules! strict_enum {
                                 Ok(())
                             }
                         )*
+                        $(
+                            $enum_name::$unknown_name(index) => <$index_type>::encode(index, writer),
+                        )?
                     }
                 }
 
@@ -102,6 +281,9 @@ macro_rules! strict_enum {
                                 Ok(size)
                             }
                         )*
+                        $(
+                            $enum_name::$unknown_name(index) => <$index_type>::size(index),
+                        )?
                     }
                 }
             }
@@ -119,6 +301,9 @@ macro_rules! strict_enum {
                                 Ok(())
                             }
                         )*
+                        $(
+                            $enum_name::$unknown_name(index) => <$index_type>::async_encode(index, writer).await,
+                        )?
                     }
                 }
             }
@@ -126,239 +311,397 @@ macro_rules! strict_enum {
     };
 }
 
+/// Declares an enum whose indices change between protocols, read and written through
+/// `MapDecodable` and `MapEncodable` so packets mark its fields `#[field(mapped)]`.
+///
+/// Every protocol table should list every variant, a variant missing from a table fails to
+/// encode for those protocols. `_ => Unknown;` opts in to a catch-all variant and attributes are
+/// applied as in `strict_enum!`.
+///
+/// ```ignore
+/// mapped_enum! {
+///     ChatPosition; VarInt {
+///         Chat;
+///         System;
+///         _ => Unknown;
+///     }
+///     match protocol {
+///         MCProtocol::V1_17_1 | MCProtocol::V1_18 => { 0 => Chat; 1 => System; }
+///         _ => { 0 => System; 1 => Chat; }
+///     }
+/// }
+/// ```
+///
+/// Registries of this crate declare theirs with a leading `@internal`, which also implements
+/// `ArbitraryField` for the round trip tests.
 #[macro_export]
-macro_rules! create_registry {
-    (
+macro_rules! mapped_enum {
+    (@internal $($(#[$attr:meta])* $enum_name:ident; $index_type:ty {
+        $($option_name:ident;)*
+        $(_ => $unknown_name:ident;)?
+    }
+    match protocol {
+        $($protocol:pat => { $($byte_representation:literal => $mapped_name:ident;)* })*
+    })*) => {
+        $crate::mapped_enum! {
+            $($(#[$attr])* $enum_name; $index_type {
+                $($option_name;)*
+                $(_ => $unknown_name;)?
+            }
+            match protocol {
+                $($protocol => { $($byte_representation => $mapped_name;)* })*
+            })*
+        }
         $(
-            $packet_name:ident {
-                $($field_name:ident: $field_type:ty,)*
-                |LocalProtocol => ($($protocol:tt)+) $(+($($protocol_ext:tt)+))* => ($packet_id:literal);
-                $(|Protocol {
-                    $(
-                        ($($extra_protocol:tt)+) $(+($($extra_protocol_ext:tt)+))* => ($proto_packet_id:literal) {
-                            $($parse_ident:ident: $parse_type:ty => ($($parse_mapper:tt)+);)*
-                            ($constructor:expr)
-                        }
-                    )*
-                })?
+            #[cfg(test)]
+            impl $crate::registry::round_trip::ArbitraryField for $enum_name {
+                fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+                    let mut variants = vec![
+                        $($enum_name::$option_name,)*
+                        $($enum_name::$unknown_name($crate::registry::round_trip::ArbitraryField::arbitrary_field(runner)),)?
+                    ];
+                    let index = $crate::registry::round_trip::sample(runner, 0..variants.len());
+                    variants.swap_remove(index)
+                }
             }
         )*
-    ) => {
-        use minecraft_data_types::encoder::*;
-        use anyhow::Context;
+    };
+    ($($(#[$attr:meta])* $enum_name:ident; $index_type:ty {
+        $($option_name:ident;)*
+        $(_ => $unknown_name:ident;)?
+    }
+    match protocol {
+        $($protocol:pat => { $($byte_representation:literal => $mapped_name:ident;)* })*
+    })*) => {
         $(
+            $(#[$attr])*
             #[derive(Debug)]
-            pub struct $packet_name {$(
-                pub $field_name: $field_type,
-            )*}
+            pub enum $enum_name {
+                $($option_name,)*
+                $($unknown_name($index_type),)?
+            }
 
-            impl $crate::protocol_version::MapDecodable for $packet_name {
-                fn decode_mapped<R: std::io::Read>(protocol: $crate::protocol_version::MCProtocol, _reader: &mut R) -> anyhow::Result<Self> {
+            impl $enum_name {
+                #[allow(unreachable_patterns)]
+                fn index(&self, protocol: $crate::protocol_version::MCProtocol) -> anyhow::Result<$index_type> {
+                    $(
+                        if let $enum_name::$unknown_name(index) = self {
+                            return Ok(*index);
+                        }
+                    )?
                     match protocol {
-                        $($protocol)* $(| $($protocol_ext)*)* => {
-                            $(
-                                let $field_name = <$field_type>::decode(_reader)
-                                    .context(format!(
-                                        "Failed to decode field {} of packet {}.",
-                                        stringify!($field_name),
-                                        stringify!($packet_name)
-                                    ))?;
-                            )*
-                            Ok(Self {
-                                $($field_name,)*
-                            })
-                        },
-                        $($(
-                            $($extra_protocol)* $(| $($extra_protocol_ext)*)* => {
+                        $(
+                            $protocol => match self {
                                 $(
-                                    let $parse_ident = <$parse_type>::decode(_reader)
-                                        .context(format!(
-                                            "Failed to decode field {} of packet {} for protocol {}.",
-                                            stringify!($parse_ident),
-                                            stringify!($packet_name),
-                                            protocol
-                                        ))?;
+                                    $enum_name::$mapped_name => Ok(<$index_type>::from($byte_representation)),
                                 )*
-                                $constructor
+                                _ => anyhow::bail!("{:?} has no index for protocol {:?}.", self, protocol),
                             },
-                        )*)*
+                        )*
                     }
                 }
             }
 
-            impl $crate::protocol_version::MapEncodable for $packet_name {
-                fn encode_mapped<W: std::io::Write>(&self, protocol: $crate::protocol_version::MCProtocol, _writer: &mut W) -> anyhow::Result<()> {
-                    match protocol {
-                        $($protocol)* $(| $($protocol_ext)*)* => {
-                            $(
-                                self.$field_name.encode(_writer)
-                                    .context(format!(
-                                        "Failed to encode field {} of packet {}.",
-                                        stringify!($field_name),
-                                        stringify!($packet_name)
-                                    ))?;
-                            )*
-                            Ok(())
-                        }
-                        $($(
-                            $($extra_protocol)* $(| $($extra_protocol_ext)*)* => {
+            impl $crate::protocol_version::MapDecodable for $enum_name {
+                fn decode_mapped<R: std::io::Read>(
+                    protocol: $crate::protocol_version::MCProtocol,
+                    reader: &mut R,
+                ) -> anyhow::Result<Self> {
+                    let index = <$index_type as minecraft_data_types::encoder::Decodable>::decode(reader)?;
+                    let known = match protocol {
+                        $(
+                            $protocol => match &*index {
                                 $(
-                                    let $parse_ident = &self.$parse_ident;
-                                    $($parse_mapper)*.encode(_writer)
-                                        .context(format!(
-                                            "Failed to encode field {} of packet {} for protocol {}.",
-                                            stringify!($parse_ident),
-                                            stringify!($packet_name),
-                                            protocol
-                                        ))?;
+                                    $byte_representation => Some($enum_name::$mapped_name),
                                 )*
-                                Ok(())
+                                _ => None,
                             },
-                        )*)*
+                        )*
+                    };
+                    match known {
+                        Some(known) => Ok(known),
+                        None => $crate::strict_enum!(@unknown $enum_name, index, $($unknown_name)?),
                     }
                 }
+            }
 
-                #[allow(unused_mut)]
-                fn size_mapped(&self, protocol: $crate::protocol_version::MCProtocol) -> anyhow::Result<minecraft_data_types::nums::VarInt> {
-                    match protocol {
-                        $($protocol)* $(| $($protocol_ext)*)* => {
-                            let mut accum = minecraft_data_types::nums::VarInt::from(0);
-                            $(
-                                accum += self.$field_name.size()
-                                    .context(format!(
-                                        "Failed to size field {} of packet {}.",
-                                        stringify!($field_name),
-                                        stringify!($packet_name)
-                                    ))?;
-                            )*
-                            Ok(accum)
-                        }
-                        $($(
-                            $($extra_protocol)* $(| $($extra_protocol_ext)*)* => {
-                                let mut accum = minecraft_data_types::nums::VarInt::from(0);
-                                $(
-                                    let $parse_ident = &self.$parse_ident;
-                                    accum += $($parse_mapper)*.size()
-                                        .context(format!(
-                                            "Failed to size field {} of packet {} for protocol {}.",
-                                            stringify!($parse_ident),
-                                            stringify!($packet_name),
-                                            protocol
-                                        ))?;
-                                )*
-                                Ok(accum)
-                            }
-                        )*)*
+            impl $crate::protocol_version::MapEncodable for $enum_name {
+                fn encode_mapped<W: std::io::Write>(
+                    &self,
+                    protocol: $crate::protocol_version::MCProtocol,
+                    writer: &mut W,
+                ) -> anyhow::Result<()> {
+                    minecraft_data_types::encoder::Encodable::encode(&self.index(protocol)?, writer)
+                }
+
+                fn size_mapped(
+                    &self,
+                    protocol: $crate::protocol_version::MCProtocol,
+                ) -> anyhow::Result<minecraft_data_types::nums::VarInt> {
+                    minecraft_data_types::encoder::Encodable::size(&self.index(protocol)?)
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl $crate::protocol_version::AsyncMapEncodable for $enum_name {
+                async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+                    &self,
+                    protocol: $crate::protocol_version::MCProtocol,
+                    writer: &mut W,
+                ) -> anyhow::Result<()> {
+                    let index = self.index(protocol)?;
+                    minecraft_data_types::encoder::AsyncEncodable::async_encode(&index, writer).await
+                }
+            }
+        )*
+    };
+}
+
+/// Declares a bit mask over an integer type, unknown bits are rejected or preserved when decoding.
+///
+/// ```ignore
+/// strict_flags! {
+///     PlayerAbilities; u8; Reject {
+///         0x01 => INVULNERABLE;
+///         0x02 => FLYING;
+///     }
+/// }
+/// ```
+///
+/// Registries of this crate declare theirs with a leading `@internal`, which also implements
+/// `ArbitraryField` for the round trip tests.
+#[macro_export]
+macro_rules! strict_flags {
+    (@unknown Reject, $flags_name:ident, $bits:ident) => {
+        if $bits & !$flags_name::all().bits() != 0 {
+            anyhow::bail!("Unknown bits {:#x} for {}.", $bits & !$flags_name::all().bits(), stringify!($flags_name));
+        }
+    };
+    (@unknown Preserve, $flags_name:ident, $bits:ident) => {};
+    (@internal $($(#[$attr:meta])* $flags_name:ident; $bits_type:ty; $policy:ident { $($bit:literal => $flag_name:ident;)* })*) => {
+        $crate::strict_flags! {
+            $($(#[$attr])* $flags_name; $bits_type; $policy { $($bit => $flag_name;)* })*
+        }
+        $(
+            #[cfg(test)]
+            impl $crate::registry::round_trip::ArbitraryField for $flags_name {
+                fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+                    let bits = <$bits_type as $crate::registry::round_trip::ArbitraryField>::arbitrary_field(runner);
+                    Self::from_bits_truncate(bits)
+                }
+            }
+        )*
+    };
+    ($($(#[$attr:meta])* $flags_name:ident; $bits_type:ty; $policy:ident { $($bit:literal => $flag_name:ident;)* })*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+            pub struct $flags_name {
+                bits: $bits_type,
+            }
+
+            #[allow(dead_code)]
+            impl $flags_name {
+                $(
+                    pub const $flag_name: Self = Self { bits: $bit };
+                )*
+
+                pub const fn empty() -> Self {
+                    Self { bits: 0 }
+                }
+
+                pub const fn all() -> Self {
+                    Self { bits: 0 $(| $bit)* }
+                }
+
+                pub const fn bits(&self) -> $bits_type {
+                    self.bits
+                }
+
+                /// Returns `None` if any bit is not a declared flag.
+                pub const fn from_bits(bits: $bits_type) -> Option<Self> {
+                    if bits & !Self::all().bits == 0 {
+                        Some(Self { bits })
+                    } else {
+                        None
+                    }
+                }
+
+                pub const fn from_bits_truncate(bits: $bits_type) -> Self {
+                    Self { bits: bits & Self::all().bits }
+                }
+
+                pub const fn is_empty(&self) -> bool {
+                    self.bits == 0
+                }
+
+                pub const fn contains(&self, other: Self) -> bool {
+                    self.bits & other.bits == other.bits
+                }
+
+                pub fn insert(&mut self, other: Self) {
+                    self.bits |= other.bits;
+                }
+
+                pub fn remove(&mut self, other: Self) {
+                    self.bits &= !other.bits;
+                }
+
+                pub fn set(&mut self, other: Self, value: bool) {
+                    if value {
+                        self.insert(other);
+                    } else {
+                        self.remove(other);
                     }
                 }
             }
 
-            impl $crate::packet::WritablePacket for $packet_name {
-                #[allow(unused_mut)]
-                fn to_resolved_packet(&self, protocol: $crate::protocol_version::MCProtocol) -> anyhow::Result<$crate::packet::ResolvedPacket> {
-                    match protocol {
-                        $($protocol)* $(| $($protocol_ext)*)* => {
-                            let mut vec: Vec<u8> = Vec::new();
-                            $(
-                                self.$field_name.encode(&mut vec)
-                                    .context(format!(
-                                        "Failed to encode field {} of packet {}.",
-                                        stringify!($field_name),
-                                        stringify!($packet_name)
-                                    ))?;
-                            )*
-                            $crate::packet::ResolvedPacket::new(minecraft_data_types::nums::VarInt::from($packet_id), vec)
+            impl std::ops::BitOr for $flags_name {
+                type Output = Self;
+
+                fn bitor(self, other: Self) -> Self {
+                    Self { bits: self.bits | other.bits }
+                }
+            }
+
+            impl std::ops::BitOrAssign for $flags_name {
+                fn bitor_assign(&mut self, other: Self) {
+                    self.bits |= other.bits;
+                }
+            }
+
+            impl std::ops::BitAnd for $flags_name {
+                type Output = Self;
+
+                fn bitand(self, other: Self) -> Self {
+                    Self { bits: self.bits & other.bits }
+                }
+            }
+
+            impl std::ops::BitAndAssign for $flags_name {
+                fn bitand_assign(&mut self, other: Self) {
+                    self.bits &= other.bits;
+                }
+            }
+
+            impl std::ops::Sub for $flags_name {
+                type Output = Self;
+
+                fn sub(self, other: Self) -> Self {
+                    Self { bits: self.bits & !other.bits }
+                }
+            }
+
+            impl std::fmt::Debug for $flags_name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}(", stringify!($flags_name))?;
+                    let mut first = true;
+                    $(
+                        if self.contains(Self::$flag_name) && $bit != 0 {
+                            if !first {
+                                write!(f, " | ")?;
+                            }
+                            write!(f, "{}", stringify!($flag_name))?;
+                            first = false;
                         }
-                        $($(
-                            $($extra_protocol)* $(| $($extra_protocol_ext)*)* => {
-                                let mut vec: Vec<u8> = Vec::new();
-                                $(
-                                    let $parse_ident = &self.$parse_ident;
-                                    $($parse_mapper)*.encode(&mut vec)
-                                        .context(format!(
-                                            "Failed to encode field {} of packet {} for protocol {}.",
-                                            stringify!($parse_ident),
-                                            stringify!($packet_name),
-                                            protocol
-                                        ))?;
-                                )*
-                                $crate::packet::ResolvedPacket::new(minecraft_data_types::nums::VarInt::from($proto_packet_id), vec)
-                            },
-                        )*)*
+                    )*
+                    let unknown = self.bits & !Self::all().bits;
+                    if unknown != 0 {
+                        if !first {
+                            write!(f, " | ")?;
+                        }
+                        write!(f, "{:#x}", unknown)?;
                     }
+                    write!(f, ")")
+                }
+            }
+
+            impl minecraft_data_types::encoder::Decodable for $flags_name {
+                fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+                    let bits = <$bits_type as minecraft_data_types::encoder::Decodable>::decode(reader)?;
+                    $crate::strict_flags!(@unknown $policy, $flags_name, bits);
+                    Ok(Self { bits })
+                }
+            }
+
+            impl minecraft_data_types::encoder::Encodable for $flags_name {
+                fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+                    minecraft_data_types::encoder::Encodable::encode(&self.bits, writer)
+                }
+
+                fn size(&self) -> anyhow::Result<minecraft_data_types::nums::VarInt> {
+                    minecraft_data_types::encoder::Encodable::size(&self.bits)
                 }
             }
 
             #[async_trait::async_trait]
-            impl $crate::protocol_version::AsyncMapEncodable for $packet_name {
-                async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(&self, protocol: $crate::protocol_version::MCProtocol, _writer: &mut W) -> anyhow::Result<()> {
-                    match protocol {
-                        $($protocol)* $(| $($protocol_ext)*)* => {
-                            $(
-                                self.$field_name.async_encode(_writer)
-                                    .await
-                                    .context(format!(
-                                        "Failed to encode field {} of packet {}.",
-                                        stringify!($field_name),
-                                        stringify!($packet_name)
-                                    ))?;
-                            )*
-                            Ok(())
-                        }
-                        $($(
-                            $($extra_protocol)* $(| $($extra_protocol_ext)*)* => {
-                                $(
-                                    let $parse_ident = &self.$parse_ident;
-                                    $($parse_mapper)*.async_encode(_writer)
-                                        .await
-                                        .context(format!(
-                                            "Failed to encode field {} of packet {} for protocol {}.",
-                                            stringify!($parse_ident),
-                                            stringify!($packet_name),
-                                            protocol
-                                        ))?;
-                                )*
-                                Ok(())
-                            },
-                        )*)*
-                    }
+            impl minecraft_data_types::encoder::AsyncEncodable for $flags_name {
+                async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+                    &self,
+                    writer: &mut W,
+                ) -> anyhow::Result<()> {
+                    minecraft_data_types::encoder::AsyncEncodable::async_encode(&self.bits, writer).await
                 }
             }
         )*
+    };
+}
+
+/// Declares the `Registry` and `RegistryHandler` for a set of `#[derive(Packet)]` types.
+///
+/// ```ignore
+/// registry! {
+///     StatusRequest,
+///     Ping,
+/// }
+/// ```
+#[macro_export]
+macro_rules! registry {
+    ($($packet_name:ident),* $(,)?) => {
         pub struct Registry;
 
+        impl Registry {
+            /// Decodes a packet (id followed by body) into its debug representation, or `None` if
+            /// the packet is not known to this registry.
+            pub fn describe_packet(
+                mut packet_cursor: std::io::Cursor<Vec<u8>>,
+                target_protocol: $crate::protocol_version::MCProtocol
+            ) -> anyhow::Result<Option<String>> {
+                let packet_id = <minecraft_data_types::nums::VarInt as minecraft_data_types::encoder::Decodable>::decode(&mut packet_cursor)?;
+                $(
+                    if <$packet_name as $crate::packet::IdentifiedPacket>::packet_id(target_protocol).map_or(false, |id| *id == *packet_id) {
+                        let packet = <$packet_name as $crate::protocol_version::MapDecodable>::decode_mapped(target_protocol, &mut packet_cursor)?;
+                        return Ok(Some(format!("{:?}", packet)));
+                    }
+                )*
+                Ok(None)
+            }
+        }
+
         #[async_trait::async_trait]
         impl<H: RegistryHandler> $crate::registry::RegistryBase<H> for Registry {
             async fn handle_packet(
                 handler: &mut H, mut packet_cursor: std::io::Cursor<Vec<u8>>,
                 target_protocol: $crate::protocol_version::MCProtocol
             ) -> anyhow::Result<()> {
-                let packet_id = minecraft_data_types::nums::VarInt::decode(&mut packet_cursor)?;
+                let packet_id = <minecraft_data_types::nums::VarInt as minecraft_data_types::encoder::Decodable>::decode(&mut packet_cursor)?;
                 paste::paste! {
-                    match (target_protocol, *packet_id) {
-                        $(
-                            ($($protocol)* $(| $($protocol_ext)*)*, $packet_id) => {
-                                handler.[<handle_$packet_name:snake>]($crate::registry::SimpleLazyHandle::new(packet_cursor, target_protocol)).await
-                            }
-                            $($(
-                                ($($extra_protocol)* $(| $($extra_protocol_ext)*)*, $proto_packet_id) => {
-                                    handler.[<handle_$packet_name:snake>]($crate::registry::SimpleLazyHandle::new(packet_cursor, target_protocol)).await
-                                }
-                            )*)*
-                        )*
-                        (_, _) => {
-                            handler.handle_unknown(packet_cursor).await
+                    $(
+                        if <$packet_name as $crate::packet::IdentifiedPacket>::packet_id(target_protocol).map_or(false, |id| *id == *packet_id) {
+                            return handler.[<handle_$packet_name:snake>]($crate::registry::SimpleLazyHandle::new(packet_cursor, target_protocol)).await;
                         }
-                    }
+                    )*
                 }
+                handler.handle_unknown(packet_cursor).await
             }
         }
+
         paste::paste! {
             #[async_trait::async_trait]
             pub trait RegistryHandler: Send + Sync {
                 async fn handle_unknown(&mut self, packet_cursor: std::io::Cursor<Vec<u8>>) -> anyhow::Result<()>;
 
-                async fn handle_default<T: crate::protocol_version::MapDecodable, H: $crate::registry::LazyHandle<T> + Send>(
+                async fn handle_default<T: $crate::protocol_version::MapDecodable, H: $crate::registry::LazyHandle<T> + Send>(
                     &mut self, handle: H
                 ) -> anyhow::Result<()>;
                 $(
@@ -371,5 +714,107 @@ macro_rules! create_registry {
                 )*
             }
         }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol_version::{MCProtocol, MapDecodable, MapEncodable};
+    use minecraft_data_types::encoder::{Decodable, Encodable};
+    use minecraft_data_types::nums::VarInt;
+    use std::io::Cursor;
+
+    crate::strict_enum! {
+        Difficulty; VarInt {
+            0 => Peaceful;
+            1 => Easy;
+        }
+        LenientDifficulty; VarInt {
+            0 => Peaceful;
+            1 => Easy;
+            _ => Unknown;
+        }
+    }
+
+    crate::mapped_enum! {
+        Hand; VarInt {
+            Main;
+            Off;
+            _ => Unknown;
+        }
+        match protocol {
+            MCProtocol::V1_17_1 | MCProtocol::V1_18 => { 0 => Main; 1 => Off; }
+            _ => { 0 => Off; 1 => Main; }
+        }
+    }
+
+    fn encode(value: &impl Encodable) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn strict_enum_preserves_unknown_indices_when_opted_in() {
+        assert!(Difficulty::decode(&mut Cursor::new(vec![7])).is_err());
+        let difficulty = LenientDifficulty::decode(&mut Cursor::new(vec![7])).unwrap();
+        assert!(matches!(difficulty, LenientDifficulty::Unknown(index) if *index == 7));
+        assert_eq!(encode(&difficulty), vec![7]);
+    }
+
+    #[test]
+    fn mapped_enum_uses_the_protocol_table() {
+        let hand = Hand::decode_mapped(MCProtocol::V1_18, &mut Cursor::new(vec![0])).unwrap();
+        assert!(matches!(hand, Hand::Main));
+        let hand = Hand::decode_mapped(MCProtocol::V1_19, &mut Cursor::new(vec![0])).unwrap();
+        assert!(matches!(hand, Hand::Off));
+
+        let mut bytes = Vec::new();
+        Hand::Main.encode_mapped(MCProtocol::V1_19, &mut bytes).unwrap();
+        assert_eq!(bytes, vec![1]);
+        assert_eq!(*Hand::Main.size_mapped(MCProtocol::V1_19).unwrap(), 1);
+
+        let hand = Hand::decode_mapped(MCProtocol::V1_19, &mut Cursor::new(vec![9])).unwrap();
+        let mut bytes = Vec::new();
+        hand.encode_mapped(MCProtocol::V1_17_1, &mut bytes).unwrap();
+        assert_eq!(bytes, vec![9]);
+    }
+
+    strict_flags! {
+        StrictAbilities; u8; Reject {
+            0x01 => INVULNERABLE;
+            0x02 => FLYING;
+        }
+        LenientAbilities; u8; Preserve {
+            0x01 => INVULNERABLE;
+            0x02 => FLYING;
+        }
+    }
+
+    #[test]
+    fn strict_flags_reject_unknown_bits() {
+        assert!(StrictAbilities::decode(&mut Cursor::new(vec![0x05])).is_err());
+        let flags = StrictAbilities::decode(&mut Cursor::new(vec![0x03])).unwrap();
+        assert_eq!(flags, StrictAbilities::INVULNERABLE | StrictAbilities::FLYING);
+    }
+
+    #[test]
+    fn strict_flags_preserve_unknown_bits() {
+        let flags = LenientAbilities::decode(&mut Cursor::new(vec![0x06])).unwrap();
+        assert!(flags.contains(LenientAbilities::FLYING));
+        assert!(!flags.contains(LenientAbilities::INVULNERABLE));
+        assert_eq!(format!("{:?}", flags), "LenientAbilities(FLYING | 0x4)");
+
+        let mut bytes = Vec::new();
+        flags.encode(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![0x06]);
+    }
+
+    #[test]
+    fn strict_flags_only_build_from_declared_bits() {
+        assert!(StrictAbilities::from_bits(0x04).is_none());
+        assert_eq!(StrictAbilities::from_bits_truncate(0x07), StrictAbilities::all());
+        assert_eq!(encode(&(StrictAbilities::all() - StrictAbilities::FLYING)), vec![0x01]);
+        assert_eq!(encode(&LenientAbilities::from_bits_truncate(0x06)), vec![0x02]);
     }
 }
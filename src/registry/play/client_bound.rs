@@ -1,38 +1,723 @@
-use crate::create_registry;
-use crate::protocol_version::MCProtocol;
-use minecraft_data_types::{nums::VarInt, common::{Chat, Identifier}};
+use crate::chat::Component;
+use crate::fields::{Position, Prefixed, PrefixedOption, RemainingBytes, SectionPosition};
+use crate::registry;
+use crate::registry::{CookiePayload, TransferHost};
+use crate::nbt::Nbt;
+use crate::Packet;
+use minecraft_data_types::{nums::VarInt, common::Identifier};
 use commander::protocol::Node;
 
-create_registry! {
-    DeclareCommands {
-        nodes: (VarInt, Vec<Node>),
-        root_index: VarInt,
-        |LocalProtocol => (MCProtocol::V1_17_1) + (MCProtocol::V1_18) => (0x12);
-        |Protocol {
-            (_) => (0x12) {
-                (anyhow::bail!("Unsupported version."))
-            }
-        }
-    }
-
-    PluginMessage {
-        channel: Identifier,
-        data: Vec<u8>,
-        |LocalProtocol => (MCProtocol::V1_17_1) + (MCProtocol::V1_18) => (0x18);
-        |Protocol {
-            (_) => (0x18) {
-                (anyhow::bail!("Unsupported version."))
-            }
-        }
-    }
-
-    Disconnect {
-        reason: Chat,
-        |LocalProtocol => (MCProtocol::V1_17_1) + (MCProtocol::V1_18) => (0x1A);
-        |Protocol {
-            (_) => (0x1A) {
-                (anyhow::bail!("Unsupported version."))
-            }
-        }
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x12, versions = "1.17.1..=1.18")]
+pub struct DeclareCommands {
+    pub nodes: Prefixed<VarInt, Vec<Node>>,
+    pub root_index: VarInt,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x18, versions = "1.17.1..=1.18")]
+pub struct PluginMessage {
+    pub channel: Identifier,
+    pub data: RemainingBytes<1048576>,
+}
+
+/// Text components are sent as NBT from 1.20.3.
+#[derive(Debug, Packet)]
+#[packet(id = 0x1A, versions = "1.17.1..=1.18")]
+#[packet(id = 0x17, versions = "1.19")]
+#[packet(id = 0x1B, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x1D, versions = "1.20.5..")]
+pub struct Disconnect {
+    #[field(mapped)]
+    pub reason: Component,
+}
+
+/// A chat message before signed chat, `position` is 0 for chat, 1 for system messages and 2 above
+/// the hotbar.
+#[derive(Debug, Packet)]
+#[packet(id = 0x0F, versions = "1.17.1..=1.18")]
+pub struct ChatMessage {
+    pub message: Component,
+    pub position: i8,
+    pub sender: uuid::Uuid,
+}
+
+/// A message from another player, signed by their chat session from 1.19.
+///
+/// 1.19 signs the JSON `signed_content`, later versions sign the plain `message` along with the
+/// signatures the sender had seen, and decorate it client side with `chat_type`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x30, versions = "1.19")]
+#[packet(id = 0x37, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x39, versions = "1.20.5..")]
+#[layout(
+    versions = "1.20.2..",
+    order = "sender, index, signature, message, timestamp, salt, previous_messages, unsigned_content, \
+             filter_mask, chat_type, sender_name, target_name, signed_content, legacy_signature"
+)]
+pub struct PlayerChatMessage {
+    #[field(versions = "1.19")]
+    pub signed_content: Component,
+    #[field(mapped)]
+    pub unsigned_content: PrefixedOption<Component>,
+    /// The chat type registry id.
+    pub chat_type: VarInt,
+    pub sender: uuid::Uuid,
+    #[field(mapped)]
+    pub sender_name: Component,
+    /// The sender's team name in 1.19, the message's target afterwards.
+    #[field(mapped)]
+    pub target_name: PrefixedOption<Component>,
+    pub timestamp: i64,
+    pub salt: i64,
+    #[field(versions = "1.19")]
+    pub legacy_signature: Prefixed<VarInt, Vec<u8>>,
+    /// The sender's message index in their chat session.
+    #[field(since = "1.20.2", default = "VarInt::from(0)")]
+    pub index: VarInt,
+    #[field(since = "1.20.2")]
+    pub signature: PrefixedOption<super::MessageSignature>,
+    #[field(since = "1.20.2", default = "super::ChatString::from(\"\")")]
+    pub message: super::ChatString,
+    #[field(since = "1.20.2")]
+    pub previous_messages: Prefixed<VarInt, Vec<super::PackedSignature>, 20>,
+    #[field(since = "1.20.2", default = "super::FilterMask::PassThrough")]
+    pub filter_mask: super::FilterMask,
+}
+
+/// An unsigned message from the server, shown above the hotbar when `overlay` is set.
+#[derive(Debug, Packet)]
+#[packet(id = 0x5F, versions = "1.19")]
+#[packet(id = 0x67, versions = "1.20.2")]
+#[packet(id = 0x69, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x6C, versions = "1.20.5..")]
+pub struct SystemChat {
+    #[field(mapped)]
+    pub content: Component,
+    /// The chat type registry id, replaced by `overlay` in 1.19.1.
+    #[field(versions = "1.19", default = "VarInt::from(0)")]
+    pub chat_type: VarInt,
+    #[field(since = "1.20.2")]
+    pub overlay: bool,
+}
+
+/// The first play packet, describing the player and the world they spawn in.
+///
+/// Before 1.20.2 it carries every registry as the `dimension_codec`, which moved to the
+/// configuration state's `RegistryData` afterwards, along with a new field order.
+#[derive(Debug, Packet)]
+#[packet(id = 0x26, versions = "1.17.1..=1.18")]
+#[packet(id = 0x23, versions = "1.19")]
+#[packet(id = 0x29, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x2B, versions = "1.20.5..")]
+#[layout(
+    versions = "1.20.2..",
+    order = "entity_id, is_hardcore, world_names, max_players, view_distance, simulation_distance, \
+             reduced_debug_info, enable_respawn_screen, do_limited_crafting, dimension_type, dimension_type_id, \
+             world_name, hashed_seed, gamemode, previous_gamemode, is_debug, is_flat, death_location, \
+             portal_cooldown, enforces_secure_chat, dimension_codec, dimension"
+)]
+pub struct JoinGame {
+    pub entity_id: i32,
+    pub is_hardcore: bool,
+    pub gamemode: u8,
+    pub previous_gamemode: i8,
+    pub world_names: Prefixed<VarInt, Vec<Identifier>>,
+    #[field(until = "1.20.2", mapped)]
+    pub dimension_codec: Nbt,
+    #[field(until = "1.19", mapped)]
+    pub dimension: Nbt,
+    #[field(since = "1.19", until = "1.20.5", default = "Identifier::from(\"\")")]
+    pub dimension_type: Identifier,
+    #[field(since = "1.20.5", default = "VarInt::from(0)")]
+    pub dimension_type_id: VarInt,
+    pub world_name: Identifier,
+    pub hashed_seed: i64,
+    pub max_players: VarInt,
+    pub view_distance: VarInt,
+    #[field(since = "1.18", default = "VarInt::from(0)")]
+    pub simulation_distance: VarInt,
+    pub reduced_debug_info: bool,
+    pub enable_respawn_screen: bool,
+    #[field(since = "1.20.2")]
+    pub do_limited_crafting: bool,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    #[field(since = "1.19")]
+    pub death_location: PrefixedOption<super::DeathLocation>,
+    #[field(since = "1.20.2", default = "VarInt::from(0)")]
+    pub portal_cooldown: VarInt,
+    #[field(since = "1.20.5")]
+    pub enforces_secure_chat: bool,
+}
+
+/// Moves the player to another world, or resets it when a proxy switches servers.
+///
+/// The client ignores a respawn into the dimension it is already in, so proxies switching between
+/// servers in the same dimension send one into a different dimension first.
+#[derive(Debug, Packet)]
+#[packet(id = 0x3D, versions = "1.17.1..=1.18")]
+#[packet(id = 0x3B, versions = "1.19")]
+#[packet(id = 0x43, versions = "1.20.2")]
+#[packet(id = 0x45, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x47, versions = "1.20.5..")]
+pub struct Respawn {
+    #[field(until = "1.19", mapped)]
+    pub dimension: Nbt,
+    #[field(since = "1.19", until = "1.20.5", default = "Identifier::from(\"\")")]
+    pub dimension_type: Identifier,
+    #[field(since = "1.20.5", default = "VarInt::from(0)")]
+    pub dimension_type_id: VarInt,
+    pub world_name: Identifier,
+    pub hashed_seed: i64,
+    pub gamemode: u8,
+    pub previous_gamemode: i8,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    #[field(until = "1.20.2")]
+    pub copy_metadata: bool,
+    #[field(since = "1.19")]
+    pub death_location: PrefixedOption<super::DeathLocation>,
+    #[field(since = "1.20.2", default = "VarInt::from(0)")]
+    pub portal_cooldown: VarInt,
+    /// Bit 0 keeps attributes and bit 1 keeps entity metadata, replacing `copy_metadata`.
+    #[field(since = "1.20.2")]
+    pub data_kept: u8,
+}
+
+/// Changes the tab list before 1.19.3, applying one action to every listed player.
+#[derive(Debug, Packet)]
+#[packet(id = 0x36, versions = "1.17.1..=1.18")]
+#[packet(id = 0x34, versions = "1.19")]
+pub struct PlayerInfo {
+    #[field(mapped)]
+    pub update: super::LegacyPlayerInfo,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x3B, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x3D, versions = "1.20.5..")]
+pub struct PlayerInfoRemove {
+    pub players: Prefixed<VarInt, Vec<uuid::Uuid>>,
+}
+
+/// Adds or changes tab list players from 1.19.3, carrying the fields of every set action.
+#[derive(Debug, Packet)]
+#[packet(id = 0x3C, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x3E, versions = "1.20.5..")]
+pub struct PlayerInfoUpdate {
+    #[field(mapped)]
+    pub update: super::PlayerInfoChanges,
+}
+
+/// An empty component clears the header or footer.
+#[derive(Debug, Packet)]
+#[packet(id = 0x5E, versions = "1.17.1")]
+#[packet(id = 0x5F, versions = "1.18")]
+#[packet(id = 0x60, versions = "1.19")]
+#[packet(id = 0x68, versions = "1.20.2")]
+#[packet(id = 0x6A, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x6D, versions = "1.20.5..")]
+pub struct TabListHeaderAndFooter {
+    #[field(mapped)]
+    pub header: Component,
+    #[field(mapped)]
+    pub footer: Component,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0D, versions = "1.17.1..=1.18")]
+#[packet(id = 0x0A, versions = "1.19..")]
+pub struct BossBar {
+    pub uuid: uuid::Uuid,
+    #[field(mapped)]
+    pub action: super::BossBarAction,
+}
+
+/// Shows a title, using the timings of the last `SetTitlesAnimation`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x59, versions = "1.17.1")]
+#[packet(id = 0x5A, versions = "1.18..=1.19")]
+#[packet(id = 0x61, versions = "1.20.2")]
+#[packet(id = 0x63, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x65, versions = "1.20.5..")]
+pub struct SetTitleText {
+    #[field(mapped)]
+    pub text: Component,
+}
+
+/// Changes the subtitle, which is only shown along with the next `SetTitleText`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x57, versions = "1.17.1")]
+#[packet(id = 0x58, versions = "1.18..=1.19")]
+#[packet(id = 0x5F, versions = "1.20.2")]
+#[packet(id = 0x61, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x63, versions = "1.20.5..")]
+pub struct SetSubtitleText {
+    #[field(mapped)]
+    pub text: Component,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x41, versions = "1.17.1..=1.18")]
+#[packet(id = 0x40, versions = "1.19")]
+#[packet(id = 0x48, versions = "1.20.2")]
+#[packet(id = 0x4A, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x4C, versions = "1.20.5..")]
+pub struct SetActionBarText {
+    #[field(mapped)]
+    pub text: Component,
+}
+
+/// Timings in ticks.
+#[derive(Debug, Packet)]
+#[packet(id = 0x5A, versions = "1.17.1")]
+#[packet(id = 0x5B, versions = "1.18..=1.19")]
+#[packet(id = 0x62, versions = "1.20.2")]
+#[packet(id = 0x64, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x66, versions = "1.20.5..")]
+pub struct SetTitlesAnimation {
+    pub fade_in: i32,
+    pub stay: i32,
+    pub fade_out: i32,
+}
+
+/// Hides the title, and with `reset` also clears the subtitle and timings.
+#[derive(Debug, Packet)]
+#[packet(id = 0x10, versions = "1.17.1..=1.18")]
+#[packet(id = 0x0D, versions = "1.19")]
+#[packet(id = 0x0F, versions = "1.20.2..")]
+pub struct ClearTitles {
+    pub reset: bool,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x53, versions = "1.17.1..=1.19")]
+#[packet(id = 0x5A, versions = "1.20.2")]
+#[packet(id = 0x5C, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x5E, versions = "1.20.5..")]
+pub struct ScoreboardObjective {
+    pub objective_name: super::ScoreboardName,
+    #[field(mapped)]
+    pub action: super::ObjectiveAction,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x56, versions = "1.17.1..=1.19")]
+#[packet(id = 0x5D, versions = "1.20.2")]
+#[packet(id = 0x5F, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x61, versions = "1.20.5..")]
+pub struct UpdateScore {
+    /// A player name or entity uuid, limited to 40 characters in 1.17.1.
+    pub entity_name: super::ScoreboardName,
+    #[field(mapped)]
+    pub update: super::ScoreUpdate,
+}
+
+/// Removes a holder's score from one objective, or from all of them without a name.
+#[derive(Debug, Packet)]
+#[packet(id = 0x42, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x44, versions = "1.20.5..")]
+pub struct ResetScore {
+    pub entity_name: super::ScoreboardName,
+    pub objective_name: PrefixedOption<super::ScoreboardName>,
+}
+
+/// Shows an objective in a slot, an empty name clears the slot.
+#[derive(Debug, Packet)]
+#[packet(id = 0x4C, versions = "1.17.1..=1.19")]
+#[packet(id = 0x53, versions = "1.20.2")]
+#[packet(id = 0x55, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x57, versions = "1.20.5..")]
+pub struct DisplayObjective {
+    #[field(mapped)]
+    pub position: super::DisplaySlot,
+    pub objective_name: super::ScoreboardName,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x55, versions = "1.17.1..=1.19")]
+#[packet(id = 0x5C, versions = "1.20.2")]
+#[packet(id = 0x5E, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x60, versions = "1.20.5..")]
+pub struct Teams {
+    pub team_name: super::ScoreboardName,
+    #[field(mapped)]
+    pub action: super::TeamAction,
+}
+
+/// A chunk column as 1.17.1 sent it, without its light which follows in an `UpdateLight`.
+///
+/// Only sections set in the primary bit mask are sent, while biomes cover the whole column in
+/// cells of 4x4x4 blocks indexed `(y * 4 + z) * 4 + x` from the bottom of the world. Block
+/// entities are full compounds with their coordinates and id.
+#[derive(Debug, Packet)]
+#[packet(id = 0x22, versions = "1.17.1")]
+pub struct ChunkData {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub primary_bit_mask: super::BitSet,
+    #[field(mapped)]
+    pub heightmaps: Nbt,
+    pub biomes: Prefixed<VarInt, Vec<VarInt>>,
+    pub sections: crate::chunk::LegacyChunkSections,
+    #[field(mapped)]
+    pub block_entities: Prefixed<VarInt, Vec<Nbt>>,
+}
+
+/// A full chunk column with its light, in the section format 1.18 introduced.
+///
+/// Light masks have a bit for every section plus one below and one above the world, with an
+/// array in `sky_light` or `block_light` for each set bit.
+#[derive(Debug, Packet)]
+#[packet(id = 0x22, versions = "1.18")]
+#[packet(id = 0x1F, versions = "1.19")]
+#[packet(id = 0x25, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x27, versions = "1.20.5..")]
+pub struct ChunkDataAndUpdateLight {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    #[field(mapped)]
+    pub heightmaps: Nbt,
+    pub sections: crate::chunk::ChunkSections,
+    #[field(mapped)]
+    pub block_entities: Prefixed<VarInt, Vec<super::BlockEntity>>,
+    #[field(until = "1.20.2")]
+    pub trust_edges: bool,
+    pub sky_light_mask: super::BitSet,
+    pub block_light_mask: super::BitSet,
+    pub empty_sky_light_mask: super::BitSet,
+    pub empty_block_light_mask: super::BitSet,
+    pub sky_light: Prefixed<VarInt, Vec<crate::chunk::LightArray>>,
+    pub block_light: Prefixed<VarInt, Vec<crate::chunk::LightArray>>,
+}
+
+/// The light of a chunk column, laid out like the light of `ChunkDataAndUpdateLight`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x25, versions = "1.17.1..=1.18")]
+#[packet(id = 0x22, versions = "1.19")]
+#[packet(id = 0x28, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x2A, versions = "1.20.5..")]
+pub struct UpdateLight {
+    pub chunk_x: VarInt,
+    pub chunk_z: VarInt,
+    #[field(until = "1.20.2")]
+    pub trust_edges: bool,
+    pub sky_light_mask: super::BitSet,
+    pub block_light_mask: super::BitSet,
+    pub empty_sky_light_mask: super::BitSet,
+    pub empty_block_light_mask: super::BitSet,
+    pub sky_light: Prefixed<VarInt, Vec<crate::chunk::LightArray>>,
+    pub block_light: Prefixed<VarInt, Vec<crate::chunk::LightArray>>,
+}
+
+/// Coordinates are in chunks, sent z first from 1.20.2.
+#[derive(Debug, Packet)]
+#[packet(id = 0x1D, versions = "1.17.1..=1.18")]
+#[packet(id = 0x1A, versions = "1.19")]
+#[packet(id = 0x1F, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x21, versions = "1.20.5..")]
+#[layout(versions = "1.20.2..", order = "chunk_z, chunk_x")]
+pub struct UnloadChunk {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0C, versions = "1.17.1..=1.18")]
+#[packet(id = 0x09, versions = "1.19..")]
+pub struct BlockUpdate {
+    pub location: Position,
+    pub block_id: VarInt,
+}
+
+/// Changes blocks within one chunk section.
+#[derive(Debug, Packet)]
+#[packet(id = 0x3F, versions = "1.17.1..=1.18")]
+#[packet(id = 0x3D, versions = "1.19")]
+#[packet(id = 0x45, versions = "1.20.2")]
+#[packet(id = 0x47, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x49, versions = "1.20.5..")]
+pub struct MultiBlockChange {
+    pub section_position: SectionPosition,
+    #[field(until = "1.20.2")]
+    pub suppress_light_updates: bool,
+    pub blocks: Prefixed<VarInt, Vec<super::BlockChange>>,
+}
+
+/// Spawns any entity, and players too from 1.20.2.
+///
+/// Angles are in 256ths of a turn and velocity in 8000ths of a block per tick.
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, versions = "1.17.1..=1.19")]
+#[packet(id = 0x01, versions = "1.20.2..")]
+pub struct SpawnEntity {
+    pub entity_id: VarInt,
+    pub entity_uuid: uuid::Uuid,
+    pub entity_type: VarInt,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub pitch: u8,
+    pub yaw: u8,
+    #[field(since = "1.19")]
+    pub head_yaw: u8,
+    #[field(mapped)]
+    pub data: super::SpawnData,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+/// Spawns another player, replaced by `SpawnEntity` in 1.20.2.
+#[derive(Debug, Packet)]
+#[packet(id = 0x04, versions = "1.17.1..=1.18")]
+#[packet(id = 0x02, versions = "1.19")]
+pub struct SpawnPlayer {
+    pub entity_id: VarInt,
+    pub player_uuid: uuid::Uuid,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: u8,
+    pub pitch: u8,
+}
+
+/// Moves an entity by less than 8 blocks, deltas are in 4096ths of a block.
+#[derive(Debug, Packet)]
+#[packet(id = 0x29, versions = "1.17.1..=1.18")]
+#[packet(id = 0x26, versions = "1.19")]
+#[packet(id = 0x2C, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x2E, versions = "1.20.5..")]
+pub struct EntityPosition {
+    pub entity_id: VarInt,
+    pub delta_x: i16,
+    pub delta_y: i16,
+    pub delta_z: i16,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x2A, versions = "1.17.1..=1.18")]
+#[packet(id = 0x27, versions = "1.19")]
+#[packet(id = 0x2D, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x2F, versions = "1.20.5..")]
+pub struct EntityPositionAndRotation {
+    pub entity_id: VarInt,
+    pub delta_x: i16,
+    pub delta_y: i16,
+    pub delta_z: i16,
+    pub yaw: u8,
+    pub pitch: u8,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x2B, versions = "1.17.1..=1.18")]
+#[packet(id = 0x28, versions = "1.19")]
+#[packet(id = 0x2E, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x30, versions = "1.20.5..")]
+pub struct EntityRotation {
+    pub entity_id: VarInt,
+    pub yaw: u8,
+    pub pitch: u8,
+    pub on_ground: bool,
+}
+
+/// Moves an entity to an absolute position, for moves of 8 blocks or more.
+#[derive(Debug, Packet)]
+#[packet(id = 0x61, versions = "1.17.1")]
+#[packet(id = 0x62, versions = "1.18")]
+#[packet(id = 0x63, versions = "1.19")]
+#[packet(id = 0x6B, versions = "1.20.2")]
+#[packet(id = 0x6D, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x70, versions = "1.20.5..")]
+pub struct EntityTeleport {
+    pub entity_id: VarInt,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: u8,
+    pub pitch: u8,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x3A, versions = "1.17.1..=1.18")]
+#[packet(id = 0x38, versions = "1.19")]
+#[packet(id = 0x40, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x42, versions = "1.20.5..")]
+pub struct DestroyEntities {
+    pub entity_ids: Prefixed<VarInt, Vec<VarInt>>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x4D, versions = "1.17.1..=1.19")]
+#[packet(id = 0x54, versions = "1.20.2")]
+#[packet(id = 0x56, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x58, versions = "1.20.5..")]
+pub struct EntityMetadata {
+    pub entity_id: VarInt,
+    #[field(mapped)]
+    pub metadata: crate::entity_metadata::EntityMetadata,
+}
+
+/// Moves the local player, each value set in `flags` is relative to where the client is.
+///
+/// The client answers with a `TeleportConfirm` for `teleport_id`, and the server ignores its
+/// movement until then.
+#[derive(Debug, Packet)]
+#[packet(id = 0x38, versions = "1.17.1..=1.18")]
+#[packet(id = 0x36, versions = "1.19")]
+#[packet(id = 0x3E, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x40, versions = "1.20.5..")]
+pub struct PlayerPositionAndLook {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub flags: super::RelativeFlags,
+    pub teleport_id: VarInt,
+    #[field(until = "1.20.2")]
+    pub dismount_vehicle: bool,
+}
+
+/// Opens a window of a menu type such as `minecraft:generic_9x3`, by its registry id.
+#[derive(Debug, Packet)]
+#[packet(id = 0x2E, versions = "1.17.1..=1.18")]
+#[packet(id = 0x2B, versions = "1.19")]
+#[packet(id = 0x31, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x33, versions = "1.20.5..")]
+pub struct OpenWindow {
+    pub window_id: VarInt,
+    pub window_type: VarInt,
+    #[field(mapped)]
+    pub title: Component,
+}
+
+/// Replaces every slot of a window, and the item on the cursor.
+///
+/// `state_id` increases with each change the server makes, the client sends the last one it saw
+/// in `ClickWindow`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x14, versions = "1.17.1..=1.18")]
+#[packet(id = 0x11, versions = "1.19")]
+#[packet(id = 0x13, versions = "1.20.2..")]
+pub struct WindowItems {
+    pub window_id: u8,
+    pub state_id: VarInt,
+    #[field(mapped)]
+    pub slots: Prefixed<VarInt, Vec<crate::slot::Slot>>,
+    #[field(mapped)]
+    pub carried_item: crate::slot::Slot,
+}
+
+/// Changes one slot, window -1 and slot -1 is the item on the cursor.
+#[derive(Debug, Packet)]
+#[packet(id = 0x16, versions = "1.17.1..=1.18")]
+#[packet(id = 0x13, versions = "1.19")]
+#[packet(id = 0x15, versions = "1.20.2..")]
+pub struct SetSlot {
+    pub window_id: i8,
+    pub state_id: VarInt,
+    pub slot: i16,
+    #[field(mapped)]
+    pub slot_data: crate::slot::Slot,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x13, versions = "1.17.1..=1.18")]
+#[packet(id = 0x10, versions = "1.19")]
+#[packet(id = 0x12, versions = "1.20.2..")]
+pub struct CloseWindow {
+    pub window_id: u8,
+}
+
+/// The client echoes `keep_alive_id` back, and is disconnected if it does not in time.
+#[derive(Debug, Packet)]
+#[packet(id = 0x21, versions = "1.17.1..=1.18")]
+#[packet(id = 0x1E, versions = "1.19")]
+#[packet(id = 0x24, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x26, versions = "1.20.5..")]
+pub struct KeepAlive {
+    pub keep_alive_id: i64,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x16, versions = "1.20.5..")]
+pub struct CookieRequest {
+    pub key: Identifier,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x6B, versions = "1.20.5..")]
+pub struct StoreCookie {
+    pub key: Identifier,
+    pub payload: CookiePayload,
+}
+
+/// Returns the client to configuration, which it acknowledges with `AcknowledgeConfiguration`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x65, versions = "1.20.2")]
+#[packet(id = 0x67, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x69, versions = "1.20.5..")]
+pub struct StartConfiguration {}
+
+/// Tells the client to reconnect to another server, which receives a handshake with `NextState::Transfer`.
+#[derive(Debug, Packet)]
+#[packet(id = 0x73, versions = "1.20.5..")]
+pub struct Transfer {
+    pub host: TransferHost,
+    pub port: VarInt,
+}
+
+registry! {
+    DeclareCommands,
+    PluginMessage,
+    Disconnect,
+    ChatMessage,
+    PlayerChatMessage,
+    SystemChat,
+    JoinGame,
+    Respawn,
+    PlayerInfo,
+    PlayerInfoRemove,
+    PlayerInfoUpdate,
+    TabListHeaderAndFooter,
+    BossBar,
+    SetTitleText,
+    SetSubtitleText,
+    SetActionBarText,
+    SetTitlesAnimation,
+    ClearTitles,
+    ScoreboardObjective,
+    UpdateScore,
+    ResetScore,
+    DisplayObjective,
+    Teams,
+    ChunkData,
+    ChunkDataAndUpdateLight,
+    UpdateLight,
+    UnloadChunk,
+    BlockUpdate,
+    MultiBlockChange,
+    SpawnEntity,
+    SpawnPlayer,
+    EntityPosition,
+    EntityPositionAndRotation,
+    EntityRotation,
+    EntityTeleport,
+    DestroyEntities,
+    EntityMetadata,
+    PlayerPositionAndLook,
+    OpenWindow,
+    WindowItems,
+    SetSlot,
+    CloseWindow,
+    KeepAlive,
+    CookieRequest,
+    StoreCookie,
+    StartConfiguration,
+    Transfer,
 }
@@ -1,2 +1,1800 @@
+use crate::chat::{Color, Component, Style};
+use crate::nbt::Nbt;
+use crate::fields::{read_var_long, var_long_bytes, FixedArray, Position, Prefixed, PrefixedOption, MAX_PREALLOCATION};
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
+use crate::registry::{PlayerPublicKey, Property};
+use crate::slot::Slot;
+use crate::{strict_enum, strict_flags};
+use minecraft_data_types::auto_string;
+use minecraft_data_types::common::Identifier;
+use minecraft_data_types::encoder::{AsyncEncodable, Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+
+auto_string!(ChatString, 256);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ChatString, 256);
+
+auto_string!(ArgumentName, 16);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ArgumentName, 16);
+
+/// An RSA signature of a chat message or command argument from 1.19.1.
+pub type MessageSignature = FixedArray<256>;
+
+/// The messages a client has seen since its last acknowledgement, one bit per message.
+pub type AcknowledgedMessages = FixedArray<3>;
+
+/// The signature of a signed command argument, sized since 1.19.1.
+#[derive(Debug)]
+pub struct ArgumentSignature {
+    pub name: ArgumentName,
+    pub signature: MessageSignature,
+}
+
+/// The signature of a signed command argument in 1.19, with a length prefix.
+#[derive(Debug)]
+pub struct LegacyArgumentSignature {
+    pub name: ArgumentName,
+    pub signature: Prefixed<VarInt, Vec<u8>>,
+}
+
+macro_rules! argument_signature {
+    ($($name:ident),*) => {
+        $(
+            impl Decodable for $name {
+                fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+                    Ok(Self {
+                        name: ArgumentName::decode(reader)?,
+                        signature: Decodable::decode(reader)?,
+                    })
+                }
+            }
+
+            impl Encodable for $name {
+                fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+                    self.name.encode(writer)?;
+                    self.signature.encode(writer)
+                }
+
+                fn size(&self) -> anyhow::Result<VarInt> {
+                    Ok(self.name.size()? + self.signature.size()?)
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl AsyncEncodable for $name {
+                async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+                    &self,
+                    writer: &mut W,
+                ) -> anyhow::Result<()> {
+                    self.name.async_encode(writer).await?;
+                    self.signature.async_encode(writer).await
+                }
+            }
+
+            #[cfg(test)]
+            impl crate::registry::round_trip::ArbitraryField for $name {
+                fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+                    Self {
+                        name: ArgumentName::arbitrary_field(runner),
+                        signature: crate::registry::round_trip::ArbitraryField::arbitrary_field(runner),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+argument_signature!(ArgumentSignature, LegacyArgumentSignature);
+
+/// A previously seen signature, by its index in the receiver's signature cache when it has one.
+#[derive(Debug)]
+pub enum PackedSignature {
+    Cached(i32),
+    Full(Box<MessageSignature>),
+}
+
+impl Decodable for PackedSignature {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        match *VarInt::decode(reader)? {
+            0 => Ok(PackedSignature::Full(Box::new(MessageSignature::decode(reader)?))),
+            id if id > 0 => Ok(PackedSignature::Cached(id - 1)),
+            id => anyhow::bail!("Invalid cached signature id {}.", id),
+        }
+    }
+}
+
+impl PackedSignature {
+    /// Cached ids are sent one higher, as 0 marks a full signature.
+    fn packed_cached_id(id: i32) -> anyhow::Result<VarInt> {
+        id.checked_add(1)
+            .filter(|_| id >= 0)
+            .map(VarInt::from)
+            .ok_or_else(|| anyhow::anyhow!("Invalid cached signature id {}.", id))
+    }
+}
+
+impl Encodable for PackedSignature {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        match self {
+            PackedSignature::Cached(id) => Self::packed_cached_id(*id)?.encode(writer),
+            PackedSignature::Full(signature) => {
+                VarInt::from(0).encode(writer)?;
+                signature.encode(writer)
+            }
+        }
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        match self {
+            PackedSignature::Cached(id) => Self::packed_cached_id(*id)?.size(),
+            PackedSignature::Full(signature) => Ok(VarInt::from(0).size()? + signature.size()?),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for PackedSignature {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        match self {
+            PackedSignature::Cached(id) => Self::packed_cached_id(*id)?.async_encode(writer).await,
+            PackedSignature::Full(signature) => {
+                VarInt::from(0).async_encode(writer).await?;
+                signature.async_encode(writer).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for PackedSignature {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        if bool::arbitrary_field(runner) {
+            PackedSignature::Cached(crate::registry::round_trip::sample(runner, 0..4096))
+        } else {
+            PackedSignature::Full(Box::new(MessageSignature::arbitrary_field(runner)))
+        }
+    }
+}
+
+/// Which parts of a chat message the server's filter hid, as a bit per character when partial.
+#[derive(Debug)]
+pub enum FilterMask {
+    PassThrough,
+    FullyFiltered,
+    PartiallyFiltered(Prefixed<VarInt, Vec<i64>>),
+}
+
+impl Decodable for FilterMask {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        match *VarInt::decode(reader)? {
+            0 => Ok(FilterMask::PassThrough),
+            1 => Ok(FilterMask::FullyFiltered),
+            2 => Ok(FilterMask::PartiallyFiltered(Prefixed::decode(reader)?)),
+            index => anyhow::bail!("Failed to decode index {} for FilterMask.", index),
+        }
+    }
+}
+
+impl FilterMask {
+    fn index(&self) -> VarInt {
+        VarInt::from(match self {
+            FilterMask::PassThrough => 0,
+            FilterMask::FullyFiltered => 1,
+            FilterMask::PartiallyFiltered(_) => 2,
+        })
+    }
+}
+
+impl Encodable for FilterMask {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.index().encode(writer)?;
+        match self {
+            FilterMask::PartiallyFiltered(mask) => mask.encode(writer),
+            _ => Ok(()),
+        }
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        match self {
+            FilterMask::PartiallyFiltered(mask) => Ok(self.index().size()? + mask.size()?),
+            _ => self.index().size(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for FilterMask {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.index().async_encode(writer).await?;
+        match self {
+            FilterMask::PartiallyFiltered(mask) => mask.async_encode(writer).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for FilterMask {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        match crate::registry::round_trip::sample(runner, 0..3) {
+            0 => FilterMask::PassThrough,
+            1 => FilterMask::FullyFiltered,
+            _ => FilterMask::PartiallyFiltered(Prefixed::arbitrary_field(runner)),
+        }
+    }
+}
+
+auto_string!(PlayerName, 16);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(PlayerName, 16);
+
+strict_enum! {
+    @internal
+    PlayerInfoAction; VarInt {
+        0 => AddPlayer;
+        1 => UpdateGameMode;
+        2 => UpdateLatency;
+        3 => UpdateDisplayName;
+        4 => RemovePlayer;
+    }
+}
+
+strict_flags! {
+    @internal
+    /// The fields sent for every player of a `PlayerInfoUpdate`, in the order of their bits.
+    PlayerInfoActions; u8; Reject {
+        0x01 => ADD_PLAYER;
+        0x02 => INITIALIZE_CHAT;
+        0x04 => UPDATE_GAME_MODE;
+        0x08 => UPDATE_LISTED;
+        0x10 => UPDATE_LATENCY;
+        0x20 => UPDATE_DISPLAY_NAME;
+    }
+}
+
+/// A player's chat session, shared through the tab list so others can verify their messages.
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    /// Nil in 1.19, where the key was not tied to a session.
+    pub session_id: uuid::Uuid,
+    pub public_key: PlayerPublicKey,
+}
+
+impl Decodable for ChatSession {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            session_id: uuid::Uuid::decode(reader)?,
+            public_key: PlayerPublicKey::decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for ChatSession {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.session_id.encode(writer)?;
+        self.public_key.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.session_id.size()? + self.public_key.size()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for ChatSession {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.session_id.async_encode(writer).await?;
+        self.public_key.async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for ChatSession {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            session_id: uuid::Uuid::arbitrary_field(runner),
+            public_key: PlayerPublicKey::arbitrary_field(runner),
+        }
+    }
+}
+
+/// One player in the tab list, independent of the version it is sent to.
+///
+/// Updates only carry the fields their actions change, the rest decode as [`PlayerInfoEntry::new`].
+#[derive(Debug, Clone)]
+pub struct PlayerInfoEntry {
+    pub uuid: uuid::Uuid,
+    pub name: PlayerName,
+    pub properties: Prefixed<VarInt, Vec<Property>>,
+    pub chat_session: Option<ChatSession>,
+    pub game_mode: VarInt,
+    /// Whether the player is shown, unlisted players are always shown before 1.19.3.
+    pub listed: bool,
+    /// Milliseconds.
+    pub latency: VarInt,
+    pub display_name: Option<Component>,
+}
+
+impl PlayerInfoEntry {
+    pub fn new(uuid: uuid::Uuid, name: &str) -> Self {
+        Self {
+            uuid,
+            name: PlayerName::from(name),
+            properties: Prefixed::default(),
+            chat_session: None,
+            game_mode: VarInt::from(0),
+            listed: true,
+            latency: VarInt::from(0),
+            display_name: None,
+        }
+    }
+
+    fn read<R: std::io::Read>(
+        protocol: MCProtocol,
+        reader: &mut R,
+        actions: &PlayerInfoActions,
+    ) -> anyhow::Result<Self> {
+        let mut entry = Self::new(uuid::Uuid::decode(reader)?, "");
+        if actions.contains(PlayerInfoActions::ADD_PLAYER) {
+            entry.name = PlayerName::decode(reader)?;
+            entry.properties = Prefixed::decode(reader)?;
+        }
+        if actions.contains(PlayerInfoActions::INITIALIZE_CHAT) {
+            entry.chat_session = PrefixedOption::decode(reader)?.0;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_GAME_MODE) {
+            entry.game_mode = VarInt::decode(reader)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_LISTED) {
+            entry.listed = bool::decode(reader)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_LATENCY) {
+            entry.latency = VarInt::decode(reader)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_DISPLAY_NAME) {
+            entry.display_name = PrefixedOption::decode_mapped(protocol, reader)?.0;
+        }
+        Ok(entry)
+    }
+
+    fn write<W: std::io::Write>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+        actions: &PlayerInfoActions,
+    ) -> anyhow::Result<()> {
+        self.uuid.encode(writer)?;
+        if actions.contains(PlayerInfoActions::ADD_PLAYER) {
+            self.name.encode(writer)?;
+            self.properties.encode(writer)?;
+        }
+        if actions.contains(PlayerInfoActions::INITIALIZE_CHAT) {
+            write_optional(&self.chat_session, writer)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_GAME_MODE) {
+            self.game_mode.encode(writer)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_LISTED) {
+            self.listed.encode(writer)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_LATENCY) {
+            self.latency.encode(writer)?;
+        }
+        if actions.contains(PlayerInfoActions::UPDATE_DISPLAY_NAME) {
+            write_optional_mapped(protocol, &self.display_name, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the entry of a pre 1.19.3 `PlayerInfo` action.
+    fn read_legacy<R: std::io::Read>(
+        protocol: MCProtocol,
+        reader: &mut R,
+        action: &PlayerInfoAction,
+    ) -> anyhow::Result<Self> {
+        let mut entry = Self::new(uuid::Uuid::decode(reader)?, "");
+        match action {
+            PlayerInfoAction::AddPlayer => {
+                entry.name = PlayerName::decode(reader)?;
+                entry.properties = Prefixed::decode(reader)?;
+                entry.game_mode = VarInt::decode(reader)?;
+                entry.latency = VarInt::decode(reader)?;
+                entry.display_name = PrefixedOption::decode(reader)?.0;
+                if protocol >= MCProtocol::V1_19 {
+                    entry.chat_session = PrefixedOption::<PlayerPublicKey>::decode(reader)?
+                        .0
+                        .map(|public_key| ChatSession {
+                            session_id: uuid::Uuid::nil(),
+                            public_key,
+                        });
+                }
+            }
+            PlayerInfoAction::UpdateGameMode => entry.game_mode = VarInt::decode(reader)?,
+            PlayerInfoAction::UpdateLatency => entry.latency = VarInt::decode(reader)?,
+            PlayerInfoAction::UpdateDisplayName => entry.display_name = PrefixedOption::decode(reader)?.0,
+            PlayerInfoAction::RemovePlayer => {}
+        }
+        Ok(entry)
+    }
+
+    fn write_legacy<W: std::io::Write>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+        action: &PlayerInfoAction,
+    ) -> anyhow::Result<()> {
+        self.uuid.encode(writer)?;
+        match action {
+            PlayerInfoAction::AddPlayer => {
+                self.name.encode(writer)?;
+                self.properties.encode(writer)?;
+                self.game_mode.encode(writer)?;
+                self.latency.encode(writer)?;
+                write_optional(&self.display_name, writer)?;
+                if protocol >= MCProtocol::V1_19 {
+                    self.chat_session.is_some().encode(writer)?;
+                    if let Some(session) = &self.chat_session {
+                        session.public_key.encode(writer)?;
+                    }
+                }
+                Ok(())
+            }
+            PlayerInfoAction::UpdateGameMode => self.game_mode.encode(writer),
+            PlayerInfoAction::UpdateLatency => self.latency.encode(writer),
+            PlayerInfoAction::UpdateDisplayName => write_optional(&self.display_name, writer),
+            PlayerInfoAction::RemovePlayer => Ok(()),
+        }
+    }
+}
+
+/// Writes an option the way `PrefixedOption` does, without taking ownership of it.
+fn write_optional<T: Encodable, W: std::io::Write>(value: &Option<T>, writer: &mut W) -> anyhow::Result<()> {
+    value.is_some().encode(writer)?;
+    match value {
+        Some(value) => value.encode(writer),
+        None => Ok(()),
+    }
+}
+
+fn write_optional_mapped<T: MapEncodable, W: std::io::Write>(
+    protocol: MCProtocol,
+    value: &Option<T>,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    value.is_some().encode(writer)?;
+    match value {
+        Some(value) => value.encode_mapped(protocol, writer),
+        None => Ok(()),
+    }
+}
+
+/// The players of a `PlayerInfoUpdate` and which of their fields it carries.
+#[derive(Debug)]
+pub struct PlayerInfoChanges {
+    pub actions: PlayerInfoActions,
+    pub players: Vec<PlayerInfoEntry>,
+}
+
+/// The players of a pre 1.19.3 `PlayerInfo` and the one action applied to each.
+#[derive(Debug)]
+pub struct LegacyPlayerInfo {
+    pub action: PlayerInfoAction,
+    pub players: Vec<PlayerInfoEntry>,
+}
+
+fn read_players<R: std::io::Read, F: FnMut(&mut R) -> anyhow::Result<PlayerInfoEntry>>(
+    reader: &mut R,
+    mut read: F,
+) -> anyhow::Result<Vec<PlayerInfoEntry>> {
+    let count = usize::try_from(*VarInt::decode(reader)?)?;
+    let mut players = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+    for _ in 0..count {
+        players.push(read(reader)?);
+    }
+    Ok(players)
+}
+
+macro_rules! player_info_codec {
+    ($($name:ident, $kind:ident, $read:ident, $write:ident;)*) => {
+        $(
+            impl MapDecodable for $name {
+                fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+                    let $kind = Decodable::decode(reader)?;
+                    let players = read_players(reader, |reader| PlayerInfoEntry::$read(protocol, reader, &$kind))?;
+                    Ok(Self { $kind, players })
+                }
+            }
+
+            impl MapEncodable for $name {
+                fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+                    self.$kind.encode(writer)?;
+                    VarInt::try_from(self.players.len())?.encode(writer)?;
+                    for player in &self.players {
+                        player.$write(protocol, writer, &self.$kind)?;
+                    }
+                    Ok(())
+                }
+
+                fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+                    let mut bytes = Vec::new();
+                    self.encode_mapped(protocol, &mut bytes)?;
+                    VarInt::try_from(bytes.len())
+                }
+            }
+
+            #[async_trait::async_trait]
+            impl AsyncMapEncodable for $name {
+                async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+                    &self,
+                    protocol: MCProtocol,
+                    writer: &mut W,
+                ) -> anyhow::Result<()> {
+                    let mut bytes = Vec::new();
+                    self.encode_mapped(protocol, &mut bytes)?;
+                    tokio::io::AsyncWriteExt::write_all(writer, &bytes).await?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+player_info_codec! {
+    PlayerInfoChanges, actions, read, write;
+    LegacyPlayerInfo, action, read_legacy, write_legacy;
+}
+
+#[cfg(test)]
+mod arbitrary_player_info {
+    use super::*;
+    use crate::registry::round_trip::{sample, ArbitraryField};
+    use proptest::test_runner::TestRunner;
+
+    fn arbitrary_players<F: FnMut(&mut TestRunner, &mut PlayerInfoEntry)>(
+        runner: &mut TestRunner,
+        mut fill: F,
+    ) -> Vec<PlayerInfoEntry> {
+        (0..sample(runner, 0..4usize))
+            .map(|_| {
+                let mut entry = PlayerInfoEntry::new(uuid::Uuid::arbitrary_field(runner), "");
+                fill(runner, &mut entry);
+                entry
+            })
+            .collect()
+    }
+
+    impl ArbitraryField for PlayerInfoChanges {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            let actions = PlayerInfoActions::arbitrary_field(runner);
+            let players = arbitrary_players(runner, |runner, entry| {
+                if actions.contains(PlayerInfoActions::ADD_PLAYER) {
+                    entry.name = PlayerName::arbitrary_field(runner);
+                    entry.properties = Prefixed::arbitrary_field(runner);
+                }
+                if actions.contains(PlayerInfoActions::INITIALIZE_CHAT) {
+                    entry.chat_session = PrefixedOption::arbitrary_field(runner).0;
+                }
+                if actions.contains(PlayerInfoActions::UPDATE_GAME_MODE) {
+                    entry.game_mode = VarInt::arbitrary_field(runner);
+                }
+                if actions.contains(PlayerInfoActions::UPDATE_LISTED) {
+                    entry.listed = bool::arbitrary_field(runner);
+                }
+                if actions.contains(PlayerInfoActions::UPDATE_LATENCY) {
+                    entry.latency = VarInt::arbitrary_field(runner);
+                }
+                if actions.contains(PlayerInfoActions::UPDATE_DISPLAY_NAME) {
+                    entry.display_name = PrefixedOption::arbitrary_field(runner).0;
+                }
+            });
+            Self { actions, players }
+        }
+    }
+
+    impl ArbitraryField for LegacyPlayerInfo {
+        fn arbitrary_field(runner: &mut TestRunner) -> Self {
+            let action = PlayerInfoAction::arbitrary_field(runner);
+            let players = arbitrary_players(runner, |runner, entry| match action {
+                PlayerInfoAction::AddPlayer => {
+                    entry.name = PlayerName::arbitrary_field(runner);
+                    entry.properties = Prefixed::arbitrary_field(runner);
+                    entry.game_mode = VarInt::arbitrary_field(runner);
+                    entry.latency = VarInt::arbitrary_field(runner);
+                    entry.display_name = PrefixedOption::arbitrary_field(runner).0;
+                }
+                PlayerInfoAction::UpdateGameMode => entry.game_mode = VarInt::arbitrary_field(runner),
+                PlayerInfoAction::UpdateLatency => entry.latency = VarInt::arbitrary_field(runner),
+                PlayerInfoAction::UpdateDisplayName => {
+                    entry.display_name = PrefixedOption::arbitrary_field(runner).0
+                }
+                PlayerInfoAction::RemovePlayer => {}
+            });
+            Self { action, players }
+        }
+    }
+}
+
+/// Where the player last died, sent from 1.19 for the recovery compass.
+#[derive(Debug, Clone)]
+pub struct DeathLocation {
+    pub dimension_name: Identifier,
+    pub position: Position,
+}
+
+impl Decodable for DeathLocation {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            dimension_name: Identifier::decode(reader)?,
+            position: Position::decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for DeathLocation {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.dimension_name.encode(writer)?;
+        self.position.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.dimension_name.size()? + self.position.size()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for DeathLocation {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.dimension_name.async_encode(writer).await?;
+        self.position.async_encode(writer).await
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for DeathLocation {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            dimension_name: Identifier::arbitrary_field(runner),
+            position: Position::arbitrary_field(runner),
+        }
+    }
+}
+
+strict_enum! {
+    @internal
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    BossBarColor; VarInt {
+        0 => Pink;
+        1 => Blue;
+        2 => Red;
+        3 => Green;
+        4 => Yellow;
+        5 => Purple;
+        6 => White;
+    }
+}
+
+strict_enum! {
+    @internal
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    BossBarDivision; VarInt {
+        0 => None;
+        1 => Notches6;
+        2 => Notches10;
+        3 => Notches12;
+        4 => Notches20;
+    }
+}
+
+strict_flags! {
+    @internal
+    /// Effects applied to the world while the boss bar is shown.
+    BossBarFlags; u8; Preserve {
+        0x01 => DARKEN_SKY;
+        0x02 => DRAGON_BAR;
+        0x04 => CREATE_FOG;
+    }
+}
+
+/// What a `BossBar` packet does to the bar, each action carrying only the fields it changes.
+#[derive(Debug, Clone)]
+pub enum BossBarAction {
+    Add {
+        title: Component,
+        /// From 0 to 1.
+        health: f32,
+        color: BossBarColor,
+        division: BossBarDivision,
+        flags: BossBarFlags,
+    },
+    Remove,
+    UpdateHealth(f32),
+    UpdateTitle(Component),
+    UpdateStyle {
+        color: BossBarColor,
+        division: BossBarDivision,
+    },
+    UpdateFlags(BossBarFlags),
+}
+
+impl MapDecodable for BossBarAction {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(match *VarInt::decode(reader)? {
+            0 => Self::Add {
+                title: Component::decode_mapped(protocol, reader)?,
+                health: f32::decode(reader)?,
+                color: BossBarColor::decode(reader)?,
+                division: BossBarDivision::decode(reader)?,
+                flags: BossBarFlags::decode(reader)?,
+            },
+            1 => Self::Remove,
+            2 => Self::UpdateHealth(f32::decode(reader)?),
+            3 => Self::UpdateTitle(Component::decode_mapped(protocol, reader)?),
+            4 => Self::UpdateStyle {
+                color: BossBarColor::decode(reader)?,
+                division: BossBarDivision::decode(reader)?,
+            },
+            5 => Self::UpdateFlags(BossBarFlags::decode(reader)?),
+            action => anyhow::bail!("Unknown boss bar action {}.", action),
+        })
+    }
+}
+
+impl BossBarAction {
+    fn id(&self) -> VarInt {
+        VarInt::from(match self {
+            Self::Add { .. } => 0,
+            Self::Remove => 1,
+            Self::UpdateHealth(_) => 2,
+            Self::UpdateTitle(_) => 3,
+            Self::UpdateStyle { .. } => 4,
+            Self::UpdateFlags(_) => 5,
+        })
+    }
+}
+
+impl MapEncodable for BossBarAction {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.id().encode(writer)?;
+        match self {
+            Self::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => {
+                title.encode_mapped(protocol, writer)?;
+                health.encode(writer)?;
+                color.encode(writer)?;
+                division.encode(writer)?;
+                flags.encode(writer)
+            }
+            Self::Remove => Ok(()),
+            Self::UpdateHealth(health) => health.encode(writer),
+            Self::UpdateTitle(title) => title.encode_mapped(protocol, writer),
+            Self::UpdateStyle { color, division } => {
+                color.encode(writer)?;
+                division.encode(writer)
+            }
+            Self::UpdateFlags(flags) => flags.encode(writer),
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        let size = match self {
+            Self::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => title.size_mapped(protocol)? + health.size()? + color.size()? + division.size()? + flags.size()?,
+            Self::Remove => VarInt::from(0),
+            Self::UpdateHealth(health) => health.size()?,
+            Self::UpdateTitle(title) => title.size_mapped(protocol)?,
+            Self::UpdateStyle { color, division } => color.size()? + division.size()?,
+            Self::UpdateFlags(flags) => flags.size()?,
+        };
+        Ok(self.id().size()? + size)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for BossBarAction {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.id().async_encode(writer).await?;
+        match self {
+            Self::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => {
+                title.encode_mapped_async(protocol, writer).await?;
+                health.async_encode(writer).await?;
+                color.async_encode(writer).await?;
+                division.async_encode(writer).await?;
+                flags.async_encode(writer).await
+            }
+            Self::Remove => Ok(()),
+            Self::UpdateHealth(health) => health.async_encode(writer).await,
+            Self::UpdateTitle(title) => title.encode_mapped_async(protocol, writer).await,
+            Self::UpdateStyle { color, division } => {
+                color.async_encode(writer).await?;
+                division.async_encode(writer).await
+            }
+            Self::UpdateFlags(flags) => flags.async_encode(writer).await,
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for BossBarAction {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        match sample(runner, 0..6) {
+            0 => Self::Add {
+                title: Component::arbitrary_field(runner),
+                health: f32::arbitrary_field(runner),
+                color: BossBarColor::arbitrary_field(runner),
+                division: BossBarDivision::arbitrary_field(runner),
+                flags: BossBarFlags::arbitrary_field(runner),
+            },
+            1 => Self::Remove,
+            2 => Self::UpdateHealth(f32::arbitrary_field(runner)),
+            3 => Self::UpdateTitle(Component::arbitrary_field(runner)),
+            4 => Self::UpdateStyle {
+                color: BossBarColor::arbitrary_field(runner),
+                division: BossBarDivision::arbitrary_field(runner),
+            },
+            _ => Self::UpdateFlags(BossBarFlags::arbitrary_field(runner)),
+        }
+    }
+}
+
+auto_string!(ScoreboardName, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(ScoreboardName, 40);
+
+strict_enum! {
+    @internal
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    ObjectiveType; VarInt {
+        0 => Integer;
+        1 => Hearts;
+    }
+}
+
+/// How the sidebar and tab list show a score from 1.20.3.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberFormat {
+    /// Hides the score.
+    Blank,
+    Styled(Style),
+    /// Shows this component instead of the score.
+    Fixed(Component),
+}
+
+impl Decodable for NumberFormat {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(match *VarInt::decode(reader)? {
+            0 => Self::Blank,
+            1 => Self::Styled(Style::from_nbt(&Nbt::decode(reader)?)?),
+            2 => Self::Fixed(Component::from_nbt(&Nbt::decode(reader)?)?),
+            format => anyhow::bail!("Unknown number format {}.", format),
+        })
+    }
+}
+
+impl NumberFormat {
+    fn id(&self) -> VarInt {
+        VarInt::from(match self {
+            Self::Blank => 0,
+            Self::Styled(_) => 1,
+            Self::Fixed(_) => 2,
+        })
+    }
+
+    /// Number formats only exist from 1.20.3, where components are always NBT.
+    fn nbt(&self) -> Option<Nbt> {
+        match self {
+            Self::Blank => None,
+            Self::Styled(style) => Some(style.to_nbt()),
+            Self::Fixed(component) => Some(component.to_nbt()),
+        }
+    }
+}
+
+impl Encodable for NumberFormat {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.id().encode(writer)?;
+        match self.nbt() {
+            Some(nbt) => nbt.encode(writer),
+            None => Ok(()),
+        }
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        match self.nbt() {
+            Some(nbt) => Ok(self.id().size()? + nbt.size()?),
+            None => self.id().size(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for NumberFormat {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.id().async_encode(writer).await?;
+        match self.nbt() {
+            Some(nbt) => nbt.async_encode(writer).await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for NumberFormat {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        match crate::registry::round_trip::sample(runner, 0..3) {
+            0 => Self::Blank,
+            1 => Self::Styled(Component::arbitrary_field(runner).style),
+            _ => Self::Fixed(Component::arbitrary_field(runner)),
+        }
+    }
+}
+
+/// How an objective is shown, with the number format its scores default to from 1.20.3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectiveDisplay {
+    pub display_name: Component,
+    pub render_type: ObjectiveType,
+    pub number_format: Option<NumberFormat>,
+}
+
+impl ObjectiveDisplay {
+    fn read<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            display_name: Component::decode_mapped(protocol, reader)?,
+            render_type: ObjectiveType::decode(reader)?,
+            number_format: if uses_number_formats(protocol) {
+                PrefixedOption::decode(reader)?.0
+            } else {
+                None
+            },
+        })
+    }
+
+    fn write<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.display_name.encode_mapped(protocol, writer)?;
+        self.render_type.encode(writer)?;
+        if uses_number_formats(protocol) {
+            write_optional(&self.number_format, writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// 1.20.3 added number formats and replaced score removal with `ResetScore`.
+fn uses_number_formats(protocol: MCProtocol) -> bool {
+    protocol >= MCProtocol::V1_20_3
+}
+
+/// What a `ScoreboardObjective` packet does, sent as a byte mode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectiveAction {
+    Create(ObjectiveDisplay),
+    Remove,
+    Update(ObjectiveDisplay),
+}
+
+impl MapDecodable for ObjectiveAction {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(match i8::decode(reader)? {
+            0 => Self::Create(ObjectiveDisplay::read(protocol, reader)?),
+            1 => Self::Remove,
+            2 => Self::Update(ObjectiveDisplay::read(protocol, reader)?),
+            mode => anyhow::bail!("Unknown objective mode {}.", mode),
+        })
+    }
+}
+
+impl MapEncodable for ObjectiveAction {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        match self {
+            Self::Create(display) => {
+                0i8.encode(writer)?;
+                display.write(protocol, writer)
+            }
+            Self::Remove => 1i8.encode(writer),
+            Self::Update(display) => {
+                2i8.encode(writer)?;
+                display.write(protocol, writer)
+            }
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        let mut bytes = Vec::new();
+        self.encode_mapped(protocol, &mut bytes)?;
+        VarInt::try_from(bytes.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for ObjectiveAction {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        self.encode_mapped(protocol, &mut bytes)?;
+        tokio::io::AsyncWriteExt::write_all(writer, &bytes).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for ObjectiveAction {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        let display = |runner: &mut proptest::test_runner::TestRunner| ObjectiveDisplay {
+            display_name: Component::arbitrary_field(runner),
+            render_type: ObjectiveType::arbitrary_field(runner),
+            number_format: PrefixedOption::arbitrary_field(runner).0,
+        };
+        match sample(runner, 0..3) {
+            0 => Self::Create(display(runner)),
+            1 => Self::Remove,
+            _ => Self::Update(display(runner)),
+        }
+    }
+}
+
+/// A score change, `UpdateScore` carries a byte action before 1.20.3 and only sets scores after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreUpdate {
+    /// Empty removes the holder's score from every objective, only before 1.20.3.
+    pub objective_name: ScoreboardName,
+    /// `None` removes the score, which only exists before 1.20.3. Send `ResetScore` afterwards.
+    pub value: Option<VarInt>,
+    /// Shown instead of the holder's name from 1.20.3.
+    pub display_name: Option<Component>,
+    /// Overrides the objective's number format from 1.20.3.
+    pub number_format: Option<NumberFormat>,
+}
+
+impl ScoreUpdate {
+    pub fn set(objective_name: &str, value: i32) -> Self {
+        Self {
+            objective_name: ScoreboardName::from(objective_name),
+            value: Some(VarInt::from(value)),
+            display_name: None,
+            number_format: None,
+        }
+    }
+
+    pub fn remove(objective_name: &str) -> Self {
+        Self {
+            value: None,
+            ..Self::set(objective_name, 0)
+        }
+    }
+}
+
+impl MapDecodable for ScoreUpdate {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if uses_number_formats(protocol) {
+            return Ok(Self {
+                objective_name: ScoreboardName::decode(reader)?,
+                value: Some(VarInt::decode(reader)?),
+                display_name: PrefixedOption::decode_mapped(protocol, reader)?.0,
+                number_format: PrefixedOption::decode(reader)?.0,
+            });
+        }
+        let action = i8::decode(reader)?;
+        let objective_name = ScoreboardName::decode(reader)?;
+        let value = match action {
+            0 => Some(VarInt::decode(reader)?),
+            1 => None,
+            action => anyhow::bail!("Unknown score action {}.", action),
+        };
+        Ok(Self {
+            objective_name,
+            value,
+            display_name: None,
+            number_format: None,
+        })
+    }
+}
+
+impl MapEncodable for ScoreUpdate {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        if !uses_number_formats(protocol) {
+            (self.value.is_none() as i8).encode(writer)?;
+            self.objective_name.encode(writer)?;
+            return match &self.value {
+                Some(value) => value.encode(writer),
+                None => Ok(()),
+            };
+        }
+        let value = self
+            .value
+            .ok_or_else(|| anyhow::anyhow!("Scores are removed with ResetScore from 1.20.3."))?;
+        self.objective_name.encode(writer)?;
+        value.encode(writer)?;
+        write_optional_mapped(protocol, &self.display_name, writer)?;
+        write_optional(&self.number_format, writer)
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        let mut bytes = Vec::new();
+        self.encode_mapped(protocol, &mut bytes)?;
+        VarInt::try_from(bytes.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for ScoreUpdate {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        self.encode_mapped(protocol, &mut bytes)?;
+        tokio::io::AsyncWriteExt::write_all(writer, &bytes).await?;
+        Ok(())
+    }
+}
+
+/// Always sets a value, as removals cannot be encoded for every protocol the packet supports.
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for ScoreUpdate {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            objective_name: ScoreboardName::arbitrary_field(runner),
+            value: Some(VarInt::arbitrary_field(runner)),
+            display_name: PrefixedOption::arbitrary_field(runner).0,
+            number_format: PrefixedOption::arbitrary_field(runner).0,
+        }
+    }
+}
+
+/// Where an objective is shown, a byte before 1.20.2 and a VarInt after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplaySlot {
+    List,
+    Sidebar,
+    BelowName,
+    /// The sidebar shown to members of teams with this color, from 0 to 15.
+    TeamSidebar(u8),
+}
+
+impl DisplaySlot {
+    fn id(&self) -> i32 {
+        match self {
+            Self::List => 0,
+            Self::Sidebar => 1,
+            Self::BelowName => 2,
+            Self::TeamSidebar(color) => 3 + i32::from(*color),
+        }
+    }
+
+    fn from_id(id: i32) -> anyhow::Result<Self> {
+        Ok(match id {
+            0 => Self::List,
+            1 => Self::Sidebar,
+            2 => Self::BelowName,
+            3..=18 => Self::TeamSidebar((id - 3) as u8),
+            id => anyhow::bail!("Unknown display slot {}.", id),
+        })
+    }
+
+    fn uses_var_int(protocol: MCProtocol) -> bool {
+        protocol >= MCProtocol::V1_20_2
+    }
+}
+
+impl MapDecodable for DisplaySlot {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if Self::uses_var_int(protocol) {
+            Self::from_id(*VarInt::decode(reader)?)
+        } else {
+            Self::from_id(i32::from(i8::decode(reader)?))
+        }
+    }
+}
+
+impl MapEncodable for DisplaySlot {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        if Self::uses_var_int(protocol) {
+            VarInt::from(self.id()).encode(writer)
+        } else {
+            (self.id() as i8).encode(writer)
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        if Self::uses_var_int(protocol) {
+            VarInt::from(self.id()).size()
+        } else {
+            Ok(VarInt::from(1))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for DisplaySlot {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        if Self::uses_var_int(protocol) {
+            VarInt::from(self.id()).async_encode(writer).await
+        } else {
+            (self.id() as i8).async_encode(writer).await
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for DisplaySlot {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self::from_id(crate::registry::round_trip::sample(runner, 0..19)).expect("Display slots go up to 18.")
+    }
+}
+
+auto_string!(TeamRuleString, 40);
+
+/// Declares a team rule sent by name, such as `hideForOtherTeams`.
+macro_rules! team_rule {
+    ($($rule:ident { $($name:literal => $variant:ident,)* })*) => {
+        $(
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub enum $rule {
+                $($variant,)*
+            }
+
+            impl $rule {
+                pub fn name(&self) -> &'static str {
+                    match self {
+                        $(Self::$variant => $name,)*
+                    }
+                }
+
+                pub fn from_name(name: &str) -> Option<Self> {
+                    match name {
+                        $($name => Some(Self::$variant),)*
+                        _ => None,
+                    }
+                }
+            }
+
+            impl Decodable for $rule {
+                fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+                    let name = TeamRuleString::decode(reader)?.to_string();
+                    Self::from_name(&name)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown {} {}.", stringify!($rule), name))
+                }
+            }
+
+            impl Encodable for $rule {
+                fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+                    TeamRuleString::from(self.name()).encode(writer)
+                }
+
+                fn size(&self) -> anyhow::Result<VarInt> {
+                    TeamRuleString::from(self.name()).size()
+                }
+            }
+
+            #[cfg(test)]
+            impl crate::registry::round_trip::ArbitraryField for $rule {
+                fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+                    let mut variants = vec![$(Self::$variant,)*];
+                    let index = crate::registry::round_trip::sample(runner, 0..variants.len());
+                    variants.swap_remove(index)
+                }
+            }
+        )*
+    };
+}
+
+team_rule! {
+    NameTagVisibility {
+        "always" => Always,
+        "hideForOtherTeams" => HideForOtherTeams,
+        "hideForOwnTeam" => HideForOwnTeam,
+        "never" => Never,
+    }
+    CollisionRule {
+        "always" => Always,
+        "pushOtherTeams" => PushOtherTeams,
+        "pushOwnTeam" => PushOwnTeam,
+        "never" => Never,
+    }
+}
+
+strict_flags! {
+    @internal
+    /// Whether team members can hurt each other and see each other while invisible.
+    TeamFlags; i8; Preserve {
+        0x01 => FRIENDLY_FIRE;
+        0x02 => SEE_INVISIBLE_TEAMMATES;
+    }
+}
+
+/// The index sent for a team without a color, after the 16 colors and 5 formatting codes.
+const TEAM_COLOR_RESET: i32 = 21;
+
+/// How a team and the names of its members are shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamInfo {
+    pub display_name: Component,
+    pub flags: TeamFlags,
+    pub name_tag_visibility: NameTagVisibility,
+    pub collision_rule: CollisionRule,
+    /// Colors member names, `None` leaves them white. Only the 16 named colors are supported.
+    pub color: Option<Color>,
+    pub prefix: Component,
+    pub suffix: Component,
+}
+
+impl TeamInfo {
+    pub fn new(display_name: Component) -> Self {
+        Self {
+            display_name,
+            flags: TeamFlags::empty(),
+            name_tag_visibility: NameTagVisibility::Always,
+            collision_rule: CollisionRule::Always,
+            color: None,
+            prefix: Component::default(),
+            suffix: Component::default(),
+        }
+    }
+
+    fn read<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            display_name: Component::decode_mapped(protocol, reader)?,
+            flags: TeamFlags::decode(reader)?,
+            name_tag_visibility: NameTagVisibility::decode(reader)?,
+            collision_rule: CollisionRule::decode(reader)?,
+            // Formatting codes are accepted by the client but do not color names.
+            color: u32::try_from(*VarInt::decode(reader)?)
+                .ok()
+                .and_then(|index| std::char::from_digit(index, 16))
+                .and_then(Color::from_legacy_code),
+            prefix: Component::decode_mapped(protocol, reader)?,
+            suffix: Component::decode_mapped(protocol, reader)?,
+        })
+    }
+
+    fn write<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.display_name.encode_mapped(protocol, writer)?;
+        self.flags.encode(writer)?;
+        self.name_tag_visibility.encode(writer)?;
+        self.collision_rule.encode(writer)?;
+        let color = match self.color {
+            Some(color) => color
+                .legacy_code()
+                .and_then(|code| code.to_digit(16))
+                .ok_or_else(|| anyhow::anyhow!("Teams cannot use the hex color {}.", color.name()))?
+                as i32,
+            None => TEAM_COLOR_RESET,
+        };
+        VarInt::from(color).encode(writer)?;
+        self.prefix.encode_mapped(protocol, writer)?;
+        self.suffix.encode_mapped(protocol, writer)
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for TeamInfo {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        Self {
+            display_name: Component::arbitrary_field(runner),
+            flags: TeamFlags::arbitrary_field(runner),
+            name_tag_visibility: NameTagVisibility::arbitrary_field(runner),
+            collision_rule: CollisionRule::arbitrary_field(runner),
+            color: Color::from_legacy_code(sample(runner, proptest::char::range('0', 'g'))),
+            prefix: Component::arbitrary_field(runner),
+            suffix: Component::arbitrary_field(runner),
+        }
+    }
+}
+
+/// Player names or entity uuids on a team.
+pub type TeamEntities = Prefixed<VarInt, Vec<ScoreboardName>>;
+
+/// What a `Teams` packet does, sent as a byte mode.
+///
+/// The layout is shared by every supported protocol, only the components switch from JSON to
+/// NBT in 1.20.3, so the same action is sent to clients of any version.
+#[derive(Debug, Clone)]
+pub enum TeamAction {
+    Create { info: Box<TeamInfo>, entities: TeamEntities },
+    Remove,
+    UpdateInfo(Box<TeamInfo>),
+    AddEntities(TeamEntities),
+    RemoveEntities(TeamEntities),
+}
+
+impl TeamAction {
+    fn mode(&self) -> i8 {
+        match self {
+            Self::Create { .. } => 0,
+            Self::Remove => 1,
+            Self::UpdateInfo(_) => 2,
+            Self::AddEntities(_) => 3,
+            Self::RemoveEntities(_) => 4,
+        }
+    }
+}
+
+impl MapDecodable for TeamAction {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(match i8::decode(reader)? {
+            0 => Self::Create {
+                info: Box::new(TeamInfo::read(protocol, reader)?),
+                entities: Prefixed::decode(reader)?,
+            },
+            1 => Self::Remove,
+            2 => Self::UpdateInfo(Box::new(TeamInfo::read(protocol, reader)?)),
+            3 => Self::AddEntities(Prefixed::decode(reader)?),
+            4 => Self::RemoveEntities(Prefixed::decode(reader)?),
+            mode => anyhow::bail!("Unknown team mode {}.", mode),
+        })
+    }
+}
+
+impl MapEncodable for TeamAction {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.mode().encode(writer)?;
+        match self {
+            Self::Create { info, entities } => {
+                info.write(protocol, writer)?;
+                entities.encode(writer)
+            }
+            Self::Remove => Ok(()),
+            Self::UpdateInfo(info) => info.write(protocol, writer),
+            Self::AddEntities(entities) | Self::RemoveEntities(entities) => entities.encode(writer),
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        let mut bytes = Vec::new();
+        self.encode_mapped(protocol, &mut bytes)?;
+        VarInt::try_from(bytes.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for TeamAction {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        self.encode_mapped(protocol, &mut bytes)?;
+        tokio::io::AsyncWriteExt::write_all(writer, &bytes).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for TeamAction {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        match sample(runner, 0..5) {
+            0 => Self::Create {
+                info: Box::new(TeamInfo::arbitrary_field(runner)),
+                entities: Prefixed::arbitrary_field(runner),
+            },
+            1 => Self::Remove,
+            2 => Self::UpdateInfo(Box::new(TeamInfo::arbitrary_field(runner))),
+            3 => Self::AddEntities(Prefixed::arbitrary_field(runner)),
+            _ => Self::RemoveEntities(Prefixed::arbitrary_field(runner)),
+        }
+    }
+}
+
+/// Chunk sections with light, one bit each from the section below the world up.
+pub type BitSet = Prefixed<VarInt, Vec<i64>>;
+
+/// A block entity sent with its chunk, such as a sign or a chest.
+#[derive(Debug, Clone)]
+pub struct BlockEntity {
+    /// The x coordinate within the chunk in the high nibble and z in the low one.
+    pub packed_xz: u8,
+    pub y: i16,
+    pub block_entity_type: VarInt,
+    pub data: Nbt,
+}
+
+impl BlockEntity {
+    /// `x` and `z` are within the chunk, from 0 to 15.
+    pub fn new(x: u8, y: i16, z: u8, block_entity_type: VarInt, data: Nbt) -> Self {
+        Self {
+            packed_xz: (x & 0x0F) << 4 | (z & 0x0F),
+            y,
+            block_entity_type,
+            data,
+        }
+    }
+
+    pub fn x(&self) -> u8 {
+        self.packed_xz >> 4
+    }
+
+    pub fn z(&self) -> u8 {
+        self.packed_xz & 0x0F
+    }
+}
+
+impl MapDecodable for BlockEntity {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            packed_xz: u8::decode(reader)?,
+            y: i16::decode(reader)?,
+            block_entity_type: VarInt::decode(reader)?,
+            data: Nbt::decode_mapped(protocol, reader)?,
+        })
+    }
+}
+
+impl MapEncodable for BlockEntity {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.packed_xz.encode(writer)?;
+        self.y.encode(writer)?;
+        self.block_entity_type.encode(writer)?;
+        self.data.encode_mapped(protocol, writer)
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        Ok(VarInt::from(3) + self.block_entity_type.size()? + self.data.size_mapped(protocol)?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for BlockEntity {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.packed_xz.async_encode(writer).await?;
+        self.y.async_encode(writer).await?;
+        self.block_entity_type.async_encode(writer).await?;
+        self.data.encode_mapped_async(protocol, writer).await
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for BlockEntity {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            packed_xz: u8::arbitrary_field(runner),
+            y: i16::arbitrary_field(runner),
+            block_entity_type: VarInt::arbitrary_field(runner),
+            data: Nbt::arbitrary_field(runner),
+        }
+    }
+}
+
+/// One block of a `MultiBlockChange`, with coordinates within the section.
+///
+/// Sent as a VarLong of the block state above 12 bits of x, z and y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockChange {
+    pub block_state: u32,
+    pub x: u8,
+    pub y: u8,
+    pub z: u8,
+}
+
+impl BlockChange {
+    fn pack(&self) -> i64 {
+        i64::from(self.block_state) << 12
+            | i64::from(self.x & 0x0F) << 8
+            | i64::from(self.z & 0x0F) << 4
+            | i64::from(self.y & 0x0F)
+    }
+}
+
+impl Decodable for BlockChange {
+    fn decode<R: std::io::Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let packed = read_var_long(reader)?;
+        Ok(Self {
+            block_state: u32::try_from(packed >> 12)?,
+            x: (packed >> 8 & 0x0F) as u8,
+            y: (packed & 0x0F) as u8,
+            z: (packed >> 4 & 0x0F) as u8,
+        })
+    }
+}
+
+impl Encodable for BlockChange {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(&var_long_bytes(self.pack()))?;
+        Ok(())
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        VarInt::try_from(var_long_bytes(self.pack()).len())
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncEncodable for BlockChange {
+    async fn async_encode<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        tokio::io::AsyncWriteExt::write_all(writer, &var_long_bytes(self.pack())).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for BlockChange {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        Self {
+            block_state: sample(runner, 0..1u32 << 20),
+            x: sample(runner, 0..16u8),
+            y: sample(runner, 0..16u8),
+            z: sample(runner, 0..16u8),
+        }
+    }
+}
+
+/// The extra value of `SpawnEntity` whose meaning depends on the entity type, such as an item
+/// frame's facing. An `i32` before 1.19 and a VarInt after.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpawnData(pub i32);
+
+impl SpawnData {
+    fn uses_var_int(protocol: MCProtocol) -> bool {
+        protocol >= MCProtocol::V1_19
+    }
+}
+
+impl MapDecodable for SpawnData {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if Self::uses_var_int(protocol) {
+            Ok(Self(*VarInt::decode(reader)?))
+        } else {
+            Ok(Self(i32::decode(reader)?))
+        }
+    }
+}
+
+impl MapEncodable for SpawnData {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        if Self::uses_var_int(protocol) {
+            VarInt::from(self.0).encode(writer)
+        } else {
+            self.0.encode(writer)
+        }
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        if Self::uses_var_int(protocol) {
+            VarInt::from(self.0).size()
+        } else {
+            Ok(VarInt::from(4))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for SpawnData {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        if Self::uses_var_int(protocol) {
+            VarInt::from(self.0).async_encode(writer).await
+        } else {
+            self.0.async_encode(writer).await
+        }
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for SpawnData {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self(i32::arbitrary_field(runner))
+    }
+}
+
+strict_flags! {
+    @internal
+    /// Coordinates of a teleport that are offsets from the current position rather than absolute.
+    RelativeFlags; i8; Reject {
+        0x01 => X;
+        0x02 => Y;
+        0x04 => Z;
+        0x08 => YAW;
+        0x10 => PITCH;
+    }
+}
+
+/// A slot a `ClickWindow` changed, with the item the client predicts it now holds.
+#[derive(Debug, Clone)]
+pub struct ChangedSlot {
+    pub slot: i16,
+    pub item: Slot,
+}
+
+impl MapDecodable for ChangedSlot {
+    fn decode_mapped<R: std::io::Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            slot: i16::decode(reader)?,
+            item: Slot::decode_mapped(protocol, reader)?,
+        })
+    }
+}
+
+impl MapEncodable for ChangedSlot {
+    fn encode_mapped<W: std::io::Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        self.slot.encode(writer)?;
+        self.item.encode_mapped(protocol, writer)
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        Ok(VarInt::from(2) + self.item.size_mapped(protocol)?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for ChangedSlot {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        self.slot.async_encode(writer).await?;
+        self.item.encode_mapped_async(protocol, writer).await
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for ChangedSlot {
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        Self {
+            slot: i16::arbitrary_field(runner),
+            item: Slot::arbitrary_field(runner),
+        }
+    }
+}
+
 pub mod client_bound;
 pub mod server_bound;
@@ -1,16 +1,224 @@
-use crate::create_registry;
+use crate::fields::{Prefixed, PrefixedOption, RemainingBytes};
+use crate::registry;
+use crate::registry::CookiePayload;
+use crate::Packet;
 use minecraft_data_types::common::Identifier;
-use crate::protocol_version::MCProtocol;
-
-create_registry! {
-    PluginMessage {
-        channel: Identifier,
-        data: Vec<u8>,
-        |LocalProtocol => (MCProtocol::V1_17_1) + (MCProtocol::V1_18) => (0x0A);
-        |Protocol {
-            (_) => (0x0A) {
-                (anyhow::bail!("Unsupported version."))
-            }
-        }
-    }
+use minecraft_data_types::nums::VarInt;
+
+/// Acknowledges `message_count` messages without sending one, so the server can trim what it tracks.
+#[derive(Debug, Packet)]
+#[packet(id = 0x03, versions = "1.20.2..")]
+pub struct MessageAcknowledgment {
+    pub message_count: VarInt,
+}
+
+/// Starts a chat session, whose key signs every following chat message and command.
+#[derive(Debug, Packet)]
+#[packet(id = 0x06, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x07, versions = "1.20.5..")]
+pub struct PlayerSession {
+    pub session_id: uuid::Uuid,
+    pub public_key: crate::registry::PlayerPublicKey,
+}
+
+/// A chat message, which also carries commands before 1.19.
+///
+/// Messages are signed from 1.19, and from 1.19.1 acknowledge the last messages the client saw.
+#[derive(Debug, Packet)]
+#[packet(id = 0x03, versions = "1.17.1..=1.18")]
+#[packet(id = 0x04, versions = "1.19")]
+#[packet(id = 0x05, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x06, versions = "1.20.5..")]
+pub struct ChatMessage {
+    pub message: super::ChatString,
+    #[field(since = "1.19")]
+    pub timestamp: i64,
+    #[field(since = "1.19")]
+    pub salt: i64,
+    #[field(versions = "1.19")]
+    pub legacy_signature: Prefixed<VarInt, Vec<u8>>,
+    #[field(versions = "1.19")]
+    pub signed_preview: bool,
+    #[field(since = "1.20.2")]
+    pub signature: PrefixedOption<super::MessageSignature>,
+    #[field(since = "1.20.2", default = "VarInt::from(0)")]
+    pub message_count: VarInt,
+    #[field(since = "1.20.2")]
+    pub acknowledged: super::AcknowledgedMessages,
+}
+
+/// A command without the leading `/`, with signatures for its message arguments until 1.20.5.
+#[derive(Debug, Packet)]
+#[packet(id = 0x03, versions = "1.19")]
+#[packet(id = 0x04, versions = "1.20.2..")]
+pub struct ChatCommand {
+    pub command: super::ChatString,
+    #[field(versions = "1.19..1.20.5")]
+    pub timestamp: i64,
+    #[field(versions = "1.19..1.20.5")]
+    pub salt: i64,
+    #[field(versions = "1.19")]
+    pub legacy_argument_signatures: Prefixed<VarInt, Vec<super::LegacyArgumentSignature>>,
+    #[field(versions = "1.19")]
+    pub signed_preview: bool,
+    #[field(versions = "1.20.2..1.20.5")]
+    pub argument_signatures: Prefixed<VarInt, Vec<super::ArgumentSignature>>,
+    #[field(versions = "1.20.2..1.20.5", default = "VarInt::from(0)")]
+    pub message_count: VarInt,
+    #[field(versions = "1.20.2..1.20.5")]
+    pub acknowledged: super::AcknowledgedMessages,
+}
+
+/// A command with signed arguments, split from `ChatCommand` in 1.20.5.
+#[derive(Debug, Packet)]
+#[packet(id = 0x05, versions = "1.20.5..")]
+pub struct SignedChatCommand {
+    pub command: super::ChatString,
+    pub timestamp: i64,
+    pub salt: i64,
+    pub argument_signatures: Prefixed<VarInt, Vec<super::ArgumentSignature>>,
+    pub message_count: VarInt,
+    pub acknowledged: super::AcknowledgedMessages,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0A, versions = "1.17.1..=1.18")]
+pub struct PluginMessage {
+    pub channel: Identifier,
+    pub data: RemainingBytes<32767>,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x0F, versions = "1.17.1..=1.18")]
+#[packet(id = 0x11, versions = "1.19")]
+#[packet(id = 0x14, versions = "1.20.2")]
+#[packet(id = 0x15, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x18, versions = "1.20.5..")]
+pub struct KeepAlive {
+    pub keep_alive_id: i64,
+}
+
+/// Confirms a client-bound `PlayerPositionAndLook` once the client has moved.
+#[derive(Debug, Packet)]
+#[packet(id = 0x00, versions = "1.17.1..")]
+pub struct TeleportConfirm {
+    pub teleport_id: VarInt,
+}
+
+/// `y` is the player's feet.
+#[derive(Debug, Packet)]
+#[packet(id = 0x11, versions = "1.17.1..=1.18")]
+#[packet(id = 0x13, versions = "1.19")]
+#[packet(id = 0x16, versions = "1.20.2")]
+#[packet(id = 0x17, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x1A, versions = "1.20.5..")]
+pub struct PlayerPosition {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x12, versions = "1.17.1..=1.18")]
+#[packet(id = 0x14, versions = "1.19")]
+#[packet(id = 0x17, versions = "1.20.2")]
+#[packet(id = 0x18, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x1B, versions = "1.20.5..")]
+pub struct PlayerPositionAndRotation {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+/// Angles in degrees.
+#[derive(Debug, Packet)]
+#[packet(id = 0x13, versions = "1.17.1..=1.18")]
+#[packet(id = 0x15, versions = "1.19")]
+#[packet(id = 0x18, versions = "1.20.2")]
+#[packet(id = 0x19, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x1C, versions = "1.20.5..")]
+pub struct PlayerRotation {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+/// A click in a window, `mode` and `button` together tell what kind of click it was.
+///
+/// The client sends what it expects the slots it changed to hold, the server corrects it with
+/// `WindowItems` if it disagrees.
+#[derive(Debug, Packet)]
+#[packet(id = 0x08, versions = "1.17.1..=1.18")]
+#[packet(id = 0x0A, versions = "1.19")]
+#[packet(id = 0x0D, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x0E, versions = "1.20.5..")]
+pub struct ClickWindow {
+    pub window_id: u8,
+    pub state_id: VarInt,
+    pub slot: i16,
+    pub button: i8,
+    pub mode: VarInt,
+    #[field(mapped)]
+    pub changed_slots: Prefixed<VarInt, Vec<super::ChangedSlot>>,
+    #[field(mapped)]
+    pub carried_item: crate::slot::Slot,
+}
+
+/// Sent when the player closes a window, including their own inventory as window 0.
+#[derive(Debug, Packet)]
+#[packet(id = 0x09, versions = "1.17.1..=1.18")]
+#[packet(id = 0x0B, versions = "1.19")]
+#[packet(id = 0x0E, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x0F, versions = "1.20.5..")]
+pub struct CloseWindow {
+    pub window_id: u8,
+}
+
+/// Sets a slot of the player's inventory in creative mode, an empty slot deletes the item.
+#[derive(Debug, Packet)]
+#[packet(id = 0x28, versions = "1.17.1..=1.18")]
+#[packet(id = 0x2A, versions = "1.19")]
+#[packet(id = 0x2E, versions = "1.20.2")]
+#[packet(id = 0x2F, versions = "1.20.3..1.20.5")]
+#[packet(id = 0x32, versions = "1.20.5..")]
+pub struct SetCreativeSlot {
+    pub slot: i16,
+    #[field(mapped)]
+    pub clicked_item: crate::slot::Slot,
+}
+
+/// Acknowledges `StartConfiguration`, the connection is in configuration once this is sent.
+#[derive(Debug, Packet)]
+#[packet(id = 0x0B, versions = "1.20.2..1.20.5")]
+#[packet(id = 0x0C, versions = "1.20.5..")]
+pub struct AcknowledgeConfiguration {}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x11, versions = "1.20.5..")]
+pub struct CookieResponse {
+    pub key: Identifier,
+    pub payload: PrefixedOption<CookiePayload>,
+}
+
+registry! {
+    TeleportConfirm,
+    MessageAcknowledgment,
+    ChatMessage,
+    ChatCommand,
+    SignedChatCommand,
+    PlayerSession,
+    PluginMessage,
+    KeepAlive,
+    PlayerPosition,
+    PlayerPositionAndRotation,
+    PlayerRotation,
+    ClickWindow,
+    CloseWindow,
+    SetCreativeSlot,
+    AcknowledgeConfiguration,
+    CookieResponse,
 }
@@ -0,0 +1,331 @@
+use crate::packet::{EncodedPacket, IdentifiedPacket, WritablePacket};
+use crate::protocol_version::{MCProtocol, MapDecodable};
+use minecraft_data_types::common::{Chat, Identifier};
+use minecraft_data_types::{encoder::*, nums::VarInt};
+use proptest::prelude::*;
+use proptest::strategy::{NewTree, ValueTree};
+use proptest::test_runner::{Config, TestRunner};
+use std::any::Any;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::marker::PhantomData;
+
+const CASES: u32 = 64;
+
+/// Produces arbitrary values for packet fields, implemented for every type used in a registry.
+///
+/// This is our own trait rather than `Arbitrary` so it can be implemented for the field types
+/// of `minecraft_data_types` and `commander`. Values are drawn through [`sample`], and
+/// [`arbitrary`] turns an implementation into a `Strategy` which shrinks those draws.
+pub trait ArbitraryField: Sized {
+    fn arbitrary_field(runner: &mut TestRunner) -> Self;
+}
+
+/// A value tree with its value type erased, so the draws of one generation can be kept together.
+trait ErasedTree {
+    fn current(&self) -> Box<dyn Any>;
+    fn simplify(&mut self) -> bool;
+    fn complicate(&mut self) -> bool;
+}
+
+impl<T: ValueTree> ErasedTree for T
+where
+    T::Value: 'static,
+{
+    fn current(&self) -> Box<dyn Any> {
+        Box::new(ValueTree::current(self))
+    }
+
+    fn simplify(&mut self) -> bool {
+        ValueTree::simplify(self)
+    }
+
+    fn complicate(&mut self) -> bool {
+        ValueTree::complicate(self)
+    }
+}
+
+enum Tape {
+    Recording(Vec<Box<dyn ErasedTree>>),
+    Replaying(std::vec::IntoIter<Box<dyn Any>>),
+}
+
+thread_local! {
+    static TAPE: RefCell<Option<Tape>> = const { RefCell::new(None) };
+}
+
+/// Runs `generate` with the tape in place, clearing it again even if generation panics.
+fn with_tape<T>(tape: Tape, generate: impl FnOnce() -> T) -> (T, Option<Tape>) {
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            TAPE.with(|current| current.borrow_mut().take());
+        }
+    }
+
+    TAPE.with(|current| *current.borrow_mut() = Some(tape));
+    let reset = Reset;
+    let value = generate();
+    let tape = TAPE.with(|current| current.borrow_mut().take());
+    drop(reset);
+    (value, tape)
+}
+
+/// Draws a value from `strategy`.
+///
+/// While [`arbitrary`] generates a value the draw is recorded so it can be shrunk, and while it
+/// replays a shrunk value the recorded draw is returned instead. Draws past the end of the
+/// recording, when shrinking changed the shape of the value, come fresh from `runner`.
+pub fn sample<S: Strategy>(runner: &mut TestRunner, strategy: S) -> S::Value
+where
+    S::Tree: 'static,
+    S::Value: 'static,
+{
+    let replayed = TAPE.with(|tape| match tape.borrow_mut().as_mut() {
+        Some(Tape::Replaying(values)) => values.next().and_then(|value| value.downcast::<S::Value>().ok()),
+        _ => None,
+    });
+    if let Some(value) = replayed {
+        return *value;
+    }
+
+    let tree = strategy.new_tree(runner).expect("Failed to generate a value.");
+    let value = ValueTree::current(&tree);
+    TAPE.with(|tape| {
+        if let Some(Tape::Recording(trees)) = tape.borrow_mut().as_mut() {
+            trees.push(Box::new(tree));
+        }
+    });
+    value
+}
+
+/// A `Strategy` generating `T` through its `ArbitraryField` implementation.
+pub fn arbitrary<T: ArbitraryField + Debug>() -> Arbitrary<T> {
+    Arbitrary(PhantomData)
+}
+
+pub struct Arbitrary<T>(PhantomData<T>);
+
+impl<T> Debug for Arbitrary<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Arbitrary<{}>", std::any::type_name::<T>())
+    }
+}
+
+impl<T: ArbitraryField + Debug> Strategy for Arbitrary<T> {
+    type Tree = ArbitraryTree<T>;
+    type Value = T;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let replay_runner = TestRunner::new_with_rng(Config::default(), runner.new_rng());
+        let (_, tape) = with_tape(Tape::Recording(Vec::new()), || T::arbitrary_field(runner));
+        let trees = match tape {
+            Some(Tape::Recording(trees)) => trees,
+            _ => unreachable!("The tape is only replaced by generation."),
+        };
+        Ok(ArbitraryTree {
+            trees,
+            index: 0,
+            shrunk: None,
+            runner: replay_runner,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// Shrinks the recorded draws one at a time, regenerating the value from them.
+pub struct ArbitraryTree<T> {
+    trees: Vec<Box<dyn ErasedTree>>,
+    index: usize,
+    shrunk: Option<usize>,
+    runner: TestRunner,
+    marker: PhantomData<T>,
+}
+
+impl<T: ArbitraryField + Debug> ValueTree for ArbitraryTree<T> {
+    type Value = T;
+
+    fn current(&self) -> T {
+        let values = self.trees.iter().map(|tree| tree.current()).collect::<Vec<_>>();
+        let mut runner = self.runner.clone();
+        with_tape(Tape::Replaying(values.into_iter()), || T::arbitrary_field(&mut runner)).0
+    }
+
+    fn simplify(&mut self) -> bool {
+        while let Some(tree) = self.trees.get_mut(self.index) {
+            if tree.simplify() {
+                self.shrunk = Some(self.index);
+                return true;
+            }
+            self.index += 1;
+        }
+        false
+    }
+
+    fn complicate(&mut self) -> bool {
+        match self.shrunk {
+            Some(index) => self.trees[index].complicate(),
+            None => false,
+        }
+    }
+}
+
+/// The config of the generated round trip tests, which cover every protocol in one run.
+pub fn config() -> ProptestConfig {
+    ProptestConfig::with_cases(CASES * MCProtocol::VARIANTS.len() as u32)
+}
+
+/// A `Strategy` picking any protocol, supported by the packet or not.
+pub fn protocols() -> impl Strategy<Value = MCProtocol> {
+    proptest::sample::select(MCProtocol::VARIANTS)
+}
+
+macro_rules! arbitrary_primitive {
+    ($($primitive:ty),*) => {
+        $(
+            impl ArbitraryField for $primitive {
+                fn arbitrary_field(runner: &mut TestRunner) -> Self {
+                    sample(runner, any::<$primitive>())
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_primitive!(bool, u8, i8, u16, i16, i32, i64, u64, f32, f64);
+
+/// Implements `ArbitraryField` for a string type generated by `auto_string!`.
+macro_rules! arbitrary_string {
+    ($string_type:ty, $max_length:literal) => {
+        impl $crate::registry::round_trip::ArbitraryField for $string_type {
+            fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+                let value = $crate::registry::round_trip::sample(
+                    runner,
+                    concat!("[a-zA-Z0-9_ ]{0,", stringify!($max_length), "}"),
+                );
+                <$string_type>::from(value.as_str())
+            }
+        }
+    };
+}
+
+pub(crate) use arbitrary_string;
+
+impl ArbitraryField for VarInt {
+    fn arbitrary_field(runner: &mut TestRunner) -> Self {
+        VarInt::from(sample(runner, any::<i32>()))
+    }
+}
+
+impl ArbitraryField for Vec<u8> {
+    fn arbitrary_field(runner: &mut TestRunner) -> Self {
+        sample(runner, proptest::collection::vec(any::<u8>(), 0..256))
+    }
+}
+
+impl ArbitraryField for uuid::Uuid {
+    fn arbitrary_field(runner: &mut TestRunner) -> Self {
+        uuid::Uuid::from_u128(sample(runner, any::<u128>()))
+    }
+}
+
+impl ArbitraryField for Identifier {
+    fn arbitrary_field(runner: &mut TestRunner) -> Self {
+        Identifier::from(sample(runner, "[a-z0-9_]{1,16}:[a-z0-9_/]{1,16}").as_str())
+    }
+}
+
+impl ArbitraryField for Chat {
+    fn arbitrary_field(runner: &mut TestRunner) -> Self {
+        let text = sample(runner, "[a-zA-Z0-9 ]{0,32}");
+        Chat::from(format!(r#"{{"text":"{}"}}"#, text).as_str())
+    }
+}
+
+impl ArbitraryField for commander::protocol::Node {
+    fn arbitrary_field(_runner: &mut TestRunner) -> Self {
+        // A root node without children: flags 0, no children.
+        commander::protocol::Node::decode(&mut Cursor::new(vec![0x00, 0x00]))
+            .expect("Failed to decode root node.")
+    }
+}
+
+/// Asserts that `size_mapped`, `encode_mapped`, `decode_mapped` and `to_resolved_packet` agree
+/// for `packet` on `protocol`, and that the registry dispatches it back to itself. Packets only
+/// have to fail consistently on protocols they do not support.
+///
+/// Packets only derive `Debug`, so decoded values are compared by re-encoding them.
+pub fn assert_round_trip<T: MapDecodable + WritablePacket + Debug>(
+    protocol: MCProtocol,
+    packet: &T,
+    describe: fn(Cursor<Vec<u8>>, MCProtocol) -> anyhow::Result<Option<String>>,
+) {
+    let size = match packet.size_mapped(protocol) {
+        Ok(size) => size,
+        Err(_) => {
+            assert!(
+                packet.encode_mapped(protocol, &mut Vec::new()).is_err(),
+                "{:?} sized as unsupported but encoded for {:?}.",
+                packet,
+                protocol
+            );
+            assert!(packet.to_resolved_packet(protocol).is_err());
+            return;
+        }
+    };
+
+    let mut encoded = Vec::new();
+    packet
+        .encode_mapped(protocol, &mut encoded)
+        .expect("Failed to encode packet.");
+    assert_eq!(
+        usize::try_from(size).expect("Size should be positive."),
+        encoded.len(),
+        "size_mapped disagreed with encode_mapped for {:?} on {:?}.",
+        packet,
+        protocol
+    );
+
+    let mut cursor = Cursor::new(encoded.clone());
+    let decoded = T::decode_mapped(protocol, &mut cursor).expect("Failed to decode packet.");
+    assert_eq!(cursor.position() as usize, encoded.len(), "Trailing bytes after decoding {:?}.", decoded);
+
+    let mut re_encoded = Vec::new();
+    decoded
+        .encode_mapped(protocol, &mut re_encoded)
+        .expect("Failed to re-encode packet.");
+    assert_eq!(encoded, re_encoded, "Round trip changed {:?} on {:?}.", packet, protocol);
+
+    let raw = packet
+        .to_resolved_packet(protocol)
+        .expect("Failed to resolve packet.")
+        .raw_bytes()
+        .expect("Failed to read resolved packet.");
+    let mut cursor = Cursor::new(raw.clone());
+    VarInt::decode(&mut cursor).expect("Failed to decode packet id.");
+    assert_eq!(&raw[cursor.position() as usize..], encoded.as_slice());
+
+    let described = describe(Cursor::new(raw), protocol).expect("Registry failed to decode packet.");
+    assert_eq!(described, Some(format!("{:?}", decoded)));
+}
+
+/// Asserts that at least one protocol supports the packet.
+pub fn assert_supported<T: IdentifiedPacket>() {
+    assert!(
+        MCProtocol::VARIANTS.iter().any(|protocol| T::packet_id(*protocol).is_some()),
+        "No protocol supports {}.",
+        std::any::type_name::<T>()
+    );
+}
+
+/// Decodes the body of an encoded packet, asserting nothing trails it.
+pub fn decode_packet<T: MapDecodable>(packet: &EncodedPacket, protocol: MCProtocol) -> T {
+    let mut bytes = Cursor::new(packet.raw_bytes().expect("Failed to read encoded packet."));
+    VarInt::decode(&mut bytes).expect("Failed to decode packet id.");
+    let decoded = T::decode_mapped(protocol, &mut bytes).expect("Failed to decode packet.");
+    assert_eq!(bytes.position() as usize, bytes.get_ref().len(), "Trailing bytes after decoding.");
+    decoded
+}
@@ -1,15 +1,24 @@
-use crate::create_registry;
+use crate::registry;
+use crate::Packet;
 use minecraft_data_types::auto_string;
 
 auto_string!(JSONResponse, 32767);
+#[cfg(test)]
+crate::registry::round_trip::arbitrary_string!(JSONResponse, 255);
 
-create_registry! {
-    StatusResponse {
-        json_response: JSONResponse,
-        |LocalProtocol => (_) => (0x00);
-    }
-    Pong {
-        payload: i64,
-        |LocalProtocol => (_) => (0x01);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x00)]
+pub struct StatusResponse {
+    pub json_response: JSONResponse,
+}
+
+#[derive(Debug, Packet)]
+#[packet(id = 0x01)]
+pub struct Pong {
+    pub payload: i64,
+}
+
+registry! {
+    StatusResponse,
+    Pong,
 }
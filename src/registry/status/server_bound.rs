@@ -1,12 +1,17 @@
-use crate::create_registry;
+use crate::registry;
+use crate::Packet;
 
-create_registry! {
-    StatusRequest {
-        |LocalProtocol => (_) => (0x00);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x00)]
+pub struct StatusRequest {}
 
-    Ping {
-        payload: i64,
-        |LocalProtocol => (_) => (0x01);
-    }
+#[derive(Debug, Packet)]
+#[packet(id = 0x01)]
+pub struct Ping {
+    pub payload: i64,
+}
+
+registry! {
+    StatusRequest,
+    Ping,
 }
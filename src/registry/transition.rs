@@ -0,0 +1,64 @@
+use crate::capture::{ConnectionState, Direction};
+use crate::packet::IdentifiedPacket;
+use crate::protocol_version::{MCProtocol, MapDecodable};
+use crate::registry::{configuration, handshake, login, play};
+use minecraft_data_types::{encoder::Decodable, nums::VarInt};
+use std::io::Cursor;
+
+impl ConnectionState {
+    /// Returns the state of the connection after `packet`, starting with its id, was sent in
+    /// `direction`.
+    ///
+    /// From 1.20.2 login moves to configuration once the client acknowledges `LoginSuccess`, and
+    /// play moves back to configuration once it acknowledges `StartConfiguration`. Both sides
+    /// switch when the acknowledgement is sent, so client-bound packets never change the state
+    /// except for `LoginSuccess` on older protocols.
+    pub fn next_state(
+        self,
+        direction: Direction,
+        protocol: MCProtocol,
+        packet: &[u8],
+    ) -> anyhow::Result<ConnectionState> {
+        let mut cursor = Cursor::new(packet);
+        let packet_id = VarInt::decode(&mut cursor)?;
+        let is = |id: Option<VarInt>| id.is_some_and(|id| *id == *packet_id);
+
+        Ok(match (self, direction) {
+            (ConnectionState::Handshake, Direction::ServerBound) => {
+                if !is(handshake::server_bound::Handshake::packet_id(protocol)) {
+                    anyhow::bail!("Expected a handshake, received packet {}.", packet_id);
+                }
+                let handshake = handshake::server_bound::Handshake::decode_mapped(protocol, &mut cursor)?;
+                match handshake.next_state {
+                    handshake::server_bound::NextState::Status => ConnectionState::Status,
+                    handshake::server_bound::NextState::Login
+                    | handshake::server_bound::NextState::Transfer => ConnectionState::Login,
+                    handshake::server_bound::NextState::Unknown(index) => {
+                        anyhow::bail!("Unknown next state {} in handshake.", index)
+                    }
+                }
+            }
+            (ConnectionState::Login, Direction::ClientBound)
+                if protocol < MCProtocol::V1_20_2 && is(login::client_bound::LoginSuccess::packet_id(protocol)) =>
+            {
+                ConnectionState::Play
+            }
+            (ConnectionState::Login, Direction::ServerBound)
+                if is(login::server_bound::LoginAcknowledged::packet_id(protocol)) =>
+            {
+                ConnectionState::Configuration
+            }
+            (ConnectionState::Configuration, Direction::ServerBound)
+                if is(configuration::server_bound::AcknowledgeFinishConfiguration::packet_id(protocol)) =>
+            {
+                ConnectionState::Play
+            }
+            (ConnectionState::Play, Direction::ServerBound)
+                if is(play::server_bound::AcknowledgeConfiguration::packet_id(protocol)) =>
+            {
+                ConnectionState::Configuration
+            }
+            (state, _) => state,
+        })
+    }
+}
@@ -0,0 +1,242 @@
+use crate::chat::Component;
+use crate::fields::PrefixedOption;
+use crate::packet::EncodedPacket;
+use crate::protocol_version::MCProtocol;
+use crate::registry::play::client_bound::{DisplayObjective, ResetScore, ScoreboardObjective, UpdateScore};
+use crate::registry::play::{
+    DisplaySlot, NumberFormat, ObjectiveAction, ObjectiveDisplay, ObjectiveType, ScoreUpdate, ScoreboardName,
+};
+
+/// The most lines the client shows in the sidebar.
+pub const MAX_LINES: usize = 15;
+
+/// Lines shown as the display name of a fixed score holder from 1.20.3.
+fn uses_display_names(protocol: MCProtocol) -> bool {
+    protocol >= MCProtocol::V1_20_3
+}
+
+/// Before 1.20.3 the line is the holder's name, with a hidden color code keeping it unique.
+fn holder(index: usize, line: &Component, protocol: MCProtocol) -> ScoreboardName {
+    if uses_display_names(protocol) {
+        ScoreboardName::from(format!("§{:x}", index))
+    } else {
+        ScoreboardName::from(format!("{}§{:x}", line.to_legacy(), index))
+    }
+}
+
+/// Lines are ordered by descending score, so the top line has the highest.
+fn score(index: usize) -> i32 {
+    (MAX_LINES - index) as i32
+}
+
+/// A line that changed, `None` where it did not exist before or was removed.
+#[derive(Debug, Clone, PartialEq)]
+struct LineChange {
+    index: usize,
+    previous: Option<Component>,
+    line: Option<Component>,
+}
+
+/// The changes between two states of a [`Sidebar`], serialized for each client's protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SidebarUpdate {
+    objective_name: String,
+    title: Option<Component>,
+    lines: Vec<LineChange>,
+}
+
+impl SidebarUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.lines.is_empty()
+    }
+
+    /// The fewest packets applying the changes, before 1.20.3 a changed line is removed and added
+    /// again as it is the holder's name.
+    pub fn packets(&self, protocol: MCProtocol) -> anyhow::Result<Vec<EncodedPacket>> {
+        let mut packets = Vec::new();
+        if let Some(title) = &self.title {
+            let update = ScoreboardObjective {
+                objective_name: ScoreboardName::from(self.objective_name.as_str()),
+                action: ObjectiveAction::Update(objective_display(title)),
+            };
+            packets.push(EncodedPacket::from_writable(&update, protocol)?);
+        }
+        for change in &self.lines {
+            match (&change.previous, &change.line) {
+                (Some(previous), None) => packets.push(self.remove_line(change.index, previous, protocol)?),
+                (previous, Some(line)) => {
+                    if let (Some(previous), false) = (previous, uses_display_names(protocol)) {
+                        packets.push(self.remove_line(change.index, previous, protocol)?);
+                    }
+                    packets.push(set_line(&self.objective_name, change.index, line, protocol)?);
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(packets)
+    }
+
+    fn remove_line(&self, index: usize, previous: &Component, protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+        let entity_name = holder(index, previous, protocol);
+        let objective_name = ScoreboardName::from(self.objective_name.as_str());
+        if uses_display_names(protocol) {
+            let reset = ResetScore {
+                entity_name,
+                objective_name: PrefixedOption(Some(objective_name)),
+            };
+            return EncodedPacket::from_writable(&reset, protocol);
+        }
+        let update = UpdateScore {
+            entity_name,
+            update: ScoreUpdate::remove(&self.objective_name),
+        };
+        EncodedPacket::from_writable(&update, protocol)
+    }
+}
+
+fn objective_display(title: &Component) -> ObjectiveDisplay {
+    ObjectiveDisplay {
+        display_name: title.clone(),
+        render_type: ObjectiveType::Integer,
+        number_format: Some(NumberFormat::Blank),
+    }
+}
+
+fn set_line(objective_name: &str, index: usize, line: &Component, protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+    let mut update = ScoreUpdate::set(objective_name, score(index));
+    if uses_display_names(protocol) {
+        update.display_name = Some(line.clone());
+    }
+    let update = UpdateScore {
+        entity_name: holder(index, line, protocol),
+        update,
+    };
+    EncodedPacket::from_writable(&update, protocol)
+}
+
+/// A sidebar built from lines of text, hiding the scores behind them.
+///
+/// Scores are hidden from 1.20.3, older clients show them counting down from [`MAX_LINES`].
+/// Before 1.20.3 each line is sent as a score holder name, which 1.17.1 limits to 40 characters.
+#[derive(Debug, Clone)]
+pub struct Sidebar {
+    objective_name: String,
+    title: Component,
+    lines: Vec<Component>,
+}
+
+impl Sidebar {
+    pub fn new(objective_name: &str, title: Component) -> Self {
+        Self {
+            objective_name: objective_name.to_string(),
+            title,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn title(&self) -> &Component {
+        &self.title
+    }
+
+    pub fn lines(&self) -> &[Component] {
+        &self.lines
+    }
+
+    /// Creates the objective with every line and shows it in the sidebar slot.
+    pub fn show(&self, protocol: MCProtocol) -> anyhow::Result<Vec<EncodedPacket>> {
+        let objective_name = || ScoreboardName::from(self.objective_name.as_str());
+        let create = ScoreboardObjective {
+            objective_name: objective_name(),
+            action: ObjectiveAction::Create(objective_display(&self.title)),
+        };
+        let display = DisplayObjective {
+            position: DisplaySlot::Sidebar,
+            objective_name: objective_name(),
+        };
+        let mut packets = vec![
+            EncodedPacket::from_writable(&create, protocol)?,
+            EncodedPacket::from_writable(&display, protocol)?,
+        ];
+        for (index, line) in self.lines.iter().enumerate() {
+            packets.push(set_line(&self.objective_name, index, line, protocol)?);
+        }
+        Ok(packets)
+    }
+
+    /// Removes the objective, which also clears it from the sidebar slot.
+    pub fn hide(&self, protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+        let remove = ScoreboardObjective {
+            objective_name: ScoreboardName::from(self.objective_name.as_str()),
+            action: ObjectiveAction::Remove,
+        };
+        EncodedPacket::from_writable(&remove, protocol)
+    }
+
+    /// Replaces the title and lines, returning only what changed.
+    pub fn update(&mut self, title: Component, lines: Vec<Component>) -> anyhow::Result<SidebarUpdate> {
+        if lines.len() > MAX_LINES {
+            anyhow::bail!("A sidebar shows at most {} lines, found {}.", MAX_LINES, lines.len());
+        }
+        let mut changes = Vec::new();
+        for index in 0..lines.len().max(self.lines.len()) {
+            let (previous, line) = (self.lines.get(index), lines.get(index));
+            if previous != line {
+                changes.push(LineChange {
+                    index,
+                    previous: previous.cloned(),
+                    line: line.cloned(),
+                });
+            }
+        }
+        let update = SidebarUpdate {
+            objective_name: self.objective_name.clone(),
+            title: (title != self.title).then(|| title.clone()),
+            lines: changes,
+        };
+        self.title = title;
+        self.lines = lines;
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::round_trip::decode_packet as decode;
+    use minecraft_data_types::nums::VarInt;
+
+    #[test]
+    fn updates_only_send_changed_lines() {
+        let mut sidebar = Sidebar::new("game", Component::text("Bed Wars"));
+        let lines = ["Red: 4", "Blue: 3", "Kills: 0"].iter().map(|line| Component::text(*line)).collect();
+        sidebar.update(Component::text("Bed Wars"), lines).unwrap();
+        assert_eq!(sidebar.show(MCProtocol::V1_20_3).unwrap().len(), 5);
+
+        let lines = ["Red: 4", "Blue: 2"].iter().map(|line| Component::text(*line)).collect();
+        let update = sidebar.update(Component::text("Bed Wars"), lines).unwrap();
+
+        let protocol = MCProtocol::V1_20_3;
+        let packets = update.packets(protocol).unwrap();
+        assert_eq!(packets.len(), 2);
+        let set = decode::<UpdateScore>(&packets[0], protocol);
+        assert_eq!(set.entity_name.to_string(), "§1");
+        assert_eq!(set.update.value, Some(VarInt::from(14)));
+        assert_eq!(set.update.display_name, Some(Component::text("Blue: 2")));
+        let reset = decode::<ResetScore>(&packets[1], protocol);
+        assert_eq!(reset.entity_name.to_string(), "§2");
+
+        // Older clients see the line as the holder's name, so a changed line is replaced.
+        let protocol = MCProtocol::V1_18;
+        let packets = update.packets(protocol).unwrap();
+        assert_eq!(packets.len(), 3);
+        let remove = decode::<UpdateScore>(&packets[0], protocol);
+        assert_eq!(remove.entity_name.to_string(), "Blue: 3§1");
+        assert_eq!(remove.update.value, None);
+        let set = decode::<UpdateScore>(&packets[1], protocol);
+        assert_eq!(set.entity_name.to_string(), "Blue: 2§1");
+
+        let update = sidebar.update(Component::text("Bed Wars"), sidebar.lines().to_vec()).unwrap();
+        assert!(update.is_empty());
+        assert!(sidebar.update(Component::text(""), vec![Component::text(""); 16]).is_err());
+    }
+}
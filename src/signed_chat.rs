@@ -0,0 +1,394 @@
+use crate::chat::Component;
+use crate::fields::FixedArray;
+use crate::registry::play::{AcknowledgedMessages, MessageSignature};
+use crate::registry::PlayerPublicKey;
+use minecraft_data_types::nums::VarInt;
+use rsa::pkcs8::FromPublicKey;
+use rsa::{Hash, PaddingScheme, PublicKey, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many previously seen messages each signed message acknowledges.
+pub const LAST_SEEN_COUNT: usize = 20;
+
+/// The signed parts of a chat message, as sent from 1.19.3.
+pub struct SignedMessageBody<'a> {
+    pub message: &'a str,
+    /// Milliseconds since the epoch, only whole seconds are signed.
+    pub timestamp: i64,
+    pub salt: i64,
+    /// The signatures acknowledged alongside the message, oldest first.
+    pub last_seen: &'a [MessageSignature],
+}
+
+impl SignedMessageBody<'_> {
+    fn update(&self, hasher: &mut Sha256) {
+        hasher.update(self.salt.to_be_bytes());
+        hasher.update(self.timestamp.div_euclid(1000).to_be_bytes());
+        hasher.update((self.message.len() as i32).to_be_bytes());
+        hasher.update(self.message.as_bytes());
+        hasher.update((self.last_seen.len() as i32).to_be_bytes());
+        for signature in self.last_seen {
+            hasher.update(signature.0);
+        }
+    }
+}
+
+/// The SHA-256 digest a message signature covers, linking it to its sender, session and index.
+pub fn message_digest(sender: uuid::Uuid, session_id: uuid::Uuid, index: i32, body: &SignedMessageBody) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(1i32.to_be_bytes());
+    hasher.update(sender.as_bytes());
+    hasher.update(session_id.as_bytes());
+    hasher.update(index.to_be_bytes());
+    body.update(&mut hasher);
+    hasher.finalize().to_vec()
+}
+
+/// The signed parts of a 1.19 chat message, which signs its content as a JSON text component
+/// rather than linking it to a session and the messages before it.
+pub struct LegacySignedMessageBody<'a> {
+    pub content: &'a Component,
+    /// Milliseconds since the epoch, only whole seconds are signed.
+    pub timestamp: i64,
+    pub salt: i64,
+}
+
+/// The bytes a 1.19 message signature covers: the salt, sender, timestamp and JSON content.
+fn legacy_signed_data(sender: uuid::Uuid, body: &LegacySignedMessageBody) -> Vec<u8> {
+    let content = body.content.to_json_string();
+    let mut data = Vec::with_capacity(32 + content.len());
+    data.extend_from_slice(&body.salt.to_be_bytes());
+    data.extend_from_slice(sender.as_bytes());
+    data.extend_from_slice(&body.timestamp.div_euclid(1000).to_be_bytes());
+    data.extend_from_slice(content.as_bytes());
+    data
+}
+
+/// The SHA-256 digest a 1.19 message signature covers.
+pub fn legacy_message_digest(sender: uuid::Uuid, body: &LegacySignedMessageBody) -> Vec<u8> {
+    Sha256::digest(&legacy_signed_data(sender, body)).to_vec()
+}
+
+fn padding() -> PaddingScheme {
+    PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256))
+}
+
+/// Signs a message as a client would with its 2048 bit profile key.
+pub fn sign_message(
+    private_key: &RsaPrivateKey,
+    sender: uuid::Uuid,
+    session_id: uuid::Uuid,
+    index: i32,
+    body: &SignedMessageBody,
+) -> anyhow::Result<MessageSignature> {
+    let signature = private_key.sign(padding(), &message_digest(sender, session_id, index, body))?;
+    let length = signature.len();
+    let signature: [u8; 256] = signature
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Expected a 256 byte signature, found {} bytes.", length))?;
+    Ok(FixedArray(signature))
+}
+
+/// Signs a message as a 1.19 client would, these signatures are not a fixed length.
+pub fn sign_legacy_message(
+    private_key: &RsaPrivateKey,
+    sender: uuid::Uuid,
+    body: &LegacySignedMessageBody,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(private_key.sign(padding(), &legacy_message_digest(sender, body))?)
+}
+
+/// Verifies the chain of messages signed in one chat session.
+///
+/// Each message must have a higher index and a later timestamp than the one before it. 1.19
+/// messages have no session or index and are checked with [`MessageVerifier::verify_legacy`].
+pub struct MessageVerifier {
+    sender: uuid::Uuid,
+    session_id: uuid::Uuid,
+    expires_at: i64,
+    public_key: RsaPublicKey,
+    last_index: Option<i32>,
+    last_timestamp: i64,
+}
+
+impl MessageVerifier {
+    pub fn new(sender: uuid::Uuid, session_id: uuid::Uuid, public_key: &PlayerPublicKey) -> anyhow::Result<Self> {
+        let key = RsaPublicKey::from_public_key_der(&public_key.key)
+            .map_err(|error| anyhow::anyhow!("Invalid chat session key for {}: {}", sender, error))?;
+        Ok(Self::from_key(sender, session_id, public_key.expires_at, key))
+    }
+
+    pub fn from_key(sender: uuid::Uuid, session_id: uuid::Uuid, expires_at: i64, public_key: RsaPublicKey) -> Self {
+        Self {
+            sender,
+            session_id,
+            expires_at,
+            public_key,
+            last_index: None,
+            last_timestamp: i64::MIN,
+        }
+    }
+
+    pub fn verify(&mut self, index: i32, body: &SignedMessageBody, signature: &MessageSignature) -> anyhow::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        if now >= self.expires_at {
+            anyhow::bail!("The chat session key of {} has expired.", self.sender);
+        }
+        if self.last_index.is_some_and(|last_index| index <= last_index) || body.timestamp < self.last_timestamp {
+            anyhow::bail!("Received message {} from {} out of order.", index, self.sender);
+        }
+        self.public_key
+            .verify(padding(), &message_digest(self.sender, self.session_id, index, body), &signature.0)
+            .map_err(|_| anyhow::anyhow!("Invalid signature for message {} from {}.", index, self.sender))?;
+        self.last_index = Some(index);
+        self.last_timestamp = body.timestamp;
+        Ok(())
+    }
+
+    /// Verifies a 1.19 message, which only has to be later than the one before it.
+    pub fn verify_legacy(&mut self, body: &LegacySignedMessageBody, signature: &[u8]) -> anyhow::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        if now >= self.expires_at {
+            anyhow::bail!("The chat session key of {} has expired.", self.sender);
+        }
+        if body.timestamp < self.last_timestamp {
+            anyhow::bail!("Received message from {} out of order.", self.sender);
+        }
+        self.public_key
+            .verify(padding(), &legacy_message_digest(self.sender, body), signature)
+            .map_err(|_| anyhow::anyhow!("Invalid signature for message from {}.", self.sender))?;
+        self.last_timestamp = body.timestamp;
+        Ok(())
+    }
+}
+
+/// An update to the messages a client has seen, sent with every chat message and command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastSeenUpdate {
+    /// Messages received since the previous update.
+    pub message_count: VarInt,
+    pub acknowledged: AcknowledgedMessages,
+}
+
+fn bit(acknowledged: &AcknowledgedMessages, index: usize) -> bool {
+    acknowledged.0[index / 8] & (1 << (index % 8)) != 0
+}
+
+#[derive(Clone)]
+struct TrackedSignature {
+    signature: MessageSignature,
+    pending: bool,
+}
+
+/// The client's side of last seen tracking, remembering the last messages it received.
+#[derive(Default)]
+pub struct LastSeenTracker {
+    entries: [Option<TrackedSignature>; LAST_SEEN_COUNT],
+    tail: usize,
+    offset: i32,
+    last_signature: Option<MessageSignature>,
+}
+
+impl LastSeenTracker {
+    /// Records a received message, ignoring repeats of the last one.
+    pub fn track(&mut self, signature: MessageSignature) {
+        if self.last_signature == Some(signature) {
+            return;
+        }
+        self.last_signature = Some(signature);
+        self.entries[self.tail] = Some(TrackedSignature {
+            signature,
+            pending: true,
+        });
+        self.tail = (self.tail + 1) % LAST_SEEN_COUNT;
+        self.offset += 1;
+    }
+
+    /// Messages received since the last update, clients acknowledge them past 64.
+    pub fn pending(&self) -> i32 {
+        self.offset
+    }
+
+    /// Builds the next update along with the signatures it acknowledges, oldest first.
+    pub fn update(&mut self) -> (LastSeenUpdate, Vec<MessageSignature>) {
+        let mut acknowledged = AcknowledgedMessages::default();
+        let mut last_seen = Vec::new();
+        for index in 0..LAST_SEEN_COUNT {
+            if let Some(entry) = &mut self.entries[(self.tail + index) % LAST_SEEN_COUNT] {
+                acknowledged.0[index / 8] |= 1 << (index % 8);
+                last_seen.push(entry.signature);
+                entry.pending = false;
+            }
+        }
+        let update = LastSeenUpdate {
+            message_count: VarInt::from(std::mem::take(&mut self.offset)),
+            acknowledged,
+        };
+        (update, last_seen)
+    }
+}
+
+/// The server's side of last seen tracking, checking a client's updates against the messages
+/// it was sent.
+pub struct LastSeenValidator {
+    tracked: VecDeque<Option<TrackedSignature>>,
+}
+
+impl Default for LastSeenValidator {
+    fn default() -> Self {
+        Self {
+            tracked: std::iter::repeat_n(None, LAST_SEEN_COUNT).collect(),
+        }
+    }
+}
+
+impl LastSeenValidator {
+    /// Records a signed message sent to the client.
+    pub fn track(&mut self, signature: MessageSignature) {
+        self.tracked.push_back(Some(TrackedSignature {
+            signature,
+            pending: true,
+        }));
+    }
+
+    /// Messages sent that the client has not acknowledged yet.
+    pub fn pending(&self) -> usize {
+        self.tracked.len() - LAST_SEEN_COUNT
+    }
+
+    /// Applies a `MessageAcknowledgment`, or the count of an update.
+    pub fn apply_offset(&mut self, message_count: VarInt) -> anyhow::Result<()> {
+        let offset = *message_count;
+        if offset < 0 || offset as usize > self.pending() {
+            anyhow::bail!("Client acknowledged {} messages with only {} pending.", offset, self.pending());
+        }
+        self.tracked.drain(..offset as usize);
+        Ok(())
+    }
+
+    /// Applies an update, returning the signatures the client claims to have seen, oldest first.
+    pub fn apply_update(&mut self, update: &LastSeenUpdate) -> anyhow::Result<Vec<MessageSignature>> {
+        self.apply_offset(update.message_count)?;
+        let mut last_seen = Vec::new();
+        for index in 0..LAST_SEEN_COUNT {
+            let entry = &mut self.tracked[index];
+            if bit(&update.acknowledged, index) {
+                let tracked = entry
+                    .as_mut()
+                    .ok_or_else(|| anyhow::anyhow!("Client acknowledged a message it was never sent."))?;
+                tracked.pending = false;
+                last_seen.push(tracked.signature);
+            } else {
+                if matches!(entry, Some(tracked) if !tracked.pending) {
+                    anyhow::bail!("Client stopped acknowledging a message it had seen.");
+                }
+                *entry = None;
+            }
+        }
+        Ok(last_seen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::Prefixed;
+    use rsa::PublicKeyParts;
+
+    fn signature(byte: u8) -> MessageSignature {
+        FixedArray([byte; 256])
+    }
+
+    #[test]
+    fn signed_messages_verify_in_order() {
+        let private_key = RsaPrivateKey::new(&mut rand::rngs::OsRng, 2048).unwrap();
+        let public_key = RsaPublicKey::from(&private_key);
+        let player_key = PlayerPublicKey {
+            expires_at: i64::MAX,
+            key: Prefixed::from(rsa_der::public_key_to_der(
+                &public_key.n().to_bytes_be(),
+                &public_key.e().to_bytes_be(),
+            )),
+            key_signature: Prefixed::default(),
+        };
+        let (sender, session_id) = (uuid::Uuid::from_u128(1), uuid::Uuid::from_u128(2));
+        let mut verifier = MessageVerifier::new(sender, session_id, &player_key).unwrap();
+
+        let last_seen = [signature(7)];
+        let body = SignedMessageBody {
+            message: "hello",
+            timestamp: 1_700_000_000_000,
+            salt: 42,
+            last_seen: &last_seen,
+        };
+        let first = sign_message(&private_key, sender, session_id, 0, &body).unwrap();
+        verifier.verify(0, &body, &first).unwrap();
+
+        let tampered = SignedMessageBody { message: "goodbye", ..body };
+        let second = sign_message(&private_key, sender, session_id, 1, &body).unwrap();
+        assert!(verifier.verify(1, &tampered, &second).is_err());
+        assert!(verifier.verify(0, &body, &first).is_err());
+        verifier.verify(1, &body, &second).unwrap();
+    }
+
+    #[test]
+    fn legacy_messages_sign_their_json_content() {
+        let private_key = RsaPrivateKey::new(&mut rand::rngs::OsRng, 1024).unwrap();
+        let sender = uuid::Uuid::from_u128(1);
+        // 1.19 keys are sent without a session, the login key is used instead.
+        let public_key = RsaPublicKey::from(&private_key);
+        let mut verifier = MessageVerifier::from_key(sender, uuid::Uuid::nil(), i64::MAX, public_key);
+
+        let content = Component::text("hello");
+        let body = LegacySignedMessageBody {
+            content: &content,
+            timestamp: 1_700_000_000_500,
+            salt: 42,
+        };
+        let data = legacy_signed_data(sender, &body);
+        assert_eq!(data[..8], 42i64.to_be_bytes());
+        assert_eq!(data[24..32], 1_700_000_000i64.to_be_bytes());
+        assert_eq!(&data[32..], br#"{"text":"hello"}"#);
+
+        let first = sign_legacy_message(&private_key, sender, &body).unwrap();
+        verifier.verify_legacy(&body, &first).unwrap();
+
+        let tampered = Component::text("goodbye");
+        let tampered = LegacySignedMessageBody { content: &tampered, ..body };
+        assert!(verifier.verify_legacy(&tampered, &first).is_err());
+        let earlier = LegacySignedMessageBody { timestamp: 0, ..body };
+        let signature = sign_legacy_message(&private_key, sender, &earlier).unwrap();
+        assert!(verifier.verify_legacy(&earlier, &signature).is_err());
+    }
+
+    #[test]
+    fn tracker_updates_satisfy_the_validator() {
+        let mut tracker = LastSeenTracker::default();
+        let mut validator = LastSeenValidator::default();
+        for byte in 0..25 {
+            tracker.track(signature(byte));
+            validator.track(signature(byte));
+        }
+        tracker.track(signature(24));
+        assert_eq!(tracker.pending(), 25);
+
+        let (update, last_seen) = tracker.update();
+        assert_eq!(*update.message_count, 25);
+        assert_eq!(last_seen, (5..25).map(signature).collect::<Vec<_>>());
+        assert_eq!(validator.apply_update(&update).unwrap(), last_seen);
+
+        tracker.track(signature(25));
+        validator.track(signature(25));
+        let (update, _) = tracker.update();
+        validator.apply_update(&update).unwrap();
+
+        validator.track(signature(26));
+        let mut forged = update.clone();
+        forged.message_count = VarInt::from(1);
+        forged.acknowledged.0[0] &= !1;
+        assert!(validator.apply_update(&forged).is_err());
+    }
+}
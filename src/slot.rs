@@ -0,0 +1,495 @@
+use crate::chat::Component;
+use crate::fields::{Prefixed, MAX_PREALLOCATION};
+use crate::nbt::{Compound, Nbt, Tag};
+use crate::protocol_version::{AsyncMapEncodable, MCProtocol, MapDecodable, MapEncodable};
+use minecraft_data_types::encoder::{Decodable, Encodable};
+use minecraft_data_types::nums::VarInt;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+/// Item data components replaced NBT in 1.20.5.
+fn uses_components(protocol: MCProtocol) -> bool {
+    protocol >= MCProtocol::V1_20_5
+}
+
+/// `HideFlags` bits of the tooltip lines components can hide before 1.20.5.
+const HIDE_UNBREAKABLE: i32 = 0x04;
+const HIDE_ADDITIONAL: i32 = 0x20;
+const HIDE_DYE: i32 = 0x40;
+/// Every line `HideFlags` covers, the name cannot be hidden before 1.20.5.
+const HIDE_ALL: i32 = 0xFF;
+
+/// An item data component from 1.20.5, only those useful for menus are supported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemComponent {
+    /// Tags of the item outside of any component, the whole NBT of older versions.
+    CustomData(Nbt),
+    MaxStackSize(VarInt),
+    MaxDamage(VarInt),
+    Damage(VarInt),
+    Unbreakable {
+        show_in_tooltip: bool,
+    },
+    CustomName(Component),
+    ItemName(Component),
+    Lore(Prefixed<VarInt, Vec<Component>>),
+    Rarity(VarInt),
+    Enchantments {
+        enchantments: Prefixed<VarInt, Vec<Enchantment>>,
+        show_in_tooltip: bool,
+    },
+    CustomModelData(VarInt),
+    HideAdditionalTooltip,
+    HideTooltip,
+    RepairCost(VarInt),
+    CreativeSlotLock,
+    EnchantmentGlintOverride(bool),
+    FireResistant,
+    StoredEnchantments {
+        enchantments: Prefixed<VarInt, Vec<Enchantment>>,
+        show_in_tooltip: bool,
+    },
+    /// An RGB color.
+    DyedColor {
+        color: i32,
+        show_in_tooltip: bool,
+    },
+}
+
+/// An enchantment's registry id and its level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Enchantment {
+    pub id: VarInt,
+    pub level: VarInt,
+}
+
+impl Decodable for Enchantment {
+    fn decode<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: VarInt::decode(reader)?,
+            level: VarInt::decode(reader)?,
+        })
+    }
+}
+
+impl Encodable for Enchantment {
+    fn encode<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        self.id.encode(writer)?;
+        self.level.encode(writer)
+    }
+
+    fn size(&self) -> anyhow::Result<VarInt> {
+        Ok(self.id.size()? + self.level.size()?)
+    }
+}
+
+fn read_count<R: Read>(reader: &mut R) -> anyhow::Result<usize> {
+    usize::try_from(*VarInt::decode(reader)?).map_err(|_| anyhow::anyhow!("Found a negative count."))
+}
+
+impl ItemComponent {
+    /// The component's id in 1.20.5.
+    pub fn type_id(&self) -> i32 {
+        match self {
+            Self::CustomData(_) => 0,
+            Self::MaxStackSize(_) => 1,
+            Self::MaxDamage(_) => 2,
+            Self::Damage(_) => 3,
+            Self::Unbreakable { .. } => 4,
+            Self::CustomName(_) => 5,
+            Self::ItemName(_) => 6,
+            Self::Lore(_) => 7,
+            Self::Rarity(_) => 8,
+            Self::Enchantments { .. } => 9,
+            Self::CustomModelData(_) => 13,
+            Self::HideAdditionalTooltip => 14,
+            Self::HideTooltip => 15,
+            Self::RepairCost(_) => 16,
+            Self::CreativeSlotLock => 17,
+            Self::EnchantmentGlintOverride(_) => 18,
+            Self::FireResistant => 21,
+            Self::StoredEnchantments { .. } => 23,
+            Self::DyedColor { .. } => 24,
+        }
+    }
+
+    fn read<R: Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        Ok(match *VarInt::decode(reader)? {
+            0 => Self::CustomData(Nbt::decode_mapped(protocol, reader)?),
+            1 => Self::MaxStackSize(VarInt::decode(reader)?),
+            2 => Self::MaxDamage(VarInt::decode(reader)?),
+            3 => Self::Damage(VarInt::decode(reader)?),
+            4 => Self::Unbreakable {
+                show_in_tooltip: bool::decode(reader)?,
+            },
+            5 => Self::CustomName(Component::decode_mapped(protocol, reader)?),
+            6 => Self::ItemName(Component::decode_mapped(protocol, reader)?),
+            7 => Self::Lore(Prefixed::decode_mapped(protocol, reader)?),
+            8 => Self::Rarity(VarInt::decode(reader)?),
+            9 => Self::Enchantments {
+                enchantments: Prefixed::decode(reader)?,
+                show_in_tooltip: bool::decode(reader)?,
+            },
+            13 => Self::CustomModelData(VarInt::decode(reader)?),
+            14 => Self::HideAdditionalTooltip,
+            15 => Self::HideTooltip,
+            16 => Self::RepairCost(VarInt::decode(reader)?),
+            17 => Self::CreativeSlotLock,
+            18 => Self::EnchantmentGlintOverride(bool::decode(reader)?),
+            21 => Self::FireResistant,
+            23 => Self::StoredEnchantments {
+                enchantments: Prefixed::decode(reader)?,
+                show_in_tooltip: bool::decode(reader)?,
+            },
+            24 => Self::DyedColor {
+                color: i32::decode(reader)?,
+                show_in_tooltip: bool::decode(reader)?,
+            },
+            id => anyhow::bail!("Item component {} is not supported.", id),
+        })
+    }
+
+    fn write<W: Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        VarInt::from(self.type_id()).encode(writer)?;
+        match self {
+            Self::CustomData(nbt) => nbt.encode_mapped(protocol, writer),
+            Self::MaxStackSize(value)
+            | Self::MaxDamage(value)
+            | Self::Damage(value)
+            | Self::Rarity(value)
+            | Self::CustomModelData(value)
+            | Self::RepairCost(value) => value.encode(writer),
+            Self::Unbreakable { show_in_tooltip } => show_in_tooltip.encode(writer),
+            Self::CustomName(name) | Self::ItemName(name) => name.encode_mapped(protocol, writer),
+            Self::Lore(lines) => lines.encode_mapped(protocol, writer),
+            Self::Enchantments {
+                enchantments,
+                show_in_tooltip,
+            }
+            | Self::StoredEnchantments {
+                enchantments,
+                show_in_tooltip,
+            } => {
+                enchantments.encode(writer)?;
+                show_in_tooltip.encode(writer)
+            }
+            Self::HideAdditionalTooltip | Self::HideTooltip | Self::CreativeSlotLock | Self::FireResistant => Ok(()),
+            Self::EnchantmentGlintOverride(glint) => glint.encode(writer),
+            Self::DyedColor { color, show_in_tooltip } => {
+                color.encode(writer)?;
+                show_in_tooltip.encode(writer)
+            }
+        }
+    }
+
+    /// Writes this component into the item NBT of older versions, where it has a tag.
+    ///
+    /// Enchantments are keyed by name there rather than registry id, so they are dropped along
+    /// with `ItemName`, `Rarity` and the other components without an NBT form.
+    fn write_legacy(&self, tags: &mut Compound) {
+        let hide = |tags: &mut Compound, flag: i32| {
+            let flags = match tags.get("HideFlags") {
+                Some(Tag::Int(flags)) => *flags,
+                _ => 0,
+            };
+            tags.insert("HideFlags", Tag::Int(flags | flag));
+        };
+        let display = |tags: &mut Compound, name: &str, tag: Tag| {
+            if !matches!(tags.get("display"), Some(Tag::Compound(_))) {
+                tags.insert("display", Tag::Compound(Compound::new()));
+            }
+            if let Some(Tag::Compound(display)) = tags.get_mut("display") {
+                display.insert(name, tag);
+            }
+        };
+        match self {
+            Self::CustomData(nbt) => {
+                if let Tag::Compound(custom) = &nbt.root {
+                    for (name, tag) in &custom.0 {
+                        tags.insert(name.as_str(), tag.clone());
+                    }
+                }
+            }
+            Self::Damage(damage) => tags.insert("Damage", Tag::Int(**damage)),
+            Self::Unbreakable { show_in_tooltip } => {
+                tags.insert("Unbreakable", Tag::Byte(1));
+                if !show_in_tooltip {
+                    hide(tags, HIDE_UNBREAKABLE);
+                }
+            }
+            Self::CustomName(name) => display(tags, "Name", Tag::String(name.to_json_string())),
+            Self::Lore(lines) => {
+                let lines = lines.iter().map(|line| Tag::String(line.to_json_string())).collect();
+                display(tags, "Lore", Tag::List(lines));
+            }
+            Self::CustomModelData(data) => tags.insert("CustomModelData", Tag::Int(**data)),
+            Self::RepairCost(cost) => tags.insert("RepairCost", Tag::Int(**cost)),
+            Self::HideAdditionalTooltip => hide(tags, HIDE_ADDITIONAL),
+            Self::HideTooltip => hide(tags, HIDE_ALL),
+            Self::DyedColor { color, show_in_tooltip } => {
+                display(tags, "color", Tag::Int(*color));
+                if !show_in_tooltip {
+                    hide(tags, HIDE_DYE);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An item with its count and data.
+///
+/// Item ids are registry ids of the client's version, which change between versions. Before
+/// 1.20.5 the item's data is NBT, where components with an NBT form are merged into `nbt` and
+/// the others left out. From 1.20.5 only components are sent and `nbt` has to be empty.
+///
+/// The pre-1.13 format of numeric ids with a damage value is older than any supported protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    pub item_id: VarInt,
+    pub count: i8,
+    pub nbt: Option<Nbt>,
+    pub components: Vec<ItemComponent>,
+    /// Ids of default components the item does not have, from 1.20.5.
+    pub removed_components: Vec<VarInt>,
+}
+
+impl ItemStack {
+    pub fn new(item_id: i32, count: i8) -> Self {
+        Self {
+            item_id: VarInt::from(item_id),
+            count,
+            nbt: None,
+            components: Vec::new(),
+            removed_components: Vec::new(),
+        }
+    }
+
+    /// Adds a component, replacing any of the same type.
+    pub fn with(mut self, component: ItemComponent) -> Self {
+        self.components.retain(|existing| existing.type_id() != component.type_id());
+        self.components.push(component);
+        self
+    }
+
+    /// The NBT sent before 1.20.5, `None` without any tag.
+    fn legacy_nbt(&self) -> Option<Nbt> {
+        let mut tags = match &self.nbt {
+            Some(Nbt {
+                root: Tag::Compound(tags),
+                ..
+            }) => tags.clone(),
+            Some(nbt) if self.components.is_empty() => return Some(nbt.clone()),
+            _ => Compound::new(),
+        };
+        for component in &self.components {
+            component.write_legacy(&mut tags);
+        }
+        if tags.0.is_empty() && self.nbt.is_none() {
+            return None;
+        }
+        Some(Nbt {
+            name: self.nbt.as_ref().map(|nbt| nbt.name.clone()).unwrap_or_default(),
+            root: Tag::Compound(tags),
+        })
+    }
+}
+
+/// An inventory slot, which may hold an item.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Slot(pub Option<ItemStack>);
+
+impl Slot {
+    pub fn empty() -> Self {
+        Self(None)
+    }
+
+    fn to_bytes(&self, protocol: MCProtocol) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let item = match &self.0 {
+            Some(item) if item.count > 0 => item,
+            // An empty stack is sent as no item.
+            _ if uses_components(protocol) => {
+                VarInt::from(0).encode(&mut bytes)?;
+                return Ok(bytes);
+            }
+            _ => {
+                false.encode(&mut bytes)?;
+                return Ok(bytes);
+            }
+        };
+
+        if !uses_components(protocol) {
+            true.encode(&mut bytes)?;
+            item.item_id.encode(&mut bytes)?;
+            item.count.encode(&mut bytes)?;
+            item.legacy_nbt().unwrap_or_else(|| Nbt::from(Tag::End)).encode_mapped(protocol, &mut bytes)?;
+            return Ok(bytes);
+        }
+
+        if item.nbt.is_some() {
+            anyhow::bail!("Items are sent with components from 1.20.5, found NBT.");
+        }
+        VarInt::from(i32::from(item.count)).encode(&mut bytes)?;
+        item.item_id.encode(&mut bytes)?;
+        VarInt::try_from(item.components.len())?.encode(&mut bytes)?;
+        VarInt::try_from(item.removed_components.len())?.encode(&mut bytes)?;
+        for component in &item.components {
+            component.write(protocol, &mut bytes)?;
+        }
+        for removed in &item.removed_components {
+            removed.encode(&mut bytes)?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl From<ItemStack> for Slot {
+    fn from(item: ItemStack) -> Self {
+        Self(Some(item))
+    }
+}
+
+impl MapDecodable for Slot {
+    fn decode_mapped<R: Read>(protocol: MCProtocol, reader: &mut R) -> anyhow::Result<Self> {
+        if !uses_components(protocol) {
+            if !bool::decode(reader)? {
+                return Ok(Self(None));
+            }
+            let item_id = VarInt::decode(reader)?;
+            let count = i8::decode(reader)?;
+            let nbt = Some(Nbt::decode_mapped(protocol, reader)?).filter(|nbt| nbt.root != Tag::End);
+            return Ok(Self(Some(ItemStack {
+                item_id,
+                count,
+                nbt,
+                components: Vec::new(),
+                removed_components: Vec::new(),
+            })));
+        }
+
+        let count = *VarInt::decode(reader)?;
+        if count == 0 {
+            return Ok(Self(None));
+        }
+        let count = i8::try_from(count)
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid item count {}.", count))?;
+        let item_id = VarInt::decode(reader)?;
+        let added = read_count(reader)?;
+        let removed = read_count(reader)?;
+        let mut components = Vec::with_capacity(added.min(MAX_PREALLOCATION));
+        for _ in 0..added {
+            components.push(ItemComponent::read(protocol, reader)?);
+        }
+        let mut removed_components = Vec::with_capacity(removed.min(MAX_PREALLOCATION));
+        for _ in 0..removed {
+            removed_components.push(VarInt::decode(reader)?);
+        }
+        Ok(Self(Some(ItemStack {
+            item_id,
+            count,
+            nbt: None,
+            components,
+            removed_components,
+        })))
+    }
+}
+
+impl MapEncodable for Slot {
+    fn encode_mapped<W: Write>(&self, protocol: MCProtocol, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(&self.to_bytes(protocol)?)?;
+        Ok(())
+    }
+
+    fn size_mapped(&self, protocol: MCProtocol) -> anyhow::Result<VarInt> {
+        VarInt::try_from(self.to_bytes(protocol)?.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncMapEncodable for Slot {
+    async fn encode_mapped_async<W: tokio::io::AsyncWrite + Send + Unpin>(
+        &self,
+        protocol: MCProtocol,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        tokio::io::AsyncWriteExt::write_all(writer, &self.to_bytes(protocol)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crate::registry::round_trip::ArbitraryField for Slot {
+    /// Only components with an NBT form, so the item can be written for every protocol.
+    fn arbitrary_field(runner: &mut proptest::test_runner::TestRunner) -> Self {
+        use crate::registry::round_trip::sample;
+        if sample(runner, 0..4u8) == 0 {
+            return Self::empty();
+        }
+        let mut item = ItemStack::new(sample(runner, 0..1400), sample(runner, 1..65i8));
+        for _ in 0..sample(runner, 0..3u8) {
+            item = item.with(match sample(runner, 0..4u8) {
+                0 => ItemComponent::CustomName(Component::arbitrary_field(runner)),
+                1 => ItemComponent::Lore(Prefixed::from(vec![
+                    Component::arbitrary_field(runner);
+                    sample(runner, 0..3usize)
+                ])),
+                2 => ItemComponent::Damage(VarInt::arbitrary_field(runner)),
+                _ => ItemComponent::CustomModelData(VarInt::arbitrary_field(runner)),
+            });
+        }
+        Self::from(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn round_trip(slot: &Slot, protocol: MCProtocol) -> Slot {
+        let mut bytes = Vec::new();
+        slot.encode_mapped(protocol, &mut bytes).unwrap();
+        let mut cursor = Cursor::new(&bytes);
+        let decoded = Slot::decode_mapped(protocol, &mut cursor).unwrap();
+        assert_eq!(cursor.position() as usize, bytes.len());
+        decoded
+    }
+
+    #[test]
+    fn menu_items_are_sent_with_nbt_or_components() {
+        let item = ItemStack::new(1, 1)
+            .with(ItemComponent::CustomName(Component::text("Survival")))
+            .with(ItemComponent::Lore(Prefixed::from(vec![Component::text("Click to join")])))
+            .with(ItemComponent::DyedColor {
+                color: 0xFF0000,
+                show_in_tooltip: false,
+            })
+            .with(ItemComponent::HideAdditionalTooltip);
+        let slot = Slot::from(item);
+
+        assert_eq!(round_trip(&slot, MCProtocol::V1_20_5), slot);
+
+        let legacy = round_trip(&slot, MCProtocol::V1_18).0.unwrap();
+        assert!(legacy.components.is_empty());
+        let nbt = legacy.nbt.clone().unwrap();
+        let tags = nbt.root.as_compound().unwrap();
+        assert_eq!(tags.get("HideFlags"), Some(&Tag::Int(HIDE_DYE | HIDE_ADDITIONAL)));
+        let display = tags.get("display").and_then(Tag::as_compound).unwrap();
+        assert_eq!(display.get("Name"), Some(&Tag::String(Component::text("Survival").to_json_string())));
+        assert_eq!(display.get("color"), Some(&Tag::Int(0xFF0000)));
+        assert!(matches!(display.get("Lore"), Some(Tag::List(lines)) if lines.len() == 1));
+
+        // NBT read from an older client cannot be sent as components.
+        let mut bytes = Vec::new();
+        assert!(Slot::from(legacy).encode_mapped(MCProtocol::V1_20_5, &mut bytes).is_err());
+
+        let empty = ItemStack::new(1, 0);
+        assert_eq!(round_trip(&Slot::from(empty), MCProtocol::V1_20_3), Slot::empty());
+        assert_eq!(round_trip(&Slot::empty(), MCProtocol::V1_20_5), Slot::empty());
+        let negative = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+        assert!(Slot::decode_mapped(MCProtocol::V1_20_5, &mut Cursor::new(&negative)).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::chat::Component;
+use crate::fields::Prefixed;
+use crate::packet::EncodedPacket;
+use crate::protocol_version::MCProtocol;
+use crate::registry::play::client_bound::{PlayerInfo, PlayerInfoRemove, PlayerInfoUpdate, TabListHeaderAndFooter};
+use crate::registry::play::{LegacyPlayerInfo, PlayerInfoAction, PlayerInfoActions, PlayerInfoChanges, PlayerInfoEntry};
+
+/// `PlayerInfoUpdate` replaced the single action `PlayerInfo` in 1.19.3.
+fn uses_player_info_update(protocol: MCProtocol) -> bool {
+    protocol >= MCProtocol::V1_20_2
+}
+
+/// A tab list kept independently of any version, serialized for each client's protocol.
+///
+/// Changing the list does not send anything, build the packets for the change with
+/// [`TabList::changes`] or [`TabList::removal`] and send them to every viewer.
+#[derive(Debug, Default)]
+pub struct TabList {
+    players: Vec<PlayerInfoEntry>,
+    pub header: Component,
+    pub footer: Component,
+}
+
+impl TabList {
+    /// Adds a player, replacing any entry with the same uuid.
+    pub fn add(&mut self, entry: PlayerInfoEntry) {
+        match self.get_mut(entry.uuid) {
+            Some(existing) => *existing = entry,
+            None => self.players.push(entry),
+        }
+    }
+
+    pub fn remove(&mut self, uuid: uuid::Uuid) -> Option<PlayerInfoEntry> {
+        let index = self.players.iter().position(|entry| entry.uuid == uuid)?;
+        Some(self.players.remove(index))
+    }
+
+    pub fn get(&self, uuid: uuid::Uuid) -> Option<&PlayerInfoEntry> {
+        self.players.iter().find(|entry| entry.uuid == uuid)
+    }
+
+    pub fn get_mut(&mut self, uuid: uuid::Uuid) -> Option<&mut PlayerInfoEntry> {
+        self.players.iter_mut().find(|entry| entry.uuid == uuid)
+    }
+
+    /// Players in the order they were added.
+    pub fn players(&self) -> &[PlayerInfoEntry] {
+        &self.players
+    }
+
+    /// Everything a client joining now needs to show this tab list.
+    pub fn snapshot(&self, protocol: MCProtocol) -> anyhow::Result<Vec<EncodedPacket>> {
+        let uuids = self.players.iter().map(|entry| entry.uuid).collect::<Vec<_>>();
+        let mut packets = self.changes(&uuids, PlayerInfoActions::all(), protocol)?;
+        packets.push(self.header_and_footer(protocol)?);
+        Ok(packets)
+    }
+
+    /// Sends the fields of `actions` for the given players.
+    ///
+    /// Before 1.19.3 each action is its own packet and `ADD_PLAYER` carries every field but
+    /// `listed`, which does not exist there. A chat session can only be sent along with
+    /// `ADD_PLAYER` and only in 1.19.
+    pub fn changes(
+        &self,
+        uuids: &[uuid::Uuid],
+        actions: PlayerInfoActions,
+        protocol: MCProtocol,
+    ) -> anyhow::Result<Vec<EncodedPacket>> {
+        let players = uuids
+            .iter()
+            .map(|uuid| {
+                self.get(*uuid)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Player {} is not in the tab list.", uuid))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if players.is_empty() || actions.bits() == 0 {
+            return Ok(Vec::new());
+        }
+
+        if uses_player_info_update(protocol) {
+            let update = PlayerInfoUpdate {
+                update: PlayerInfoChanges { actions, players },
+            };
+            return Ok(vec![EncodedPacket::from_writable(&update, protocol)?]);
+        }
+
+        let legacy_actions = if actions.contains(PlayerInfoActions::ADD_PLAYER) {
+            vec![PlayerInfoAction::AddPlayer]
+        } else {
+            [
+                (PlayerInfoActions::UPDATE_GAME_MODE, PlayerInfoAction::UpdateGameMode),
+                (PlayerInfoActions::UPDATE_LATENCY, PlayerInfoAction::UpdateLatency),
+                (PlayerInfoActions::UPDATE_DISPLAY_NAME, PlayerInfoAction::UpdateDisplayName),
+            ]
+            .into_iter()
+            .filter(|(flag, _)| actions.contains(*flag))
+            .map(|(_, action)| action)
+            .collect()
+        };
+        legacy_actions
+            .into_iter()
+            .map(|action| {
+                let update = PlayerInfo {
+                    update: LegacyPlayerInfo {
+                        action,
+                        players: players.clone(),
+                    },
+                };
+                EncodedPacket::from_writable(&update, protocol)
+            })
+            .collect()
+    }
+
+    /// Removes players from a client's tab list, whether or not they are still in this one.
+    pub fn removal(uuids: &[uuid::Uuid], protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+        if uses_player_info_update(protocol) {
+            let remove = PlayerInfoRemove {
+                players: Prefixed::from(uuids.to_vec()),
+            };
+            return EncodedPacket::from_writable(&remove, protocol);
+        }
+        let remove = PlayerInfo {
+            update: LegacyPlayerInfo {
+                action: PlayerInfoAction::RemovePlayer,
+                players: uuids.iter().map(|uuid| PlayerInfoEntry::new(*uuid, "")).collect(),
+            },
+        };
+        EncodedPacket::from_writable(&remove, protocol)
+    }
+
+    pub fn header_and_footer(&self, protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+        let packet = TabListHeaderAndFooter {
+            header: self.header.clone(),
+            footer: self.footer.clone(),
+        };
+        EncodedPacket::from_writable(&packet, protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::round_trip::decode_packet as decode;
+    use minecraft_data_types::nums::VarInt;
+
+    fn tab_list() -> TabList {
+        let mut tab_list = TabList {
+            header: Component::text("Lobby"),
+            ..TabList::default()
+        };
+        let mut alex = PlayerInfoEntry::new(uuid::Uuid::from_u128(1), "Alex");
+        alex.latency = VarInt::from(35);
+        alex.display_name = Some(Component::text("[Admin] Alex"));
+        tab_list.add(alex);
+        tab_list.add(PlayerInfoEntry::new(uuid::Uuid::from_u128(2), "Steve"));
+        tab_list
+    }
+
+    #[test]
+    fn snapshot_is_serialized_per_protocol() {
+        let tab_list = tab_list();
+
+        let packets = tab_list.snapshot(MCProtocol::V1_20_5).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(*packets[0].packet_id(), 0x3E);
+        let update = decode::<PlayerInfoUpdate>(&packets[0], MCProtocol::V1_20_5).update;
+        assert_eq!(update.actions, PlayerInfoActions::all());
+        assert_eq!(update.players[0].name.to_string(), "Alex");
+        assert_eq!(*update.players[0].latency, 35);
+        assert_eq!(update.players[1].display_name, None);
+        let header = decode::<TabListHeaderAndFooter>(&packets[1], MCProtocol::V1_20_5);
+        assert_eq!(header.header, tab_list.header);
+
+        let packets = tab_list.snapshot(MCProtocol::V1_18).unwrap();
+        assert_eq!(*packets[0].packet_id(), 0x36);
+        assert_eq!(*packets[1].packet_id(), 0x5F);
+        let update = decode::<PlayerInfo>(&packets[0], MCProtocol::V1_18).update;
+        assert!(matches!(update.action, PlayerInfoAction::AddPlayer));
+        assert_eq!(update.players[1].name.to_string(), "Steve");
+        assert_eq!(update.players[0].display_name, tab_list.players()[0].display_name);
+    }
+
+    #[test]
+    fn legacy_changes_split_into_one_packet_per_action() {
+        let mut tab_list = tab_list();
+        let uuid = uuid::Uuid::from_u128(2);
+        tab_list.get_mut(uuid).unwrap().game_mode = VarInt::from(3);
+        let actions = PlayerInfoActions::UPDATE_GAME_MODE | PlayerInfoActions::UPDATE_LATENCY;
+
+        let packets = tab_list.changes(&[uuid], actions, MCProtocol::V1_19).unwrap();
+        assert_eq!(packets.len(), 2);
+        let update = decode::<PlayerInfo>(&packets[0], MCProtocol::V1_19).update;
+        assert!(matches!(update.action, PlayerInfoAction::UpdateGameMode));
+        assert_eq!(*update.players[0].game_mode, 3);
+
+        let packets = tab_list.changes(&[uuid], actions, MCProtocol::V1_20_2).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert!(tab_list.changes(&[uuid::Uuid::nil()], actions, MCProtocol::V1_19).is_err());
+
+        tab_list.remove(uuid);
+        let removal = TabList::removal(&[uuid], MCProtocol::V1_19).unwrap();
+        let update = decode::<PlayerInfo>(&removal, MCProtocol::V1_19).update;
+        assert!(matches!(update.action, PlayerInfoAction::RemovePlayer));
+        let removal = TabList::removal(&[uuid], MCProtocol::V1_20_3).unwrap();
+        assert_eq!(*decode::<PlayerInfoRemove>(&removal, MCProtocol::V1_20_3).players, vec![uuid]);
+        assert_eq!(tab_list.players().len(), 1);
+    }
+}
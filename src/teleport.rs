@@ -0,0 +1,107 @@
+use crate::registry::play::client_bound::PlayerPositionAndLook;
+use crate::registry::play::server_bound::TeleportConfirm;
+use crate::registry::play::RelativeFlags;
+use minecraft_data_types::nums::VarInt;
+use std::collections::VecDeque;
+
+/// Hands out teleport ids and checks the client confirms them.
+///
+/// Clients confirm teleports in the order they were sent, and their movement should be ignored
+/// while any is pending as it was made before the teleport.
+#[derive(Debug, Default)]
+pub struct TeleportTracker {
+    next_id: i32,
+    pending: VecDeque<i32>,
+}
+
+impl TeleportTracker {
+    /// Builds the teleport under a new id, which is pending until confirmed.
+    pub fn teleport(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+        flags: RelativeFlags,
+    ) -> PlayerPositionAndLook {
+        let teleport_id = self.next_id;
+        // Vanilla wraps before reaching `i32::MAX`, as clients never send it.
+        self.next_id = if teleport_id == i32::MAX - 1 { 0 } else { teleport_id + 1 };
+        self.pending.push_back(teleport_id);
+        PlayerPositionAndLook {
+            x,
+            y,
+            z,
+            yaw,
+            pitch,
+            flags,
+            teleport_id: VarInt::from(teleport_id),
+            dismount_vehicle: false,
+        }
+    }
+
+    /// Accepts the confirmation of the oldest pending teleport, anything else is an error the
+    /// connection should be closed for.
+    pub fn confirm(&mut self, confirm: &TeleportConfirm) -> anyhow::Result<()> {
+        match self.pending.front() {
+            Some(teleport_id) if *teleport_id == *confirm.teleport_id => {
+                self.pending.pop_front();
+                Ok(())
+            }
+            Some(teleport_id) => anyhow::bail!(
+                "Expected confirmation of teleport {}, found {}.",
+                teleport_id,
+                *confirm.teleport_id
+            ),
+            None => anyhow::bail!("Unexpected confirmation of teleport {}.", *confirm.teleport_id),
+        }
+    }
+
+    /// Teleports sent but not confirmed yet, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = i32> + '_ {
+        self.pending.iter().copied()
+    }
+
+    /// Whether the client has confirmed every teleport, so its movement can be trusted.
+    pub fn is_settled(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::EncodedPacket;
+    use crate::protocol_version::MCProtocol;
+
+    fn confirm(teleport_id: i32) -> TeleportConfirm {
+        TeleportConfirm {
+            teleport_id: VarInt::from(teleport_id),
+        }
+    }
+
+    #[test]
+    fn confirmations_must_follow_teleports_in_order() {
+        let mut tracker = TeleportTracker::default();
+        assert!(tracker.confirm(&confirm(0)).is_err());
+
+        let spawn = tracker.teleport(0.5, 64.0, 0.5, 0.0, 0.0, RelativeFlags::empty());
+        let turn = tracker.teleport(0.0, 0.0, 0.0, 90.0, 0.0, RelativeFlags::all() - RelativeFlags::YAW);
+        assert_eq!((*spawn.teleport_id, *turn.teleport_id), (0, 1));
+        assert_eq!(tracker.pending().collect::<Vec<_>>(), vec![0, 1]);
+
+        assert!(tracker.confirm(&confirm(1)).is_err());
+        tracker.confirm(&confirm(0)).unwrap();
+        assert!(!tracker.is_settled());
+        tracker.confirm(&confirm(1)).unwrap();
+        assert!(tracker.is_settled());
+
+        // Flags follow the position and angles, and `dismount_vehicle` is gone from 1.19.4.
+        let packet = EncodedPacket::from_writable(&turn, MCProtocol::V1_19).unwrap();
+        assert_eq!(packet.raw_bytes().unwrap()[33..], [0x17, 1, 0]);
+        let packet = EncodedPacket::from_writable(&turn, MCProtocol::V1_20_5).unwrap();
+        assert_eq!(*packet.packet_id(), 0x40);
+        assert_eq!(packet.raw_bytes().unwrap()[33..], [0x17, 1]);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::packet::{PacketReader, PacketWriter, WritablePacket};
+use crate::protocol_version::{MCProtocol, MapDecodable};
+use crate::registry::RegistryBase;
+use minecraft_data_types::{encoder::*, nums::VarInt};
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{DuplexStream, ReadHalf, WriteHalf};
+
+const DUPLEX_CAPACITY: usize = 1 << 22;
+
+/// One end of an in-memory connection, driving a real `PacketReader`/`PacketWriter` pair.
+pub struct TestConnection {
+    pub reader: PacketReader<ReadHalf<DuplexStream>>,
+    pub writer: PacketWriter<WriteHalf<DuplexStream>>,
+    pub protocol: MCProtocol,
+}
+
+/// Builds a connected `(client, server)` pair over `tokio::io::duplex`.
+pub fn connected_pair(protocol: MCProtocol) -> (TestConnection, TestConnection) {
+    let (client, server) = tokio::io::duplex(DUPLEX_CAPACITY);
+    (
+        TestConnection::new(client, "127.0.0.1:25565", protocol),
+        TestConnection::new(server, "127.0.0.1:50000", protocol),
+    )
+}
+
+impl TestConnection {
+    fn new(stream: DuplexStream, peer: &str, protocol: MCProtocol) -> Self {
+        let address: SocketAddr = peer.parse().expect("Test peer address should be valid.");
+        let (read, write) = tokio::io::split(stream);
+        Self {
+            reader: PacketReader::new(read, Arc::new(address)),
+            writer: PacketWriter::new(write),
+            protocol,
+        }
+    }
+
+    /// Enables compression in both directions for this end, call on both ends to keep them in sync.
+    pub fn enable_compression(&mut self, compression_threshold: i32) {
+        self.writer.enable_compression(compression_threshold);
+        self.reader.enable_decompression();
+    }
+
+    /// Enables encryption in both directions for this end, call on both ends to keep them in sync.
+    pub fn enable_encryption(&mut self, shared_secret: &[u8]) -> anyhow::Result<()> {
+        let (read_codec, write_codec) = crate::encryption::Codec::new(shared_secret)?;
+        self.reader.enable_decryption(read_codec);
+        self.writer.enable_encryption(write_codec);
+        Ok(())
+    }
+
+    pub async fn send<T: WritablePacket>(&mut self, packet: &T) -> anyhow::Result<()> {
+        let mut resolved = packet.to_resolved_packet(self.protocol)?;
+        self.writer.send_resolved_packet(&mut resolved).await
+    }
+
+    pub async fn receive_raw(&mut self) -> anyhow::Result<Cursor<Vec<u8>>> {
+        self.reader.next_packet().await
+    }
+
+    pub async fn receive<T: MapDecodable>(&mut self) -> anyhow::Result<(VarInt, T)> {
+        let mut cursor = self.receive_raw().await?;
+        let packet_id = VarInt::decode(&mut cursor)?;
+        let packet = T::decode_mapped(self.protocol, &mut cursor)?;
+        if cursor.position() != cursor.get_ref().len() as u64 {
+            anyhow::bail!(
+                "Packet {} left {} trailing bytes.",
+                packet_id,
+                cursor.get_ref().len() as u64 - cursor.position()
+            );
+        }
+        Ok((packet_id, packet))
+    }
+
+    /// Receives the next packet and asserts it has the expected packet id.
+    pub async fn expect<T: MapDecodable>(&mut self, packet_id: i32) -> anyhow::Result<T> {
+        let (received_id, packet) = self.receive::<T>().await?;
+        if *received_id != packet_id {
+            anyhow::bail!("Expected packet id {:#x}, received {:#x}.", packet_id, *received_id);
+        }
+        Ok(packet)
+    }
+
+    /// Receives the next packet and dispatches it through a registry.
+    pub async fn dispatch<H: Send + Sync, R: RegistryBase<H>>(&mut self, handler: &mut H) -> anyhow::Result<()> {
+        let cursor = self.receive_raw().await?;
+        R::handle_packet(handler, cursor, self.protocol).await
+    }
+}
+
+/// Packets only derive `Debug`, so equality is asserted on their debug representation.
+pub fn assert_packet_eq<T: Debug>(left: &T, right: &T) {
+    assert_eq!(format!("{:?}", left), format!("{:?}", right));
+}
@@ -0,0 +1,124 @@
+use crate::chat::Component;
+use crate::packet::EncodedPacket;
+use crate::protocol_version::MCProtocol;
+use crate::registry::play::client_bound::{
+    ClearTitles, SetActionBarText, SetSubtitleText, SetTitleText, SetTitlesAnimation,
+};
+
+/// A title with its subtitle and timings, shown through the packets 1.17 split the old `Title`
+/// packet into.
+///
+/// Every protocol this crate supports uses the split packets, so only their ids differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Title {
+    pub title: Component,
+    pub subtitle: Option<Component>,
+    /// Ticks.
+    pub fade_in: i32,
+    pub stay: i32,
+    pub fade_out: i32,
+}
+
+impl Title {
+    /// Vanilla's default timings of half a second in, 3.5 seconds shown and a second out.
+    pub fn new(title: Component) -> Self {
+        Self {
+            title,
+            subtitle: None,
+            fade_in: 10,
+            stay: 70,
+            fade_out: 20,
+        }
+    }
+
+    pub fn subtitle(mut self, subtitle: Component) -> Self {
+        self.subtitle = Some(subtitle);
+        self
+    }
+
+    pub fn times(mut self, fade_in: i32, stay: i32, fade_out: i32) -> Self {
+        self.fade_in = fade_in;
+        self.stay = stay;
+        self.fade_out = fade_out;
+        self
+    }
+
+    /// The packets showing this title, the subtitle and timings have to come before the title.
+    ///
+    /// Without a subtitle the one left from a previous title is cleared.
+    pub fn packets(&self, protocol: MCProtocol) -> anyhow::Result<Vec<EncodedPacket>> {
+        let times = SetTitlesAnimation {
+            fade_in: self.fade_in,
+            stay: self.stay,
+            fade_out: self.fade_out,
+        };
+        let subtitle = SetSubtitleText {
+            text: self.subtitle.clone().unwrap_or_default(),
+        };
+        let title = SetTitleText {
+            text: self.title.clone(),
+        };
+        Ok(vec![
+            EncodedPacket::from_writable(&times, protocol)?,
+            EncodedPacket::from_writable(&subtitle, protocol)?,
+            EncodedPacket::from_writable(&title, protocol)?,
+        ])
+    }
+}
+
+pub fn action_bar(text: Component, protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+    EncodedPacket::from_writable(&SetActionBarText { text }, protocol)
+}
+
+/// Hides the current title, with `reset` also forgetting its subtitle and timings.
+pub fn clear_title(reset: bool, protocol: MCProtocol) -> anyhow::Result<EncodedPacket> {
+    EncodedPacket::from_writable(&ClearTitles { reset }, protocol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_version::MapEncodable;
+
+    fn ids(packets: &[EncodedPacket]) -> Vec<i32> {
+        packets.iter().map(|packet| *packet.packet_id()).collect()
+    }
+
+    #[test]
+    fn titles_use_the_ids_and_text_format_of_each_protocol() {
+        let title = Title::new(Component::text("Hi"))
+            .subtitle(Component::text("there"))
+            .times(5, 40, 10);
+
+        let packets = title.packets(MCProtocol::V1_18).unwrap();
+        assert_eq!(ids(&packets), vec![0x5B, 0x58, 0x5A]);
+        assert_eq!(packets[0].raw_bytes().unwrap(), [0x5B, 0, 0, 0, 5, 0, 0, 0, 40, 0, 0, 0, 10]);
+        let mut expected = vec![0x5A, 13];
+        expected.extend_from_slice(br#"{"text":"Hi"}"#);
+        assert_eq!(packets[2].raw_bytes().unwrap(), expected);
+
+        // Text is sent as NBT from 1.20.3.
+        let packets = title.packets(MCProtocol::V1_20_5).unwrap();
+        assert_eq!(ids(&packets), vec![0x66, 0x63, 0x65]);
+        let mut expected = vec![0x65];
+        title.title.encode_mapped(MCProtocol::V1_20_5, &mut expected).unwrap();
+        assert!(matches!(expected[1], 0x08 | 0x0A), "Expected an NBT tag, found {:#x}.", expected[1]);
+        assert_eq!(packets[2].raw_bytes().unwrap(), expected);
+
+        // Without a subtitle an empty one replaces the last.
+        let packets = Title::new(Component::text("Hi")).packets(MCProtocol::V1_19).unwrap();
+        assert_eq!(ids(&packets), vec![0x5B, 0x58, 0x5A]);
+        assert_eq!(packets[1].raw_bytes().unwrap()[2..], *br#"{"text":""}"#);
+    }
+
+    #[test]
+    fn action_bars_and_clears_use_the_ids_of_each_protocol() {
+        let text = || Component::text("Hi");
+        assert_eq!(*action_bar(text(), MCProtocol::V1_17_1).unwrap().packet_id(), 0x41);
+        assert_eq!(*action_bar(text(), MCProtocol::V1_19).unwrap().packet_id(), 0x40);
+        assert_eq!(*action_bar(text(), MCProtocol::V1_20_5).unwrap().packet_id(), 0x4C);
+
+        assert_eq!(clear_title(true, MCProtocol::V1_18).unwrap().raw_bytes().unwrap(), [0x10, 1]);
+        assert_eq!(clear_title(false, MCProtocol::V1_20_5).unwrap().raw_bytes().unwrap(), [0x0F, 0]);
+    }
+}
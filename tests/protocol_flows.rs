@@ -0,0 +1,435 @@
+use mc_packet_protocol::capture::{ConnectionState, Direction};
+use mc_packet_protocol::chat::{Color, Component};
+use mc_packet_protocol::fields::{Prefixed, PrefixedOption, RemainingBytes};
+use mc_packet_protocol::keep_alive::{spawn_keep_alive, KeepAliveConfig};
+use mc_packet_protocol::nbt::{Compound, Nbt, Tag};
+use mc_packet_protocol::packet::{spin, PacketReadWriteLocker};
+use mc_packet_protocol::protocol_version::{MCProtocol, MapDecodable};
+use mc_packet_protocol::registry::{configuration, handshake, login, play, status};
+use mc_packet_protocol::test_util::{assert_packet_eq, connected_pair};
+use minecraft_data_types::common::Identifier;
+use minecraft_data_types::encoder::Decodable;
+use minecraft_data_types::nums::VarInt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+#[tokio::test]
+async fn handshake_and_status() -> anyhow::Result<()> {
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_18);
+
+    let handshake = handshake::server_bound::Handshake {
+        protocol_version: VarInt::from(757),
+        server_address: handshake::server_bound::ServerAddress::from("localhost"),
+        server_port: 25565,
+        next_state: handshake::server_bound::NextState::Status,
+    };
+    client.send(&handshake).await?;
+    let received = server.expect::<handshake::server_bound::Handshake>(0x00).await?;
+    assert_packet_eq(&handshake, &received);
+
+    client.send(&status::server_bound::StatusRequest {}).await?;
+    server.expect::<status::server_bound::StatusRequest>(0x00).await?;
+
+    let response = status::client_bound::StatusResponse {
+        json_response: status::client_bound::JSONResponse::from(r#"{"description":"test"}"#),
+    };
+    server.send(&response).await?;
+    let received = client.expect::<status::client_bound::StatusResponse>(0x00).await?;
+    assert_packet_eq(&response, &received);
+
+    client.send(&status::server_bound::Ping { payload: 42 }).await?;
+    let ping = server.expect::<status::server_bound::Ping>(0x01).await?;
+    server.send(&status::client_bound::Pong { payload: ping.payload }).await?;
+    let pong = client.expect::<status::client_bound::Pong>(0x01).await?;
+    assert_eq!(pong.payload, 42);
+    Ok(())
+}
+
+#[tokio::test]
+async fn login_with_encryption_and_compression() -> anyhow::Result<()> {
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_18);
+    let shared_secret = [7u8; 16];
+
+    client
+        .send(&login::server_bound::LoginStart {
+            name: login::LoginName::from("Notch"),
+            public_key: PrefixedOption(None),
+            uuid: uuid::Uuid::nil(),
+        })
+        .await?;
+    server.expect::<login::server_bound::LoginStart>(0x00).await?;
+
+    let (_, _, request) = login::client_bound::EncryptionRequest::new()?;
+    server.send(&request).await?;
+    let received = client.expect::<login::client_bound::EncryptionRequest>(0x01).await?;
+    assert_eq!(*received.public_key, *request.public_key);
+    assert_eq!(*received.verify_token, *request.verify_token);
+    // Only sent from 1.20.5.
+    assert!(!received.should_authenticate);
+
+    client.enable_encryption(&shared_secret)?;
+    server.enable_encryption(&shared_secret)?;
+
+    server
+        .send(&login::client_bound::SetCompression {
+            threshold: VarInt::from(64),
+        })
+        .await?;
+    let compression = client.expect::<login::client_bound::SetCompression>(0x03).await?;
+    client.enable_compression(*compression.threshold);
+    server.enable_compression(64);
+
+    let success = login::client_bound::LoginSuccess {
+        uuid: uuid::Uuid::from_u128(0x1234),
+        username: login::LoginName::from("Notch"),
+        properties: Default::default(),
+        strict_error_handling: false,
+    };
+    server.send(&success).await?;
+    let received = client.expect::<login::client_bound::LoginSuccess>(0x02).await?;
+    assert_packet_eq(&success, &received);
+
+    // Large enough to cross the compression threshold.
+    let plugin_message = play::client_bound::PluginMessage {
+        channel: Identifier::from("minecraft:brand"),
+        data: RemainingBytes::from(vec![3; 4096]),
+    };
+    server.send(&plugin_message).await?;
+    let received = client.expect::<play::client_bound::PluginMessage>(0x18).await?;
+    assert_eq!(*received.data, *plugin_message.data);
+
+    let disconnect = play::client_bound::Disconnect {
+        reason: Component::text("bye"),
+    };
+    server.send(&disconnect).await?;
+    let received = client.expect::<play::client_bound::Disconnect>(0x1A).await?;
+    assert_packet_eq(&disconnect, &received);
+    Ok(())
+}
+
+#[tokio::test]
+async fn login_success_properties_are_version_gated() -> anyhow::Result<()> {
+    let success = || login::client_bound::LoginSuccess {
+        uuid: uuid::Uuid::from_u128(0x1234),
+        username: login::LoginName::from("Notch"),
+        properties: Prefixed::from(vec![login::Property {
+            name: login::PropertyString::from("textures"),
+            value: login::PropertyString::from("e30="),
+            signature: PrefixedOption(None),
+        }]),
+        strict_error_handling: false,
+    };
+
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_19);
+    server.send(&success()).await?;
+    let received = client.expect::<login::client_bound::LoginSuccess>(0x02).await?;
+    assert_packet_eq(&success(), &received);
+
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_18);
+    server.send(&success()).await?;
+    let received = client.expect::<login::client_bound::LoginSuccess>(0x02).await?;
+    assert!(received.properties.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn transfer_handshake_and_cookies() -> anyhow::Result<()> {
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_20_5);
+
+    client
+        .send(&handshake::server_bound::Handshake {
+            protocol_version: VarInt::from(766),
+            server_address: handshake::server_bound::ServerAddress::from("localhost"),
+            server_port: 25565,
+            next_state: handshake::server_bound::NextState::Transfer,
+        })
+        .await?;
+    let received = server.expect::<handshake::server_bound::Handshake>(0x00).await?;
+    assert!(matches!(received.next_state, handshake::server_bound::NextState::Transfer));
+
+    let start = login::server_bound::LoginStart {
+        name: login::LoginName::from("Notch"),
+        public_key: PrefixedOption(None),
+        uuid: uuid::Uuid::from_u128(0x1234),
+    };
+    client.send(&start).await?;
+    let received = server.expect::<login::server_bound::LoginStart>(0x00).await?;
+    assert_packet_eq(&start, &received);
+
+    server
+        .send(&login::client_bound::CookieRequest {
+            key: Identifier::from("proxy:session"),
+        })
+        .await?;
+    let request = client.expect::<login::client_bound::CookieRequest>(0x05).await?;
+    let response = login::server_bound::CookieResponse {
+        key: request.key,
+        payload: PrefixedOption(Some(Prefixed::from(vec![1, 2, 3]))),
+    };
+    client.send(&response).await?;
+    let received = server.expect::<login::server_bound::CookieResponse>(0x04).await?;
+    assert_packet_eq(&response, &received);
+    Ok(())
+}
+
+#[tokio::test]
+async fn configuration_state_transitions() -> anyhow::Result<()> {
+    let protocol = MCProtocol::V1_20_2;
+    let (mut client, mut server) = connected_pair(protocol);
+    let mut state = ConnectionState::Login;
+
+    server
+        .send(&login::client_bound::LoginSuccess {
+            uuid: uuid::Uuid::from_u128(0x1234),
+            username: login::LoginName::from("Notch"),
+            properties: Default::default(),
+            strict_error_handling: false,
+        })
+        .await?;
+    let raw = client.receive_raw().await?;
+    state = state.next_state(Direction::ClientBound, protocol, raw.get_ref())?;
+    assert_eq!(state, ConnectionState::Login);
+
+    client.send(&login::server_bound::LoginAcknowledged {}).await?;
+    let raw = server.receive_raw().await?;
+    state = state.next_state(Direction::ServerBound, protocol, raw.get_ref())?;
+    assert_eq!(state, ConnectionState::Configuration);
+
+    let registry_data = configuration::client_bound::RegistryData {
+        codec: Nbt::from(Compound::new().with("minecraft:dimension_type", Tag::Compound(Compound::new()))),
+        registry_id: Identifier::from(""),
+        entries: Default::default(),
+    };
+    server.send(&registry_data).await?;
+    let received = client.expect::<configuration::client_bound::RegistryData>(0x05).await?;
+    assert_packet_eq(&registry_data, &received);
+
+    server
+        .send(&configuration::client_bound::FeatureFlags {
+            flags: Prefixed::from(vec![Identifier::from("minecraft:vanilla")]),
+        })
+        .await?;
+    let received = client.expect::<configuration::client_bound::FeatureFlags>(0x07).await?;
+    assert_eq!(received.flags.len(), 1);
+
+    server.send(&configuration::client_bound::FinishConfiguration {}).await?;
+    let raw = client.receive_raw().await?;
+    assert_eq!(state.next_state(Direction::ClientBound, protocol, raw.get_ref())?, state);
+    client
+        .send(&configuration::server_bound::AcknowledgeFinishConfiguration {})
+        .await?;
+    let raw = server.receive_raw().await?;
+    state = state.next_state(Direction::ServerBound, protocol, raw.get_ref())?;
+    assert_eq!(state, ConnectionState::Play);
+
+    server.send(&play::client_bound::StartConfiguration {}).await?;
+    client.expect::<play::client_bound::StartConfiguration>(0x65).await?;
+    client.send(&play::server_bound::AcknowledgeConfiguration {}).await?;
+    let raw = server.receive_raw().await?;
+    state = state.next_state(Direction::ServerBound, protocol, raw.get_ref())?;
+    assert_eq!(state, ConnectionState::Configuration);
+    Ok(())
+}
+
+fn join_game(dimension_codec: Nbt, dimension: Nbt, dimension_type: &str) -> play::client_bound::JoinGame {
+    play::client_bound::JoinGame {
+        entity_id: 7,
+        is_hardcore: false,
+        gamemode: 3,
+        previous_gamemode: -1,
+        world_names: Prefixed::from(vec![Identifier::from("minecraft:overworld")]),
+        dimension_codec,
+        dimension,
+        dimension_type: Identifier::from(dimension_type),
+        dimension_type_id: VarInt::from(0),
+        world_name: Identifier::from("minecraft:overworld"),
+        hashed_seed: 0x1234,
+        max_players: VarInt::from(20),
+        view_distance: VarInt::from(10),
+        simulation_distance: VarInt::from(10),
+        reduced_debug_info: false,
+        enable_respawn_screen: true,
+        do_limited_crafting: false,
+        is_debug: false,
+        is_flat: false,
+        death_location: PrefixedOption(None),
+        portal_cooldown: VarInt::from(0),
+        enforces_secure_chat: false,
+    }
+}
+
+#[tokio::test]
+async fn join_game_and_respawn_across_versions() -> anyhow::Result<()> {
+    let codec = Nbt {
+        name: String::new(),
+        root: Tag::Compound(Compound::new().with("minecraft:dimension_type", Tag::Compound(Compound::new()))),
+    };
+    let dimension = Nbt::from(Compound::new().with("natural", Tag::Byte(1)));
+
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_18);
+    let join = join_game(codec, dimension, "");
+    server.send(&join).await?;
+    let received = client.expect::<play::client_bound::JoinGame>(0x26).await?;
+    assert_packet_eq(&join, &received);
+
+    let respawn = play::client_bound::Respawn {
+        dimension: Nbt::from(Compound::new().with("natural", Tag::Byte(0))),
+        dimension_type: Identifier::from(""),
+        dimension_type_id: VarInt::from(0),
+        world_name: Identifier::from("minecraft:the_nether"),
+        hashed_seed: 0x1234,
+        gamemode: 0,
+        previous_gamemode: -1,
+        is_debug: false,
+        is_flat: false,
+        copy_metadata: true,
+        death_location: PrefixedOption(None),
+        portal_cooldown: VarInt::from(0),
+        data_kept: 0,
+    };
+    server.send(&respawn).await?;
+    let received = client.expect::<play::client_bound::Respawn>(0x3D).await?;
+    assert_packet_eq(&respawn, &received);
+
+    // From 1.20.2 the registries are sent during configuration and the world names follow the flags.
+    let protocol = MCProtocol::V1_20_2;
+    let (mut client, mut server) = connected_pair(protocol);
+    let join = join_game(Nbt::default(), Nbt::default(), "minecraft:overworld");
+    server.send(&join).await?;
+    let mut raw = client.receive_raw().await?;
+    assert_eq!(&raw.get_ref()[..7], &[0x29, 0, 0, 0, 7, 0, 1]);
+    VarInt::decode(&mut raw)?;
+    let received = play::client_bound::JoinGame::decode_mapped(protocol, &mut raw)?;
+    assert_packet_eq(&join, &received);
+    assert_eq!(received.gamemode, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn chat_components_follow_the_protocol_format() -> anyhow::Result<()> {
+    let (mut client, mut server) = connected_pair(MCProtocol::V1_18);
+    client
+        .send(&play::server_bound::ChatMessage {
+            message: play::ChatString::from("/spawn"),
+            timestamp: 0,
+            salt: 0,
+            legacy_signature: Default::default(),
+            signed_preview: false,
+            signature: PrefixedOption(None),
+            message_count: VarInt::from(0),
+            acknowledged: Default::default(),
+        })
+        .await?;
+    let received = server.expect::<play::server_bound::ChatMessage>(0x03).await?;
+    assert_eq!(received.message.to_string(), "/spawn");
+
+    let message = Component::from_legacy("§aWelcome §lback");
+    server
+        .send(&play::client_bound::ChatMessage {
+            message: message.clone(),
+            position: 1,
+            sender: uuid::Uuid::nil(),
+        })
+        .await?;
+    let received = client.expect::<play::client_bound::ChatMessage>(0x0F).await?;
+    assert_eq!(received.message, message);
+
+    // Components are NBT from 1.20.3, so the first byte after the id is a compound tag.
+    let protocol = MCProtocol::V1_20_3;
+    let (mut client, mut server) = connected_pair(protocol);
+    let content = Component::text("Saved").color(Color::Green);
+    server
+        .send(&play::client_bound::SystemChat {
+            content: content.clone(),
+            chat_type: VarInt::from(0),
+            overlay: true,
+        })
+        .await?;
+    let mut raw = client.receive_raw().await?;
+    assert_eq!(&raw.get_ref()[..2], &[0x69, 0x0A]);
+    VarInt::decode(&mut raw)?;
+    let received = play::client_bound::SystemChat::decode_mapped(protocol, &mut raw)?;
+    assert_eq!(received.content, content);
+    assert!(received.overlay);
+    Ok(())
+}
+
+#[tokio::test]
+async fn teams_reach_mixed_version_clients() -> anyhow::Result<()> {
+    let mut info = play::TeamInfo::new(Component::text("Red"));
+    info.color = Some(Color::Red);
+    info.prefix = Component::text("[R] ").color(Color::Red);
+    info.name_tag_visibility = play::NameTagVisibility::HideForOtherTeams;
+    info.collision_rule = play::CollisionRule::PushOwnTeam;
+    let teams = play::client_bound::Teams {
+        team_name: play::ScoreboardName::from("red"),
+        action: play::TeamAction::Create {
+            info: Box::new(info.clone()),
+            entities: Prefixed::from(vec![play::ScoreboardName::from("Alex")]),
+        },
+    };
+
+    let clients = [
+        (MCProtocol::V1_17_1, 0x55),
+        (MCProtocol::V1_20_2, 0x5C),
+        (MCProtocol::V1_20_5, 0x60),
+    ];
+    for (protocol, packet_id) in clients {
+        let (mut client, mut server) = connected_pair(protocol);
+        server.send(&teams).await?;
+        let received = client.expect::<play::client_bound::Teams>(packet_id).await?;
+        assert_packet_eq(&teams, &received);
+        match received.action {
+            play::TeamAction::Create { info: received, entities } => {
+                assert_eq!(*received, info);
+                assert_eq!(entities[0].to_string(), "Alex");
+            }
+            action => panic!("Expected a created team, found {:?}.", action),
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn keep_alive_service_measures_latency_and_times_out() -> anyhow::Result<()> {
+    let protocol = MCProtocol::V1_18;
+    let (mut client, server) = connected_pair(protocol);
+    let locker = Arc::new(PacketReadWriteLocker::new(
+        Arc::new(Mutex::new(server.writer)),
+        Arc::new(Mutex::new(server.reader)),
+    ));
+    let (inbound_sender, inbound) = flume::unbounded();
+    let (outbound, _read_handle, _write_handle) = spin(String::from("server"), locker, inbound_sender);
+    let (forward, forwarded) = flume::unbounded();
+    let config = KeepAliveConfig {
+        interval: Duration::from_millis(20),
+        timeout: Duration::from_millis(250),
+    };
+    let (handle, service) = spawn_keep_alive(protocol, config, outbound, inbound, forward);
+
+    let keep_alive = client.expect::<play::client_bound::KeepAlive>(0x21).await?;
+    client
+        .send(&play::server_bound::PluginMessage {
+            channel: Identifier::from("minecraft:brand"),
+            data: RemainingBytes::from(vec![1]),
+        })
+        .await?;
+    client
+        .send(&play::server_bound::KeepAlive {
+            keep_alive_id: keep_alive.keep_alive_id,
+        })
+        .await?;
+
+    // Other packets pass through, the echoed keep-alive does not.
+    let mut forwarded_packet = forwarded.recv_async().await?;
+    assert_eq!(*VarInt::decode(&mut forwarded_packet)?, 0x0A);
+    client.expect::<play::client_bound::KeepAlive>(0x21).await?;
+    assert!(handle.latency().is_some());
+
+    // Stop answering and wait for the disconnect.
+    let disconnect = client.expect::<play::client_bound::Disconnect>(0x1A).await?;
+    assert!(format!("{:?}", disconnect.reason).contains("disconnect.timeout"));
+    assert!(service.await?.is_err());
+    Ok(())
+}